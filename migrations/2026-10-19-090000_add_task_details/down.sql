@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE tasks
+    DROP COLUMN tags,
+    DROP COLUMN project,
+    DROP COLUMN priority,
+    DROP COLUMN recurrence;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE tasks
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN project VARCHAR,
+    ADD COLUMN priority Integer NOT NULL DEFAULT 0,
+    ADD COLUMN recurrence VARCHAR;
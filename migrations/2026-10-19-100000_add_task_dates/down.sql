@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE tasks
+    DROP COLUMN scheduled,
+    DROP COLUMN deadline,
+    DROP COLUMN all_day;
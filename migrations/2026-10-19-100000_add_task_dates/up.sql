@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE tasks
+    ADD COLUMN scheduled TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN deadline TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN all_day BOOLEAN NOT NULL DEFAULT FALSE;
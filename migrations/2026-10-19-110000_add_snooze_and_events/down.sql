@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE events;
+ALTER TABLE tasks DROP COLUMN snoozed_until;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE tasks ADD COLUMN snoozed_until TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE events (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR NOT NULL,
+    task_id VARCHAR NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX events_task_id_idx ON events (task_id);
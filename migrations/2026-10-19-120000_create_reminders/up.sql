@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE reminders (
+    id VARCHAR PRIMARY KEY,
+    task_id VARCHAR NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    remind_at TIMESTAMP WITH TIME ZONE,
+    minutes_before Integer,
+    fire_at TIMESTAMP WITH TIME ZONE,
+    fired_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX reminders_pending_idx ON reminders (fire_at) WHERE fired_at IS NULL;
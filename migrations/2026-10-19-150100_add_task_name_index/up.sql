@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- duplicate checks look tasks up by name
+CREATE INDEX IF NOT EXISTS tasks_name_idx ON tasks (name);
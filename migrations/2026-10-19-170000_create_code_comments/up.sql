@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE code_comments (
+    fingerprint VARCHAR PRIMARY KEY,
+    root VARCHAR NOT NULL,
+    task_id VARCHAR REFERENCES tasks (id) ON DELETE SET NULL,
+    path VARCHAR NOT NULL,
+    line Integer NOT NULL,
+    present BOOLEAN NOT NULL DEFAULT TRUE,
+    seen_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX code_comments_root_idx ON code_comments (root);
@@ -0,0 +1,211 @@
+mod output;
+
+use std::process::ExitCode;
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+
+use todo_api::client::{priority_of, status_of, Changes, Client};
+use todo_api::db::models::{Task, TaskStatus};
+use output::{details, table};
+
+#[derive(Debug, Parser)]
+#[command(name = "doit", about = "Manage tasks on a todo_api server")]
+struct Cli {
+    /// print JSON instead of tables
+    #[arg(long, global = true)]
+    json: bool,
+    /// time zone for due dates, DEFAULT_TZ or the server's when not given
+    #[arg(long, global = true)]
+    tz: Option<String>,
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// create a task
+    Add(AddArgs),
+    /// list open tasks, or those a filter finds
+    Ls {
+        filter: Vec<String>
+    },
+    /// find tasks: words, or :project:home, :tag:work, :due:today and the like
+    Search {
+        #[arg(required = true)]
+        filter: Vec<String>
+    },
+    /// show every field of a task
+    Show {
+        id: String
+    },
+    /// change fields of a task
+    Edit(EditArgs),
+    /// mark tasks done
+    Done {
+        #[arg(required = true)]
+        ids: Vec<String>
+    },
+    /// mark tasks deleted
+    Rm {
+        #[arg(required = true)]
+        ids: Vec<String>
+    }
+}
+
+#[derive(Debug, Args)]
+struct AddArgs {
+    #[arg(required = true)]
+    name: Vec<String>,
+    /// read the text like quick add, "pay rent friday #home !high"
+    #[arg(long, short)]
+    quick: bool,
+    #[arg(long, short)]
+    description: Option<String>,
+    /// a date or time like "2023-05-12", "friday 9:00" or "tomorrow"
+    #[arg(long)]
+    due: Option<String>,
+    #[arg(long = "tag", short)]
+    tags: Vec<String>,
+    #[arg(long, short)]
+    project: Option<String>,
+    /// none, low, medium or high
+    #[arg(long, value_parser = priority)]
+    priority: Option<i32>
+}
+
+#[derive(Debug, Args)]
+struct EditArgs {
+    id: String,
+    #[arg(long)]
+    name: Option<String>,
+    #[arg(long, short)]
+    description: Option<String>,
+    #[arg(long, conflicts_with = "no_due")]
+    due: Option<String>,
+    #[arg(long)]
+    no_due: bool,
+    /// replaces the tags, repeat for several
+    #[arg(long = "tag", short)]
+    tags: Vec<String>,
+    /// an empty project clears it
+    #[arg(long, short)]
+    project: Option<String>,
+    #[arg(long, value_parser = priority)]
+    priority: Option<i32>,
+    /// open, overdue, done or deleted
+    #[arg(long, value_parser = status)]
+    status: Option<i32>
+}
+
+fn priority(name: &str) -> Result<i32, String> {
+    priority_of(name).ok_or_else(|| format!("Unknown priority {name}, use none, low, medium or high"))
+}
+
+fn status(name: &str) -> Result<i32, String> {
+    status_of(name).ok_or_else(|| format!("Unknown status {name}, use open, overdue, done or deleted"))
+}
+
+impl EditArgs {
+    fn changes(self) -> Changes {
+        Changes {
+            name: self.name,
+            description: self.description,
+            due: match (self.due, self.no_due) {
+                (_, true) => Some(None),
+                (Some(due), _) => Some(Some(due)),
+                (None, false) => None
+            },
+            tags: Some(self.tags).filter(|t| !t.is_empty()),
+            project: self.project,
+            priority: self.priority,
+            status: self.status
+        }
+    }
+}
+
+fn print<T: Serialize>(value: &T, json: bool, text: impl FnOnce() -> String) {
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(value).unwrap_or_default()),
+        false => println!("{}", text())
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let tz_name = cli.tz.or_else(|| std::env::var("DEFAULT_TZ").ok()).filter(|t| !t.trim().is_empty());
+    let tz = match &tz_name {
+        Some(name) => name.trim().parse::<Tz>().map_err(|_| format!("Unknown time zone {name}"))?,
+        None => Tz::UTC
+    };
+    let client = Client::from_env(tz_name);
+    let json = cli.json;
+    let list = |tasks: Vec<Task>| print(&tasks, json, || table(&tasks, &tz));
+    match cli.command {
+        Command::Add(args) => {
+            let name = args.name.join(" ");
+            let task = match args.quick {
+                true => client.quick(&name)?,
+                false => client.create(json!({
+                    "name": name,
+                    "description": args.description,
+                    "due": args.due,
+                    "tags": args.tags,
+                    "project": args.project,
+                    "priority": args.priority.unwrap_or(0)
+                }))?
+            };
+            print(&task, json, || details(&task, &tz));
+        },
+        Command::Ls { filter } if filter.is_empty() => list(client.list()?),
+        Command::Ls { filter } | Command::Search { filter } => list(client.search(&filter.join(" "))?),
+        Command::Show { id } => {
+            let task = client.resolve(&id)?;
+            print(&task, json, || details(&task, &tz));
+        },
+        Command::Edit(args) => {
+            let task = client.resolve(&args.id)?;
+            let task = client.update(&task, args.changes())?;
+            print(&task, json, || details(&task, &tz));
+        },
+        Command::Done { ids } => list(set_status(&client, &ids, TaskStatus::Done.to_store())?),
+        Command::Rm { ids } => list(set_status(&client, &ids, TaskStatus::Deleted.to_store())?)
+    }
+    Ok(())
+}
+
+fn set_status(client: &Client, ids: &[String], status: i32) -> Result<Vec<Task>, String> {
+    ids.iter().map(|id| client.set_status(&client.resolve(id)?, status)).collect()
+}
+
+fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("doit: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_args() {
+        let cli = Cli::parse_from(["doit", "edit", "5d1e", "--no-due", "-t", "home", "-t", "work", "--status", "done"]);
+        let Command::Edit(args) = cli.command else { panic!("not an edit") };
+        let changes = args.changes();
+        assert_eq!(changes.due, Some(None));
+        assert_eq!(changes.tags, Some(vec!["home".to_string(), "work".to_string()]));
+        assert_eq!(changes.status, Some(2));
+        assert_eq!(changes.name, None);
+        assert!(Cli::try_parse_from(["doit", "edit", "5d1e", "--priority", "urgent"]).is_err());
+        assert!(Cli::try_parse_from(["doit", "edit", "5d1e", "--due", "friday", "--no-due"]).is_err());
+        let cli = Cli::parse_from(["doit", "--json", "search", ":project:home", "rent"]);
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::Search { filter } if filter.join(" ") == ":project:home rent"));
+    }
+}
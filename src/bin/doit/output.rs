@@ -0,0 +1,89 @@
+use chrono_tz::Tz;
+use comfy_table::{presets::UTF8_HORIZONTAL_ONLY, Table};
+
+use todo_api::client::{date, priority_name, status_name, SHORT_ID};
+use todo_api::db::models::Task;
+
+pub fn table(tasks: &[Task], tz: &Tz) -> String {
+    let mut table = Table::new();
+    table.load_preset(UTF8_HORIZONTAL_ONLY);
+    table.set_header(vec!["id", "status", "priority", "due", "project", "tags", "name"]);
+    for task in tasks {
+        table.add_row(vec![
+            task.id.chars().take(SHORT_ID).collect::<String>(),
+            status_name(task.status),
+            match task.priority {
+                0 => String::new(),
+                p => priority_name(p)
+            },
+            date(task.due, task.all_day, tz),
+            task.project.clone().unwrap_or_default(),
+            task.tags.join(", "),
+            task.name.clone()
+        ]);
+    }
+    table.to_string()
+}
+
+//every field that is set, one per line, the description last
+pub fn details(task: &Task, tz: &Tz) -> String {
+    let mut lines = vec![
+        format!("id:        {}", task.id),
+        format!("name:      {}", task.name),
+        format!("status:    {}", status_name(task.status)),
+        format!("priority:  {}", priority_name(task.priority))
+    ];
+    let dates = [("due", task.due), ("scheduled", task.scheduled), ("deadline", task.deadline), ("snoozed", task.snoozed_until)];
+    for (label, at) in dates.into_iter().filter(|(_, at)| at.is_some()) {
+        lines.push(format!("{:<11}{}", format!("{label}:"), date(at, task.all_day, tz)));
+    }
+    if let Some(project) = &task.project {
+        lines.push(format!("project:   {project}"));
+    }
+    if !task.tags.is_empty() {
+        lines.push(format!("tags:      {}", task.tags.join(", ")));
+    }
+    if let Some(recurrence) = &task.recurrence {
+        lines.push(format!("repeats:   {recurrence}"));
+    }
+    lines.push(format!("created:   {}", date(Some(task.created_at), false, tz)));
+    lines.push(format!("updated:   {} (version {})", date(Some(task.updated_at), false, tz), task.version));
+    if !task.description.trim().is_empty() {
+        lines.push(String::new());
+        lines.push(task.description.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn task() -> Task {
+        let at = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let mut task = Task::new("Pay rent", Some("transfer"), Some(Utc.with_ymd_and_hms(2023, 5, 10, 7, 0, 0).unwrap()));
+        task.id = "5d1e6b38-3c1c-4bf6-9a4e-6b1f9e4a1c01".to_string();
+        task.status = 0;
+        task.tags = vec!["finance".to_string(), "home".to_string()];
+        task.project = Some("home".to_string());
+        task.priority = 3;
+        task.created_at = at;
+        task.updated_at = at;
+        task.version = 2;
+        task
+    }
+
+    #[test]
+    fn test_table_and_details() {
+        let text = table(&[task()], &Tz::Europe__Vienna);
+        let row = text.lines().find(|l| l.contains("Pay rent")).unwrap();
+        for cell in ["5d1e6b38 ", "overdue", "high", "2023-05-10 09:00", "finance, home"] {
+            assert!(row.contains(cell), "{row}");
+        }
+        assert!(!row.contains("5d1e6b38-"));
+        let text = details(&task(), &Tz::UTC);
+        assert!(text.contains("due:       2023-05-10 07:00\n"));
+        assert!(text.ends_with("(version 2)\n\ntransfer"));
+    }
+}
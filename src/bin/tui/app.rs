@@ -0,0 +1,310 @@
+use chrono_tz::Tz;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use todo_api::client::{date, priority_name, priority_of, status_name, Changes};
+use todo_api::db::models::{Task, TaskStatus};
+use crate::backend::Backend;
+
+pub const FIELDS: [&str; 6] = ["name", "due", "tags", "project", "priority", "description"];
+const DUE: usize = 1;
+
+//the edit pane: the task as it was read and the text of each field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Form {
+    pub task: Task,
+    pub values: [String; 6],
+    pub focus: usize
+}
+
+impl Form {
+    pub fn new(task: Task, focus: usize, tz: &Tz) -> Self {
+        let values = [
+            task.name.clone(),
+            date(task.due, task.all_day, tz),
+            task.tags.join(", "),
+            task.project.clone().unwrap_or_default(),
+            priority_name(task.priority),
+            task.description.clone()
+        ];
+        Self { task, values, focus }
+    }
+
+    //only the fields that were edited, so a refresh elsewhere is not overwritten
+    pub fn changes(&self, tz: &Tz) -> Result<Changes, String> {
+        let before = Form::new(self.task.clone(), 0, tz).values;
+        let edited = |i: usize| Some(self.values[i].trim().to_string()).filter(|_| self.values[i].trim() != before[i].trim());
+        let name = edited(0);
+        if name.as_deref() == Some("") {
+            return Err("Task name is missing".to_string())
+        }
+        let priority = match edited(4) {
+            Some(text) => Some(priority_of(&text).ok_or_else(|| format!("Unknown priority {text}, use none, low, medium or high"))?),
+            None => None
+        };
+        Ok(Changes {
+            name,
+            description: edited(5),
+            due: edited(DUE).map(|d| Some(d).filter(|d| !d.is_empty())),
+            tags: edited(2).map(|t| t.split([',', ' ']).filter(|t| !t.is_empty()).map(String::from).collect()),
+            project: edited(3),
+            priority,
+            status: None
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    List,
+    Search,
+    Edit(Box<Form>)
+}
+
+pub struct App {
+    pub tasks: Vec<Task>,
+    pub selected: usize,
+    pub filter: String,
+    pub mode: Mode,
+    pub message: Option<String>,
+    pub quit: bool,
+    pub tz: Tz
+}
+
+impl App {
+    pub fn new(tz: Tz) -> Self {
+        Self { tasks: Vec::new(), selected: 0, filter: String::new(), mode: Mode::List, message: None, quit: false, tz }
+    }
+
+    pub fn current(&self) -> Option<&Task> {
+        self.tasks.get(self.selected)
+    }
+
+    //keeps the selected task selected when it is still listed
+    pub fn reload(&mut self, backend: &mut dyn Backend) {
+        let selected = self.current().map(|t| t.id.clone());
+        match backend.tasks(&self.filter) {
+            Ok(tasks) => {
+                self.tasks = tasks;
+                self.selected = selected
+                    .and_then(|id| self.tasks.iter().position(|t| t.id == id))
+                    .unwrap_or(self.selected.min(self.tasks.len().saturating_sub(1)));
+            },
+            Err(err) => self.message = Some(err)
+        }
+    }
+
+    fn replace(&mut self, task: Task) {
+        if let Some(listed) = self.tasks.iter_mut().find(|t| t.id == task.id) {
+            *listed = task;
+        }
+    }
+
+    fn set_status(&mut self, status: TaskStatus, backend: &mut dyn Backend) {
+        let Some(task) = self.current().cloned() else { return };
+        match backend.set_status(&task, status.to_store()) {
+            Ok(task) => {
+                self.message = Some(format!("{} is {}", task.name, status_name(task.status)));
+                self.replace(task);
+            },
+            Err(err) => self.message = Some(err)
+        }
+    }
+
+    pub fn key(&mut self, key: KeyEvent, backend: &mut dyn Backend) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return
+        }
+        match self.mode.clone() {
+            Mode::List => self.list_key(key, backend),
+            Mode::Search => self.search_key(key, backend),
+            Mode::Edit(form) => self.edit_key(form, key, backend)
+        }
+    }
+
+    fn list_key(&mut self, key: KeyEvent, backend: &mut dyn Backend) {
+        self.message = None;
+        let last = self.tasks.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = last,
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.reload(backend);
+            },
+            KeyCode::Char('r') => self.reload(backend),
+            KeyCode::Char(' ') | KeyCode::Char('x') => {
+                let done = self.current().is_some_and(|t| t.status == TaskStatus::Done.to_store());
+                self.set_status(if done { TaskStatus::Created } else { TaskStatus::Done }, backend);
+            },
+            KeyCode::Char('D') | KeyCode::Delete => self.set_status(TaskStatus::Deleted, backend),
+            KeyCode::Char('e') | KeyCode::Enter => self.edit(0),
+            KeyCode::Char('t') => self.edit(DUE),
+            _ => ()
+        }
+    }
+
+    fn edit(&mut self, focus: usize) {
+        if let Some(task) = self.current().cloned() {
+            self.mode = Mode::Edit(Box::new(Form::new(task, focus, &self.tz)));
+        }
+    }
+
+    //every keystroke runs the filter again
+    fn search_key(&mut self, key: KeyEvent, backend: &mut dyn Backend) {
+        match key.code {
+            KeyCode::Enter => self.mode = Mode::List,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.mode = Mode::List;
+                self.reload(backend);
+            },
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.reload(backend);
+            },
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.reload(backend);
+            },
+            _ => ()
+        }
+    }
+
+    fn edit_key(&mut self, mut form: Box<Form>, key: KeyEvent, backend: &mut dyn Backend) {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::List;
+                return
+            },
+            KeyCode::Enter => {
+                let saved = form.changes(&self.tz).and_then(|changes| backend.update(&form.task, changes));
+                match saved {
+                    Ok(task) => {
+                        self.message = Some(format!("Saved {}", task.name));
+                        self.replace(task);
+                        self.mode = Mode::List;
+                        self.reload(backend);
+                    },
+                    Err(err) => {
+                        self.message = Some(err);
+                        self.mode = Mode::Edit(form);
+                    }
+                }
+                return
+            },
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => form.focus = (form.focus + FIELDS.len() - 1) % FIELDS.len(),
+            KeyCode::Backspace => {
+                form.values[form.focus].pop();
+            },
+            KeyCode::Char(c) => form.values[form.focus].push(c),
+            _ => ()
+        }
+        self.mode = Mode::Edit(form);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use ratatui::crossterm::event::KeyEvent;
+
+    //tasks in memory, the filter matches names
+    struct Memory {
+        tasks: Vec<Task>,
+        updates: Vec<Changes>
+    }
+
+    impl Backend for Memory {
+        fn tasks(&mut self, filter: &str) -> Result<Vec<Task>, String> {
+            Ok(self.tasks.iter().filter(|t| t.name.contains(filter.trim())).cloned().collect())
+        }
+
+        fn set_status(&mut self, task: &Task, status: i32) -> Result<Task, String> {
+            let stored = self.tasks.iter_mut().find(|t| t.id == task.id).ok_or("gone")?;
+            stored.status = status;
+            Ok(stored.clone())
+        }
+
+        fn update(&mut self, task: &Task, changes: Changes) -> Result<Task, String> {
+            self.updates.push(changes.clone());
+            let stored = self.tasks.iter_mut().find(|t| t.id == task.id).ok_or("gone")?;
+            stored.name = changes.name.unwrap_or(stored.name.clone());
+            Ok(stored.clone())
+        }
+
+        fn changed(&mut self) -> bool {
+            false
+        }
+    }
+
+    fn press(app: &mut App, keys: &str, backend: &mut Memory) {
+        for c in keys.chars() {
+            app.key(KeyEvent::from(KeyCode::Char(c)), backend);
+        }
+    }
+
+    fn setup() -> (App, Memory) {
+        let mut rent = Task::new("Pay rent", None, Some(Utc.with_ymd_and_hms(2023, 5, 10, 7, 0, 0).unwrap()));
+        rent.tags = vec!["home".to_string()];
+        let mut backend = Memory { tasks: vec![rent, Task::new("Water plants", None, None)], updates: Vec::new() };
+        let mut app = App::new(Tz::UTC);
+        app.reload(&mut backend);
+        (app, backend)
+    }
+
+    #[test]
+    fn test_search_and_status() {
+        let (mut app, mut backend) = setup();
+        assert_eq!(app.tasks.len(), 2);
+        press(&mut app, "j/Water", &mut backend);
+        assert_eq!(app.mode, Mode::Search);
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.current().unwrap().name, "Water plants");
+        app.key(KeyEvent::from(KeyCode::Enter), &mut backend);
+        press(&mut app, "x", &mut backend);
+        assert_eq!(app.current().unwrap().status, TaskStatus::Done.to_store());
+        press(&mut app, "x", &mut backend);
+        assert_eq!(app.current().unwrap().status, TaskStatus::Created.to_store());
+        app.key(KeyEvent::from(KeyCode::Esc), &mut backend);
+        assert_eq!((app.tasks.len(), app.current().unwrap().name.as_str()), (2, "Water plants"));
+        press(&mut app, "q", &mut backend);
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_edit() {
+        let (mut app, mut backend) = setup();
+        press(&mut app, "t", &mut backend);
+        let Mode::Edit(form) = &app.mode else { panic!("not editing") };
+        assert_eq!((form.focus, form.values[DUE].as_str()), (DUE, "2023-05-10 07:00"));
+        for _ in 0..16 {
+            app.key(KeyEvent::from(KeyCode::Backspace), &mut backend);
+        }
+        press(&mut app, "friday", &mut backend);
+        app.key(KeyEvent::from(KeyCode::BackTab), &mut backend);
+        press(&mut app, " now", &mut backend);
+        app.key(KeyEvent::from(KeyCode::Enter), &mut backend);
+        assert_eq!(app.mode, Mode::List);
+        let changes = backend.updates.pop().unwrap();
+        assert_eq!(changes.due, Some(Some("friday".to_string())));
+        assert_eq!(changes.name.as_deref(), Some("Pay rent now"));
+        assert_eq!((changes.tags, changes.project, changes.priority), (None, None, None));
+        assert_eq!(app.current().unwrap().name, "Pay rent now");
+
+        press(&mut app, "e", &mut backend);
+        for _ in 0..4 {
+            app.key(KeyEvent::from(KeyCode::Tab), &mut backend);
+        }
+        press(&mut app, "x", &mut backend);
+        app.key(KeyEvent::from(KeyCode::Enter), &mut backend);
+        assert!(matches!(app.mode, Mode::Edit(_)));
+        assert!(app.message.as_deref().unwrap().starts_with("Unknown priority nonex"));
+    }
+}
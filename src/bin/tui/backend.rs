@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+use chrono_tz::Tz;
+use diesel::{PgConnection, r2d2::{ConnectionManager, PooledConnection}};
+
+use todo_api::client::{Changes, Client};
+use todo_api::db::DbPool;
+use todo_api::db::models::{Task, TaskError, event::Event};
+use todo_api::services::task::TaskUpdate;
+use todo_api::utils::date::DueInput;
+
+//where the tasks come from, the REST API or the database the server uses
+pub trait Backend {
+    //open tasks, or what `filter` finds with the syntax of /filter
+    fn tasks(&mut self, filter: &str) -> Result<Vec<Task>, String>;
+    fn set_status(&mut self, task: &Task, status: i32) -> Result<Task, String>;
+    fn update(&mut self, task: &Task, changes: Changes) -> Result<Task, String>;
+    //whether tasks may have changed since the last call
+    fn changed(&mut self) -> bool;
+}
+
+pub struct Rest {
+    pub client: Client
+}
+
+//the API sends no notice of changes here, the list is polled
+impl Backend for Rest {
+    fn tasks(&mut self, filter: &str) -> Result<Vec<Task>, String> {
+        match filter.trim() {
+            "" => self.client.list(),
+            filter => self.client.search(filter)
+        }
+    }
+
+    fn set_status(&mut self, task: &Task, status: i32) -> Result<Task, String> {
+        self.client.set_status(task, status)
+    }
+
+    fn update(&mut self, task: &Task, changes: Changes) -> Result<Task, String> {
+        self.client.update(task, changes)
+    }
+
+    fn changed(&mut self) -> bool {
+        true
+    }
+}
+
+//tasks turn overdue without an event, a list this old is loaded again anyway
+const STALE: Duration = Duration::from_secs(60);
+
+//goes through the same model code as the server, so events, webhooks and reminders
+//follow changes made here. the events table tells when to reload
+pub struct Local {
+    pool: DbPool,
+    tz: Tz,
+    last_event: i64,
+    loaded_at: Option<Instant>
+}
+
+impl Local {
+    pub fn new(pool: DbPool, tz: Tz) -> Self {
+        Self { pool, tz, last_event: -1, loaded_at: None }
+    }
+
+    fn conn(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, String> {
+        self.pool.get().map_err(|e| e.to_string())
+    }
+}
+
+fn task_error(err: TaskError) -> String {
+    match err {
+        TaskError::InvalidDue => "Could not parse due date".to_string(),
+        TaskError::NotFound => "The task is gone".to_string(),
+        TaskError::Conflict(_) => "The task changed meanwhile, try again".to_string(),
+        TaskError::Duplicate(_) => "A task with that name exists".to_string(),
+        TaskError::Database => "Could not store the task".to_string()
+    }
+}
+
+impl Backend for Local {
+    fn tasks(&mut self, filter: &str) -> Result<Vec<Task>, String> {
+        let mut conn = self.conn()?;
+        Ok(match filter.trim() {
+            "" => Task::list(&mut conn),
+            filter => Task::filter(filter, &self.tz, &mut conn)
+        })
+    }
+
+    fn set_status(&mut self, task: &Task, status: i32) -> Result<Task, String> {
+        Task::set_status(&task.id, status, Some(task.version), &mut *self.conn()?).map_err(task_error)
+    }
+
+    fn update(&mut self, task: &Task, changes: Changes) -> Result<Task, String> {
+        let due = match changes.due {
+            Some(due) => due,
+            None => task.due.map(|d| d.to_rfc3339())
+        };
+        let update = TaskUpdate {
+            id: task.id.clone(),
+            name: changes.name.unwrap_or(task.name.clone()),
+            description: changes.description.unwrap_or(task.description.clone()),
+            status: changes.status.unwrap_or(task.status),
+            due: due.map(DueInput::Text),
+            tags: changes.tags,
+            project: changes.project,
+            priority: changes.priority,
+            recurrence: None,
+            scheduled: None,
+            deadline: None,
+            all_day: None,
+            version: Some(task.version),
+            created_at: task.created_at,
+            updated_at: task.updated_at
+        };
+        Task::update(update, &self.tz, &mut *self.conn()?).map_err(task_error)
+    }
+
+    fn changed(&mut self) -> bool {
+        let Ok(mut conn) = self.conn() else { return false };
+        let latest = Event::latest_id(&mut conn);
+        let stale = self.loaded_at.is_none_or(|at| at.elapsed() >= STALE);
+        if latest == self.last_event && !stale {
+            return false
+        }
+        self.last_event = latest;
+        self.loaded_at = Some(Instant::now());
+        true
+    }
+}
@@ -0,0 +1,85 @@
+mod app;
+mod backend;
+mod ui;
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use chrono_tz::Tz;
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+
+use app::App;
+use backend::{Backend, Local, Rest};
+use todo_api::client::Client;
+
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Parser)]
+#[command(name = "doit-tui", about = "Browse and edit tasks in the terminal")]
+struct Cli {
+    /// use DATABASE_URL directly instead of the server at REST_HOST and REST_PORT
+    #[arg(long)]
+    local: bool,
+    /// time zone for due dates, DEFAULT_TZ when not given
+    #[arg(long)]
+    tz: Option<String>,
+    /// seconds between looking for changes made elsewhere
+    #[arg(long, default_value_t = 2)]
+    refresh: u64
+}
+
+//redraws after every key and reloads when the backend has seen changes, except while
+//a task is being edited
+fn run(app: &mut App, backend: &mut dyn Backend, refresh: Duration) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    app.reload(backend);
+    let mut checked = Instant::now();
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.key(key, backend);
+                }
+            }
+        }
+        let editing = matches!(app.mode, app::Mode::Edit(_));
+        if !editing && checked.elapsed() >= refresh {
+            checked = Instant::now();
+            if backend.changed() {
+                app.reload(backend);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+    let cli = Cli::parse();
+    let tz_name = cli.tz.or_else(|| std::env::var("DEFAULT_TZ").ok()).filter(|t| !t.trim().is_empty());
+    let tz = match &tz_name {
+        Some(name) => match name.trim().parse::<Tz>() {
+            Ok(tz) => tz,
+            Err(_) => {
+                eprintln!("doit-tui: Unknown time zone {name}");
+                return ExitCode::FAILURE
+            }
+        },
+        None => Tz::UTC
+    };
+    let mut backend: Box<dyn Backend> = match cli.local {
+        true => Box::new(Local::new(todo_api::db::establish_connection(), tz)),
+        false => Box::new(Rest { client: Client::from_env(tz_name) })
+    };
+    let mut app = App::new(tz);
+    let result = run(&mut app, backend.as_mut(), Duration::from_secs(cli.refresh.max(1)));
+    ratatui::restore();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("doit-tui: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+
+use todo_api::client::{date, priority_name, status_name};
+use todo_api::db::models::{Task, TaskStatus};
+use crate::app::{App, Form, Mode, FIELDS};
+
+const LIST_HELP: &str = "j/k move  / search  x done  D delete  e edit  t due  r refresh  q quit";
+const SEARCH_HELP: &str = "type to filter, :project:home, :due:today  enter keep  esc clear";
+const EDIT_HELP: &str = "tab next field  enter save  esc cancel";
+
+//search line on top, the list next to the detail or edit pane, messages and keys below
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [search, body, footer] = Layout::vertical([Constraint::Length(3), Constraint::Min(3), Constraint::Length(1)])
+        .areas(frame.area());
+    let [list, side] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(body);
+
+    let searching = app.mode == Mode::Search;
+    let cursor = if searching { "█" } else { "" };
+    frame.render_widget(
+        Paragraph::new(format!("{}{cursor}", app.filter)).block(titled("Search", searching)),
+        search
+    );
+    draw_list(frame, app, list);
+    match &app.mode {
+        Mode::Edit(form) => draw_form(frame, form, side),
+        _ => draw_details(frame, app, side)
+    }
+    let help = match app.mode {
+        Mode::List => LIST_HELP,
+        Mode::Search => SEARCH_HELP,
+        Mode::Edit(_) => EDIT_HELP
+    };
+    let footer_text = match &app.message {
+        Some(message) => Line::from(vec![Span::styled(message.as_str(), Style::default().fg(Color::Yellow)), Span::raw(format!("  {help}"))]),
+        None => Line::from(help)
+    };
+    frame.render_widget(Paragraph::new(footer_text), footer);
+}
+
+fn titled(title: &str, focused: bool) -> Block<'_> {
+    let style = match focused {
+        true => Style::default().fg(Color::Cyan),
+        false => Style::default()
+    };
+    Block::default().borders(Borders::ALL).title(title).border_style(style)
+}
+
+fn status_style(task: &Task) -> Style {
+    match TaskStatus::from_store(task.status) {
+        Some(TaskStatus::Overdue) => Style::default().fg(Color::Red),
+        Some(TaskStatus::Done) | Some(TaskStatus::Deleted) => Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT),
+        _ => Style::default()
+    }
+}
+
+fn draw_list(frame: &mut Frame, app: &App, area: Rect) {
+    let items = app.tasks.iter().map(|task| {
+        let mark = if task.status == TaskStatus::Done.to_store() { "[x]" } else { "[ ]" };
+        let due = date(task.due, task.all_day, &app.tz);
+        ListItem::new(Line::from(vec![
+            Span::raw(format!("{mark} ")),
+            Span::styled(format!("{due:<16} "), Style::default().fg(Color::Blue)),
+            Span::styled(task.name.clone(), status_style(task))
+        ]))
+    });
+    let title = format!("Tasks ({})", app.tasks.len());
+    let list = List::new(items)
+        .block(titled(&title, app.mode == Mode::List))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(app.selected).filter(|_| !app.tasks.is_empty()));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_details(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(task) = app.current() else {
+        return frame.render_widget(Paragraph::new("No tasks").block(titled("Details", false)), area)
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(task.name.clone(), Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(format!("status:   {}", status_name(task.status))),
+        Line::from(format!("priority: {}", priority_name(task.priority)))
+    ];
+    let dates = [("due", task.due), ("scheduled", task.scheduled), ("deadline", task.deadline), ("snoozed", task.snoozed_until)];
+    for (label, at) in dates.into_iter().filter(|(_, at)| at.is_some()) {
+        lines.push(Line::from(format!("{:<10}{}", format!("{label}:"), date(at, task.all_day, &app.tz))));
+    }
+    if let Some(project) = &task.project {
+        lines.push(Line::from(format!("project:  {project}")));
+    }
+    if !task.tags.is_empty() {
+        lines.push(Line::from(format!("tags:     {}", task.tags.join(", "))));
+    }
+    lines.push(Line::from(format!("version:  {}", task.version)));
+    if !task.description.trim().is_empty() {
+        lines.push(Line::from(""));
+        lines.extend(task.description.lines().map(|l| Line::from(l.to_string())));
+    }
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(titled("Details", false)), area);
+}
+
+fn draw_form(frame: &mut Frame, form: &Form, area: Rect) {
+    let mut lines = Vec::new();
+    for (index, (label, value)) in FIELDS.iter().zip(form.values.iter()).enumerate() {
+        let focused = index == form.focus;
+        let style = match focused {
+            true => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            false => Style::default()
+        };
+        let cursor = if focused { "█" } else { "" };
+        let mut value_lines = value.split('\n');
+        lines.push(Line::from(vec![
+            Span::styled(format!("{label:<12}"), style),
+            Span::raw(value_lines.next().unwrap_or_default().to_string())
+        ]));
+        lines.extend(value_lines.map(|l| Line::from(format!("{:<12}{l}", ""))));
+        if let Some(last) = lines.last_mut() {
+            last.push_span(Span::styled(cursor, style));
+        }
+    }
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(titled("Edit", true)), area);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono_tz::Tz;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content.chunks(buffer.area.width as usize).map(|row| row.iter().map(|c| c.symbol()).collect::<String>()).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = App::new(Tz::UTC);
+        assert!(screen(&app).contains("No tasks"));
+        let mut task = Task::new("Pay rent", Some("transfer"), None);
+        task.project = Some("home".to_string());
+        app.tasks = vec![task.clone(), Task::new("Water plants", None, None)];
+        let text = screen(&app);
+        assert!(text.contains("Tasks (2)"));
+        assert!(text.contains("[ ] ") && text.contains("Water plants"));
+        assert!(text.contains("project:  home"));
+        app.mode = Mode::Edit(Box::new(Form::new(task, 1, &app.tz)));
+        let text = screen(&app);
+        assert!(text.contains("Edit") && text.contains("description transfer"));
+        assert!(text.contains(EDIT_HELP));
+    }
+}
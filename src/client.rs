@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+use crate::utils::dav::encode;
+
+const HOST: &str = "127.0.0.1";
+const PORT: u16 = 8080;
+//ids shown in tables, and the shortest prefix looked up
+pub const SHORT_ID: usize = 8;
+const MIN_PREFIX: usize = 4;
+//the stored number of a status name, the web client calls created open
+pub fn status_of(name: &str) -> Option<i32> {
+    match name.trim().to_lowercase().as_str() {
+        "open" => Some(TaskStatus::Created.to_store()),
+        name => TaskStatus::from_str(name).map(|s| s.to_store())
+    }
+}
+
+pub fn priority_of(name: &str) -> Option<i32> {
+    TaskPriority::from_str(name.trim()).map(|p| p.to_store())
+}
+
+pub fn status_name(status: i32) -> String {
+    match TaskStatus::from_store(status) {
+        Some(TaskStatus::Created) => "open".to_string(),
+        Some(status) => status.to_string(),
+        None => "?".to_string()
+    }
+}
+
+pub fn priority_name(priority: i32) -> String {
+    TaskPriority::from_store(priority).map_or("?".to_string(), |p| p.to_string())
+}
+
+//in `tz`, without the time for all day dates
+pub fn date(at: Option<DateTime<Utc>>, all_day: bool, tz: &Tz) -> String {
+    match at.map(|a| a.with_timezone(tz)) {
+        Some(local) if all_day => local.format("%Y-%m-%d").to_string(),
+        Some(local) => local.format("%Y-%m-%d %H:%M").to_string(),
+        None => String::new()
+    }
+}
+
+//what `edit` changes, None keeps the field
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    //Some(None) clears the due date
+    pub due: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+    pub project: Option<String>,
+    pub priority: Option<i32>,
+    pub status: Option<i32>
+}
+
+//talks to the server in src/services/task.rs, found through REST_HOST and REST_PORT
+//like the server itself. due dates are read in `tz` when one is given
+pub struct Client {
+    base: String,
+    tz: Option<String>
+}
+
+impl Client {
+    pub fn from_env(tz: Option<String>) -> Self {
+        let host = std::env::var("REST_HOST").unwrap_or(HOST.to_string());
+        let port = std::env::var("REST_PORT")
+            .ok()
+            .and_then(|p| p.trim().parse::<u16>().ok())
+            .unwrap_or(PORT);
+        Self { base: format!("http://{host}:{port}"), tz }
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = ureq::request(method, &format!("{}{path}", self.base));
+        match &self.tz {
+            Some(tz) => request.set("X-Timezone", tz),
+            None => request
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<Task>, String> {
+        read(self.request("GET", "/").call())
+    }
+
+    //the same syntax as /filter: words, or :project:home, :due:today and the like
+    pub fn search(&self, term: &str) -> Result<Vec<Task>, String> {
+        match self.request("GET", "/filter").query("term", term).call() {
+            Err(ureq::Error::Status(404, _)) => Ok(Vec::new()),
+            response => read(response)
+        }
+    }
+
+    //a full id, or a prefix of at least four characters that only one listed task starts with
+    pub fn resolve(&self, id: &str) -> Result<Task, String> {
+        match self.request("GET", &format!("/{}", encode(id))).call() {
+            Err(ureq::Error::Status(404, _)) if id.len() >= MIN_PREFIX => (),
+            response => return read(response)
+        }
+        let tasks = self.list()?;
+        let mut found = tasks.into_iter().filter(|t| t.id.starts_with(id));
+        match (found.next(), found.next()) {
+            (Some(task), None) => Ok(task),
+            (Some(_), Some(_)) => Err(format!("{id} is the start of several task ids")),
+            (None, _) => Err(format!("No task {id}"))
+        }
+    }
+
+    pub fn create(&self, form: Value) -> Result<Task, String> {
+        read(self.request("POST", "/create").send_json(form))
+    }
+
+    //parsed like quick add in the web client: "pay rent friday #home !high"
+    pub fn quick(&self, text: &str) -> Result<Task, String> {
+        let result: Value = read(self.request("POST", "/quick").send_json(json!({"text": text})))?;
+        serde_json::from_value(result["task"].clone()).map_err(|e| e.to_string())
+    }
+
+    //refused with a message when the task changed since it was read
+    pub fn update(&self, task: &Task, changes: Changes) -> Result<Task, String> {
+        let due = match changes.due {
+            Some(due) => due,
+            None => task.due.map(|d| d.to_rfc3339())
+        };
+        let form = json!({
+            "id": task.id,
+            "name": changes.name.unwrap_or(task.name.clone()),
+            "description": changes.description.unwrap_or(task.description.clone()),
+            "status": changes.status.unwrap_or(task.status),
+            "due": due,
+            "tags": changes.tags.unwrap_or(task.tags.clone()),
+            "project": changes.project.or(task.project.clone()),
+            "priority": changes.priority.unwrap_or(task.priority),
+            "version": task.version,
+            "created_at": task.created_at,
+            "updated_at": task.updated_at
+        });
+        read(self.request("PUT", "/").set("If-Match", &format!("\"{}\"", task.version)).send_json(form))
+    }
+
+    pub fn set_status(&self, task: &Task, status: i32) -> Result<Task, String> {
+        read(self.request("GET", &format!("/set/{}/{status}", encode(&task.id)))
+            .set("If-Match", &format!("\"{}\"", task.version))
+            .call())
+    }
+}
+
+//the body of a 2xx response, or the message the server sent with an error
+fn read<T: serde::de::DeserializeOwned>(response: Result<ureq::Response, ureq::Error>) -> Result<T, String> {
+    match response {
+        Ok(response) => response.into_json::<T>().map_err(|e| e.to_string()),
+        Err(ureq::Error::Status(412, _)) => Err("The task changed meanwhile, try again".to_string()),
+        Err(ureq::Error::Status(code, response)) => {
+            let message = response.into_json::<Value>().ok().and_then(|v| v.as_str().map(String::from));
+            Err(message.unwrap_or_else(|| format!("Server answered {code}")))
+        },
+        Err(err) => Err(format!("Could not reach the server: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(status_of("Done"), Some(2));
+        assert_eq!(status_of("created"), Some(1));
+        assert_eq!(status_of("doing"), None);
+        assert_eq!(priority_of("HIGH"), Some(3));
+        assert_eq!(status_of("open"), Some(1));
+        assert_eq!(status_of("overdue"), Some(0));
+        assert_eq!(status_name(0), "overdue");
+        assert_eq!(status_name(1), "open");
+        assert_eq!(priority_of("urgent"), None);
+    }
+}
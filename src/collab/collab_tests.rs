@@ -0,0 +1,95 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+use serial_test::serial;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::db::{establish_connection, models::{Task, TaskStatus, event::Event}};
+use super::Hub;
+
+fn drain(rx: &mut UnboundedReceiver<String>) -> Vec<Value> {
+    let mut msgs = Vec::new();
+    while let Ok(text) = rx.try_recv() {
+        msgs.push(serde_json::from_str(&text).unwrap());
+    }
+    msgs
+}
+
+fn of_type<'a>(msgs: &'a [Value], kind: &str) -> Vec<&'a Value> {
+    msgs.iter().filter(|m| m["type"] == kind).collect()
+}
+
+#[test]
+#[serial]
+fn peers_get_changes_presence_and_conflicts() {
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_collab", None, None, &mut conn).unwrap();
+    let hub = Hub::new(Event::latest_id(&mut conn));
+    let (tx_a, mut rx_a) = unbounded_channel();
+    let (tx_b, mut rx_b) = unbounded_channel();
+    let a = hub.join(tx_a, Tz::UTC);
+    let b = hub.join(tx_b, Tz::UTC);
+    let subscribe = json!({"type": "subscribe", "tasks": [task.id]}).to_string();
+    hub.handle(&a, &json!({"type": "hello", "name": "alice"}).to_string(), &mut conn);
+    hub.handle(&a, &subscribe, &mut conn);
+    hub.handle(&b, &json!({"type": "hello", "name": "bob"}).to_string(), &mut conn);
+    hub.handle(&b, &subscribe, &mut conn);
+    let snapshot = drain(&mut rx_a);
+    assert_eq!(snapshot[0]["type"], "welcome");
+    assert_eq!(of_type(&snapshot, "snapshot")[0]["tasks"][0]["id"], task.id.as_str());
+    let base = of_type(&snapshot, "snapshot")[0]["event_id"].as_i64().unwrap();
+    drain(&mut rx_b);
+
+    let editing = json!({"type": "presence", "task": task.id, "state": "editing"}).to_string();
+    hub.handle(&a, &editing, &mut conn);
+    hub.handle(&b, &editing, &mut conn);
+    let msgs = drain(&mut rx_a);
+    let presence = of_type(&msgs, "presence");
+    assert_eq!(presence.last().unwrap()["viewers"].as_array().unwrap().len(), 2);
+    assert_eq!(of_type(&msgs, "conflict")[0]["by"], "bob");
+    assert_eq!(of_type(&drain(&mut rx_b), "conflict")[0]["reason"], "editing");
+
+    let done = json!({"type": "set_status", "ref": "r1", "id": task.id, "status": TaskStatus::Done.to_store(), "base_event": base});
+    hub.handle(&a, &done.to_string(), &mut conn);
+    let msgs = drain(&mut rx_a);
+    assert_eq!(of_type(&msgs, "ack")[0]["ref"], "r1");
+    assert_eq!(of_type(&msgs, "change")[0]["event"], "task.status_changed");
+    let msgs = drain(&mut rx_b);
+    assert_eq!(of_type(&msgs, "change")[0]["task"]["status"], TaskStatus::Done.to_store());
+    let changed = of_type(&msgs, "conflict");
+    assert_eq!((changed[0]["reason"].as_str(), changed[0]["by"].as_str()), (Some("changed"), Some("alice")));
+
+    //bob still works on the copy from before alice's change
+    let reopen = json!({"type": "set_status", "ref": "r2", "id": task.id, "status": TaskStatus::Created.to_store(), "base_event": base});
+    hub.handle(&b, &reopen.to_string(), &mut conn);
+    let msgs = drain(&mut rx_b);
+    assert_eq!(of_type(&msgs, "conflict")[0]["reason"], "stale");
+    assert!(of_type(&msgs, "ack").is_empty());
+    assert_eq!(Task::by_id(&task.id, &mut conn).unwrap().status, TaskStatus::Done.to_store());
+
+    hub.leave(&b);
+    let msgs = drain(&mut rx_a);
+    assert_eq!(of_type(&msgs, "presence")[0]["viewers"].as_array().unwrap().len(), 1);
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+//dates are read in the zone the client said hello with
+#[test]
+#[serial]
+fn dates_use_client_zone() {
+    let mut conn = establish_connection().get().unwrap();
+    let hub = Hub::new(Event::latest_id(&mut conn));
+    let (tx, mut rx) = unbounded_channel();
+    let client = hub.join(tx, Tz::UTC);
+    hub.handle(&client, &json!({"type": "hello", "name": "kiwi", "tz": "Pacific/Auckland"}).to_string(), &mut conn);
+    let create = json!({"type": "create", "ref": "c1", "name": "test_collab_zone", "due": "2030-01-10"});
+    hub.handle(&client, &create.to_string(), &mut conn);
+    let msgs = drain(&mut rx);
+    let task: Task = serde_json::from_value(of_type(&msgs, "ack")[0]["task"].clone()).unwrap();
+    let midnight = Tz::Pacific__Auckland.from_local_datetime(&NaiveDate::from_ymd_opt(2030, 1, 10).unwrap().and_hms_opt(0, 0, 0).unwrap()).unwrap();
+    assert_eq!(task.due, Some(midnight.with_timezone(&Utc)));
+
+    hub.handle(&client, &json!({"type": "hello", "name": "kiwi", "tz": "Mars/Olympus"}).to_string(), &mut conn);
+    assert_eq!(of_type(&drain(&mut rx), "error")[0]["error"], "Unknown time zone");
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
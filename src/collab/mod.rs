@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use chrono_tz::Tz;
+use diesel::PgConnection;
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::db::models::{Task, TaskDetails, TaskError, TaskStatus, event::Event};
+use crate::utils::date::parse_tz;
+use protocol::{ClientMessage, PresenceState, ServerMessage, Viewer};
+
+pub mod protocol;
+
+const PUMP_MILLIS: u64 = 1000;
+
+//which tasks a client hears about, all of them unless narrowed down
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subscription {
+    pub project: Option<String>,
+    pub status: Option<i32>,
+    pub tasks: Vec<String>
+}
+
+impl Subscription {
+    pub fn matches(&self, task: &serde_json::Value) -> bool {
+        let id_ok = self.tasks.is_empty() || self.tasks.iter().any(|t| task["id"].as_str() == Some(t.as_str()));
+        let project_ok = self.project.as_ref().is_none_or(|p| task["project"].as_str() == Some(p.as_str()));
+        let status_ok = self.status.is_none_or(|s| task["status"].as_i64() == Some(s as i64));
+        id_ok && project_ok && status_ok
+    }
+}
+
+struct Client {
+    tx: UnboundedSender<String>,
+    name: String,
+    //the zone dates like "tomorrow" are read in
+    tz: Tz,
+    subscription: Option<Subscription>,
+    presence: HashMap<String, PresenceState>
+}
+
+//connected websocket clients, their subscriptions and what they are looking at.
+//changes reach subscribers through `pump`, which follows the events table so edits
+//made over REST show up as well
+pub struct Hub {
+    clients: Mutex<HashMap<String, Client>>,
+    last_event: Mutex<i64>
+}
+
+impl Hub {
+
+    pub fn new(last_event: i64) -> Self {
+        Self { clients: Mutex::new(HashMap::new()), last_event: Mutex::new(last_event) }
+    }
+
+    pub fn join(&self, tx: UnboundedSender<String>, tz: Tz) -> String {
+        let id = Uuid::new_v4().hyphenated().to_string();
+        let client = Client { tx, name: id.clone(), tz, subscription: None, presence: HashMap::new() };
+        self.clients.lock().unwrap().insert(id.clone(), client);
+        self.send(&id, &ServerMessage::Welcome { client: id.clone() });
+        id
+    }
+
+    pub fn leave(&self, client: &str) {
+        let left = self.clients.lock().unwrap().remove(client);
+        if let Some(left) = left {
+            for task in left.presence.keys() {
+                self.broadcast_presence(task);
+            }
+        }
+    }
+
+    fn send(&self, client: &str, msg: &ServerMessage) {
+        if let (Some(c), Ok(text)) = (self.clients.lock().unwrap().get(client), serde_json::to_string(msg)) {
+            let _ = c.tx.send(text);
+        }
+    }
+
+    fn name(&self, client: &str) -> Option<String> {
+        self.clients.lock().unwrap().get(client).map(|c| c.name.clone())
+    }
+
+    fn tz(&self, client: &str) -> Tz {
+        self.clients.lock().unwrap().get(client).map_or(Tz::UTC, |c| c.tz)
+    }
+
+    //everyone but `client` who has `task` open for editing
+    fn editors(&self, task: &str, client: &str) -> Vec<(String, String)> {
+        self.clients
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, c)| id.as_str() != client && c.presence.get(task) == Some(&PresenceState::Editing))
+            .map(|(id, c)| (id.clone(), c.name.clone()))
+            .collect()
+    }
+
+    fn broadcast_presence(&self, task: &str) {
+        let clients = self.clients.lock().unwrap();
+        let mut viewers = clients
+            .iter()
+            .filter_map(|(id, c)| c.presence.get(task).map(|state| Viewer { client: id.clone(), name: c.name.clone(), state: *state }))
+            .collect::<Vec<Viewer>>();
+        viewers.sort_by(|a, b| a.client.cmp(&b.client));
+        let msg = ServerMessage::Presence { task: task.to_string(), viewers };
+        if let Ok(text) = serde_json::to_string(&msg) {
+            for c in clients.values().filter(|c| c.subscription.is_some()) {
+                let _ = c.tx.send(text.clone());
+            }
+        }
+    }
+
+    //sends new events to every client whose subscription matches the task
+    pub fn pump(&self, conn: &mut PgConnection) -> usize {
+        let mut last_event = self.last_event.lock().unwrap();
+        let events = Event::since(*last_event, 100, conn);
+        let clients = self.clients.lock().unwrap();
+        for event in &events {
+            let msg = ServerMessage::Change { event_id: event.id, event: event.kind.clone(), task: event.payload.clone() };
+            let Ok(text) = serde_json::to_string(&msg) else { continue };
+            for c in clients.values() {
+                if c.subscription.as_ref().is_some_and(|s| s.matches(&event.payload)) {
+                    let _ = c.tx.send(text.clone());
+                }
+            }
+        }
+        if let Some(last) = events.last() {
+            *last_event = last.id;
+        }
+        events.len()
+    }
+
+    pub fn handle(&self, client: &str, text: &str, conn: &mut PgConnection) {
+        let msg = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(msg) => msg,
+            Err(err) => return self.send(client, &ServerMessage::Error { reference: None, error: err.to_string() })
+        };
+        match msg {
+            ClientMessage::Hello { name, tz } => {
+                let tz = match tz.as_deref().map(parse_tz) {
+                    Some(None) => return self.send(client, &ServerMessage::Error { reference: None, error: "Unknown time zone".to_string() }),
+                    Some(Some(tz)) => Some(tz),
+                    None => None
+                };
+                if let Some(c) = self.clients.lock().unwrap().get_mut(client) {
+                    c.name = name;
+                    c.tz = tz.unwrap_or(c.tz);
+                }
+            },
+            ClientMessage::Subscribe { project, status, tasks } => {
+                let status = match status.as_deref().map(TaskStatus::from_str) {
+                    Some(None) => return self.send(client, &ServerMessage::Error { reference: None, error: "Unknown status".to_string() }),
+                    Some(Some(s)) => Some(s.to_store()),
+                    None => None
+                };
+                let subscription = Subscription { project, status, tasks };
+                let event_id = Event::latest_id(conn);
+                let tasks = Task::list(conn)
+                    .into_iter()
+                    .filter(|t| serde_json::to_value(t).is_ok_and(|v| subscription.matches(&v)))
+                    .collect::<Vec<Task>>();
+                if let Some(c) = self.clients.lock().unwrap().get_mut(client) {
+                    c.subscription = Some(subscription);
+                }
+                self.send(client, &ServerMessage::Snapshot { event_id, tasks });
+            },
+            ClientMessage::Create { reference, name, description, due, on_duplicate } => {
+                let due = match due.map(|d| d.resolve(&self.tz(client))) {
+                    Some(None) => return self.send(client, &ServerMessage::Error { reference, error: "Could not parse due date".to_string() }),
+                    Some(Some(parsed)) => Some(parsed.due),
+                    None => None
+                };
+                match Task::create_with_details(&name, description.as_deref(), due, TaskDetails::default(), on_duplicate, conn) {
+                    Ok(task) => self.applied(client, reference, task, conn),
+                    Err(TaskError::NotFound) => self.send(client, &ServerMessage::Error { reference, error: "Could not create task".to_string() }),
+                    Err(err) => self.refused(client, reference, err)
+                }
+            },
+            ClientMessage::Update { reference, task, base_event } => {
+                if self.is_stale(client, &reference, &task.id, base_event, conn) {
+                    return
+                }
+                match Task::update(*task, &self.tz(client), conn) {
+                    Ok(task) => self.applied(client, reference, task, conn),
+                    Err(err) => self.refused(client, reference, err)
+                }
+            },
+            ClientMessage::SetStatus { reference, id, status, version, base_event } => {
+                if self.is_stale(client, &reference, &id, base_event, conn) {
+                    return
+                }
+                match Task::set_status(&id, status, version, conn) {
+                    Ok(task) => self.applied(client, reference, task, conn),
+                    Err(err) => self.refused(client, reference, err)
+                }
+            },
+            ClientMessage::Presence { task, state } => {
+                if let Some(c) = self.clients.lock().unwrap().get_mut(client) {
+                    match state {
+                        PresenceState::Idle => c.presence.remove(&task),
+                        _ => c.presence.insert(task.clone(), state)
+                    };
+                }
+                self.broadcast_presence(&task);
+                if state == PresenceState::Editing {
+                    self.editing_conflict(client, &task, conn);
+                }
+            },
+        }
+    }
+
+    //a mutation based on an older copy than the latest change is refused
+    fn is_stale(&self, client: &str, reference: &Option<String>, task_id: &str, base_event: Option<i64>, conn: &mut PgConnection) -> bool {
+        let Some(base) = base_event else { return false };
+        let latest = Event::for_task(task_id, conn).last().map(|e| e.id).unwrap_or(0);
+        if latest <= base {
+            return false
+        }
+        if let Some(task) = Task::by_id(task_id, conn) {
+            self.send(client, &ServerMessage::Conflict { reference: reference.clone(), reason: "stale".to_string(), by: None, task });
+        }
+        true
+    }
+
+    fn refused(&self, client: &str, reference: Option<String>, err: TaskError) {
+        let msg = match err {
+            TaskError::Conflict(task) => ServerMessage::Conflict { reference, reason: "stale".to_string(), by: None, task: *task },
+            TaskError::InvalidDue => ServerMessage::Error { reference, error: "Could not parse due date".to_string() },
+            TaskError::NotFound => ServerMessage::Error { reference, error: "Not Found".to_string() },
+            TaskError::Duplicate(task) => ServerMessage::Conflict { reference, reason: "duplicate".to_string(), by: None, task: *task },
+            TaskError::Database => ServerMessage::Error { reference, error: "Could not store task".to_string() }
+        };
+        self.send(client, &msg);
+    }
+
+    fn applied(&self, client: &str, reference: Option<String>, task: Task, conn: &mut PgConnection) {
+        self.send(client, &ServerMessage::Ack { reference, task: task.clone() });
+        self.pump(conn);
+        let by = self.name(client);
+        for (editor, _) in self.editors(&task.id, client) {
+            self.send(&editor, &ServerMessage::Conflict { reference: None, reason: "changed".to_string(), by: by.clone(), task: task.clone() });
+        }
+    }
+
+    fn editing_conflict(&self, client: &str, task_id: &str, conn: &mut PgConnection) {
+        let editors = self.editors(task_id, client);
+        if editors.is_empty() {
+            return
+        }
+        let Some(task) = Task::by_id(task_id, conn) else { return };
+        let by = self.name(client);
+        for (editor, name) in editors {
+            self.send(client, &ServerMessage::Conflict { reference: None, reason: "editing".to_string(), by: Some(name), task: task.clone() });
+            self.send(&editor, &ServerMessage::Conflict { reference: None, reason: "editing".to_string(), by: by.clone(), task: task.clone() });
+        }
+    }
+}
+
+//forwards changes made outside the websocket, e.g. over REST, to subscribers
+pub fn spawn_pump(hub: std::sync::Arc<Hub>, pool: DbPool) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        if let Ok(mut conn) = pool.get() {
+            hub.pump(&mut conn);
+        }
+        thread::sleep(Duration::from_millis(PUMP_MILLIS));
+    })
+}
+
+#[cfg(test)]
+mod collab_tests;
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::{DuplicateMode, Task};
+use crate::services::task::TaskUpdate;
+use crate::utils::date::DueInput;
+
+//what a client sends, one JSON object per text frame tagged by "type".
+//mutations carry an optional "ref" that comes back with their ack, and
+//"base_event", the id of the last change the client saw for that task
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello {
+        name: String,
+        //IANA zone, else the X-Timezone of the upgrade request or DEFAULT_TZ
+        #[serde(default)]
+        tz: Option<String>
+    },
+    Subscribe {
+        #[serde(default)]
+        project: Option<String>,
+        #[serde(default)]
+        status: Option<String>,
+        #[serde(default)]
+        tasks: Vec<String>
+    },
+    Create {
+        #[serde(rename = "ref", default)]
+        reference: Option<String>,
+        name: String,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        due: Option<DueInput>,
+        #[serde(default)]
+        on_duplicate: DuplicateMode
+    },
+    Update {
+        #[serde(rename = "ref", default)]
+        reference: Option<String>,
+        task: Box<TaskUpdate>,
+        #[serde(default)]
+        base_event: Option<i64>
+    },
+    SetStatus {
+        #[serde(rename = "ref", default)]
+        reference: Option<String>,
+        id: String,
+        status: i32,
+        //the version the client last saw, refused as stale when the task moved on
+        #[serde(default)]
+        version: Option<i32>,
+        #[serde(default)]
+        base_event: Option<i64>
+    },
+    Presence {
+        task: String,
+        state: PresenceState
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceState {
+    Viewing,
+    Editing,
+    Idle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Viewer {
+    pub client: String,
+    pub name: String,
+    pub state: PresenceState
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        client: String
+    },
+    Snapshot {
+        event_id: i64,
+        tasks: Vec<Task>
+    },
+    Ack {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        task: Task
+    },
+    Error {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        error: String
+    },
+    Change {
+        event_id: i64,
+        event: String,
+        task: serde_json::Value
+    },
+    Presence {
+        task: String,
+        viewers: Vec<Viewer>
+    },
+    //"stale" when a mutation was based on an old copy and was not applied,
+    //"duplicate" when a create was refused because the name is taken,
+    //"editing" when someone else is editing the same task, "changed" when
+    //someone else saved the task being edited
+    Conflict {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        reason: String,
+        by: Option<String>,
+        task: Task
+    },
+}
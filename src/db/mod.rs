@@ -1,5 +1,6 @@
 pub mod models;
 pub mod schema;
+pub mod store;
 
 use diesel::pg::Pg;
 use dotenv::dotenv;
@@ -30,7 +31,7 @@ pub fn establish_connection() -> DbPool {
             .build(manager)
             .expect("Failed to create DB pool.");
         if cfg!(test) {
-            let _ = run_migrations(&mut pool.get().unwrap()).expect("error running migrations");
+            run_migrations(&mut pool.get().unwrap()).expect("error running migrations");
         }
         pool
 }
@@ -1,24 +1,64 @@
 use std::fmt;
 use serde::{Deserialize, Serialize};
 use diesel::{prelude::*};
-use diesel::dsl::{now, not};
+use diesel::dsl::not;
+use diesel::pg::Pg;
+use diesel::sql_types::{Bool, Nullable};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
 use uuid::Uuid;
 
 use super::schema::tasks;
 use super::schema::tasks::dsl::tasks as task_dsl;
 use crate::services::task::TaskUpdate;
-use crate::utils::{sort::sort_by_score, parse::parse_search_value};
+use event::{Event, EventKind};
+use reminder::Reminder;
+use crate::utils::{sort::sort_by_score, parse::parse_search_value, date::{today, day_range, DueInput}};
 
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Insertable, Identifiable)]
-#[diesel(table_name = tasks)]
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+type Condition = Box<dyn BoxableExpression<tasks::table, Pg, SqlType = Nullable<Bool>>>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Insertable, Identifiable, AsChangeset)]
+#[diesel(table_name = tasks, treat_none_as_null = true)]
 pub struct Task {
     pub id: String,
     pub name: String,
     pub description: String,
     pub status: i32,
-    pub due: Option<chrono::NaiveDateTime>,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: chrono::NaiveDateTime
+    pub due: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub priority: i32,
+    pub recurrence: Option<String>,
+    pub scheduled: Option<DateTime<Utc>>,
+    pub deadline: Option<DateTime<Utc>>,
+    pub all_day: bool,
+    pub snoozed_until: Option<DateTime<Utc>>,
+    //bumped on every write, sent as ETag and expected back in If-Match
+    pub version: i32,
+    //key:value pairs from imported todo.txt lines that have no field of their own
+    #[serde(default = "no_extensions")]
+    pub extensions: serde_json::Value
+}
+
+fn no_extensions() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+//optional fields set on creation, recurrence is an RFC 5545 RRULE value like FREQ=MONTHLY.
+//a task stays out of day views until `scheduled`, `all_day` dates are overdue once their day is over
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskDetails {
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub priority: i32,
+    pub recurrence: Option<String>,
+    pub scheduled: Option<DateTime<Utc>>,
+    pub deadline: Option<DateTime<Utc>>,
+    pub all_day: bool
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,6 +69,48 @@ pub enum TaskStatus {
     Done,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub enum TaskPriority {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+//what creating a task with a name that is already taken does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateMode {
+    #[default]
+    Allow,
+    Reject,
+    Existing,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TaskError {
+    NotFound,
+    InvalidDue,
+    //a task with that name exists and duplicates were rejected
+    Duplicate(Box<Task>),
+    //the write was based on an older version, carries the current copy
+    Conflict(Box<Task>),
+    //the store failed, nothing was written
+    Database,
+}
+
+impl From<diesel::result::Error> for TaskError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => TaskError::NotFound,
+            err => {
+                log::error!("task write failed: {err}");
+                TaskError::Database
+            }
+        }
+    }
+}
+
 impl fmt::Display for TaskStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -58,15 +140,56 @@ impl TaskStatus {
             _         => None 
         }
      }
+    pub fn from_store(status: i32) -> Option<Self> {
+        [TaskStatus::Created, TaskStatus::Overdue, TaskStatus::Done, TaskStatus::Deleted]
+            .into_iter()
+            .find(|s| s.to_store() == status)
+    }
+}
+
+impl fmt::Display for TaskPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TaskPriority::None      => write!(f, "none"),
+            TaskPriority::Low       => write!(f, "low"),
+            TaskPriority::Medium    => write!(f, "medium"),
+            TaskPriority::High      => write!(f, "high"),
+        }
+    }
+}
+
+impl TaskPriority {
+    pub fn to_store(&self) -> i32 {
+        match *self {
+            TaskPriority::None      => 0,
+            TaskPriority::Low       => 1,
+            TaskPriority::Medium    => 2,
+            TaskPriority::High      => 3,
+        }
+    }
+    pub fn from_str(priority: &str) -> Option<Self> {
+        match priority.to_ascii_lowercase().as_str() {
+            "none"          => Some(TaskPriority::None),
+            "low"           => Some(TaskPriority::Low),
+            "medium" | "med"=> Some(TaskPriority::Medium),
+            "high"          => Some(TaskPriority::High),
+            _               => None
+        }
+    }
+    pub fn from_store(priority: i32) -> Option<Self> {
+        [TaskPriority::None, TaskPriority::Low, TaskPriority::Medium, TaskPriority::High]
+            .into_iter()
+            .find(|p| p.to_store() == priority)
+    }
 }
 
 impl Task {
 
 
-    pub fn new(name: &str, descr: Option<&str>, due: Option<chrono::NaiveDateTime>) -> Self {
+    pub fn new(name: &str, descr: Option<&str>, due: Option<DateTime<Utc>>) -> Self {
         let id = Uuid::new_v4().hyphenated().to_string();
         let description = descr.unwrap_or("").to_string();
-        let ts = chrono::Local::now().naive_local();
+        let ts = Utc::now();
         Self {
             id,
             name: name.to_string(),
@@ -74,153 +197,449 @@ impl Task {
             status: TaskStatus::Created.to_store(),
             due,
             created_at: ts,
-            updated_at: ts
+            updated_at: ts,
+            tags: vec![],
+            project: None,
+            priority: TaskPriority::None.to_store(),
+            recurrence: None,
+            scheduled: None,
+            deadline: None,
+            all_day: false,
+            snoozed_until: None,
+            version: 1,
+            extensions: no_extensions()
         }
     }
 
+    pub fn with_details(mut self, details: TaskDetails) -> Self {
+        self.tags = normalize_tags(details.tags);
+        self.project = details.project;
+        self.priority = details.priority;
+        self.recurrence = details.recurrence;
+        self.scheduled = details.scheduled;
+        self.deadline = details.deadline;
+        self.all_day = details.all_day;
+        self
+    }
+
+    pub fn is_overdue(&self, at: DateTime<Utc>) -> bool {
+        overdue_at(self.due, self.deadline, self.all_day, at)
+    }
+
     pub fn check_overdue(check_id: &str, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
-        use super::schema::tasks::dsl::{id, due, status};
-        diesel::update(task_dsl)
-            .filter(id.eq(check_id))
-            .filter(not(status.eq(TaskStatus::Done.to_store())))
-            .filter(not(status.eq(TaskStatus::Deleted.to_store())))
-            .filter(due.lt(now))
-            .set(status.eq(TaskStatus::Overdue.to_store()))
-            .execute(conn)
+        Task::mark_overdue(Some(check_id), conn)
     }
 
-    pub fn create(name: &str, description: Option<&str>, due: Option<chrono::NaiveDateTime>, conn: &mut PgConnection) -> Option<Self> {
-        if let Some(mut task) = Self::by_name(name, conn) {
-            if task.due.is_some() {
-                if task.due.unwrap().timestamp_millis() < chrono::Local::now().naive_local().timestamp_millis() {
-                    task.status = TaskStatus::Overdue.to_store();
+    //open tasks past their due or deadline become overdue. looking first keeps reads
+    //from queueing behind writers when nothing has to change. the version stays, this
+    //follows from the dates and must not turn a client's If-Match stale
+    fn mark_overdue(only: Option<&str>, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+        use super::schema::tasks::dsl::{id, status};
+        let mut query = task_dsl
+            .select(id)
+            .filter(status.eq(TaskStatus::Created.to_store()))
+            .filter(overdue_condition(Utc::now()))
+            .into_boxed();
+        if let Some(only) = only {
+            query = query.filter(id.eq(only));
+        }
+        let ids = query.load::<String>(conn)?;
+        if ids.is_empty() {
+            return Ok(0)
+        }
+        write_transaction(conn, |conn| {
+            let overdue = diesel::update(task_dsl)
+                .filter(id.eq_any(&ids))
+                .filter(status.eq(TaskStatus::Created.to_store()))
+                .filter(overdue_condition(Utc::now()))
+                .set(status.eq(TaskStatus::Overdue.to_store()))
+                .get_results::<Task>(conn)?;
+            for task in &overdue {
+                Event::emit(EventKind::Overdue, task, conn)?;
+            }
+            Ok(overdue.len())
+        })
+    }
+
+    //returns the task of that name if there is one
+    pub fn create(name: &str, description: Option<&str>, due: Option<DateTime<Utc>>, conn: &mut PgConnection) -> Option<Self> {
+        Task::create_with_details(name, description, due, TaskDetails::default(), DuplicateMode::Existing, conn).ok()
+    }
+
+    pub fn create_with_details(name: &str, description: Option<&str>, due: Option<DateTime<Utc>>, details: TaskDetails, on_duplicate: DuplicateMode, conn: &mut PgConnection) -> Result<Self, TaskError> {
+        Task::find_or_create(name, description, due, details, on_duplicate, conn).map(|(task, _)| task)
+    }
+
+    //like create_with_details, the flag is false when an existing task came back.
+    //the name check and the insert hold a lock on the name, so two requests cannot
+    //both find it free
+    pub fn find_or_create(name: &str, description: Option<&str>, due: Option<DateTime<Utc>>, details: TaskDetails, on_duplicate: DuplicateMode, conn: &mut PgConnection) -> Result<(Self, bool), TaskError> {
+        write_transaction::<(Self, bool), TaskError, _>(conn, |conn| {
+            if on_duplicate != DuplicateMode::Allow {
+                advisory_lock("task.name", name, conn)?;
+                if let Some(existing) = Self::by_name(name, conn) {
+                    return match on_duplicate {
+                        DuplicateMode::Reject => Err(TaskError::Duplicate(Box::new(existing))),
+                        _ => Ok((existing, false))
+                    }
                 }
-                return Some(task)
-            } else {
-                return Some(task)
             }
-        }
-        let mut new_task = Task::new(name, description, due);
-        if new_task.due.is_some() && new_task.due.unwrap().timestamp_millis() < chrono::Local::now().naive_local().timestamp_millis() {
+            let created = Task::insert(Task::new(name, description, due).with_details(details), conn).ok_or(TaskError::Database)?;
+            Ok((created, true))
+        })
+    }
+
+    //stores a task built by the caller, e.g. with an id chosen by an offline client
+    pub fn insert(mut new_task: Task, conn: &mut PgConnection) -> Option<Self> {
+        //done and deleted tasks keep their status
+        if new_task.status == TaskStatus::Created.to_store() && new_task.is_overdue(Utc::now()) {
             new_task.status = TaskStatus::Overdue.to_store();
         }
-        diesel::insert_into(task_dsl)
-            .values(&new_task)
-            .execute(conn)
-            .expect("Error saving new user");
-        Self::by_id(&new_task.id.as_str(), conn)
+        write_transaction::<Self, diesel::result::Error, _>(conn, |conn| {
+            diesel::insert_into(task_dsl)
+                .values(&new_task)
+                .execute(conn)?;
+            let created = Self::by_id(new_task.id.as_str(), conn).ok_or(diesel::result::Error::NotFound)?;
+            Event::emit(EventKind::Created, &created, conn)?;
+            Ok(created)
+        }).ok()
     }
 
     pub fn list(conn: &mut PgConnection) -> Vec<Self> {
         Task::set_overdues(conn);
-        use super::schema::tasks::dsl::{due, updated_at, status};
+        Task::wake_snoozed(conn);
+        use super::schema::tasks::dsl::{due, updated_at, status, snoozed_until};
         task_dsl
             .filter(not(status.eq(TaskStatus::Deleted.to_store())))
+            .filter(snoozed_until.is_null())
             .order_by((due.asc(), status.asc(), updated_at.desc()))
             .load::<Task>(conn).expect("Error loading tasks")
     }
 
+    //every task but deleted ones, snoozed tasks included
+    pub fn all(conn: &mut PgConnection) -> Vec<Self> {
+        use super::schema::tasks::dsl::{status, created_at};
+        task_dsl
+            .filter(not(status.eq(TaskStatus::Deleted.to_store())))
+            .order(created_at.asc())
+            .load::<Task>(conn)
+            .unwrap_or_default()
+    }
+
+    //the open task to work on next: overdue first, then by deadline, due and priority.
+    //snoozed tasks and those scheduled for later are skipped
+    pub fn next(conn: &mut PgConnection) -> Option<Self> {
+        Task::set_overdues(conn);
+        Task::wake_snoozed(conn);
+        use super::schema::tasks::dsl::{status, due, deadline, scheduled, priority, created_at, snoozed_until};
+        task_dsl
+            .filter(status.eq_any([TaskStatus::Overdue.to_store(), TaskStatus::Created.to_store()]))
+            .filter(snoozed_until.is_null())
+            .filter(scheduled.is_null().or(scheduled.le(Utc::now())))
+            .order_by((status.asc(), deadline.asc().nulls_last(), due.asc().nulls_last(), priority.desc(), created_at.asc()))
+            .first::<Task>(conn)
+            .ok()
+    }
+
+    //hides the task until `until`, None brings it back right away.
+    //`expected` is the version the caller last read, None skips the check
+    pub fn snooze(task_id: &str, until: Option<DateTime<Utc>>, expected: Option<i32>, conn: &mut PgConnection) -> Result<Self, TaskError> {
+        use super::schema::tasks::dsl::{snoozed_until, updated_at, version};
+        write_transaction(conn, |conn| {
+            let current = Self::by_id(task_id, conn).ok_or(TaskError::NotFound)?;
+            if expected.is_some_and(|v| v != current.version) {
+                return Err(TaskError::Conflict(Box::new(current)))
+            }
+            let task = diesel::update(task_dsl.find(task_id))
+                .filter(version.eq(current.version))
+                .set((snoozed_until.eq(until), updated_at.eq(Utc::now()), version.eq(version + 1)))
+                .get_result::<Task>(conn)?;
+            let kind = if until.is_some() { EventKind::Snoozed } else { EventKind::Unsnoozed };
+            Event::emit(kind, &task, conn)?;
+            Ok(task)
+        })
+    }
+
+    //snoozes that ran out are cleared once, each woken task gets an event.
+    //like overdue this is derived and keeps the version
+    pub fn wake_snoozed(conn: &mut PgConnection) {
+        use super::schema::tasks::dsl::{id, snoozed_until};
+        let ids = task_dsl
+            .select(id)
+            .filter(snoozed_until.le(Utc::now()))
+            .load::<String>(conn)
+            .expect("Failed to look for snoozed tasks");
+        if ids.is_empty() {
+            return
+        }
+        write_transaction::<_, diesel::result::Error, _>(conn, |conn| {
+            let woken = diesel::update(task_dsl)
+                .filter(id.eq_any(&ids))
+                .filter(snoozed_until.le(Utc::now()))
+                .set(snoozed_until.eq(None::<DateTime<Utc>>))
+                .get_results::<Task>(conn)?;
+            for task in woken {
+                Event::emit(EventKind::Unsnoozed, &task, conn)?;
+            }
+            Ok(())
+        }).expect("Failed to wake snoozed tasks");
+    }
+
 
 
     pub fn by_id(id: &str, conn: &mut PgConnection) -> Option<Self> {
         Task::check_overdue(id, conn).unwrap();
-        if let Ok(record) = task_dsl.find(id).first::<Task>(conn) {
-            Some(record)
-        } else {
-            None
-        }
+        task_dsl.find(id).first::<Task>(conn).ok()
     }
 
+    pub fn by_ids(ids: &[String], conn: &mut PgConnection) -> Vec<Self> {
+        use super::schema::tasks::dsl::id;
+        task_dsl
+            .filter(id.eq_any(ids))
+            .load::<Task>(conn)
+            .unwrap_or_default()
+    }
+
+    //deleted tasks do not hold on to their name
     pub fn by_name(name_query: &str, conn: &mut PgConnection) -> Option<Self> {
-        use super::schema::tasks::dsl::name;
-        if let Ok(record) = task_dsl.filter(name.eq(name_query)).first::<Task>(conn) {
-            Some(record)
-        } else {
-            None
-        }
+        use super::schema::tasks::dsl::{name, status, created_at};
+        let found = task_dsl
+            .filter(name.eq(name_query))
+            .filter(not(status.eq(TaskStatus::Deleted.to_store())))
+            .order(created_at.asc())
+            .first::<Task>(conn)
+            .ok()?;
+        Self::by_id(&found.id, conn)
     }
 
     fn set_overdues(conn: &mut PgConnection) {
-        use super::schema::tasks::dsl::{due, status};
-        diesel::update(task_dsl)
-            .filter(due.lt(now))
-            .set(status.eq(TaskStatus::Overdue.to_store()))
-            .execute(conn).expect("Failed to run overdue set");
-    }
-
-    pub fn update(mut tsk: TaskUpdate, conn: &mut PgConnection) -> Option<Self> {
-        use super::schema::tasks::dsl::{name, description, status, due};
-
-        match tsk.due {
-            Some(d) => {
-                let ts_now = chrono::Local::now().naive_local();
-                if d.timestamp_millis() < ts_now.timestamp_millis() {
-                    tsk.status = TaskStatus::Overdue.to_store();
-                } else {
-                    tsk.status = TaskStatus::Created.to_store();
-                }
-            }
-            None => {}
+        Task::mark_overdue(None, conn).expect("Failed to run overdue set");
+    }
+
+    //every open task that is past its dates, for the dispatcher so task.overdue goes
+    //out when it happens and not on the next read
+    pub fn sweep_overdue(conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+        Task::mark_overdue(None, conn)
+    }
+
+    pub fn update(tsk: TaskUpdate, tz: &Tz, conn: &mut PgConnection) -> Result<Self, TaskError> {
+        write_transaction(conn, |conn| {
+            let current = Self::by_id(&tsk.id, conn).ok_or(TaskError::NotFound)?;
+            let changed = Task::apply(&current, tsk, tz)?;
+            Task::save(changed, current.version, conn)
+        })
+    }
+
+    //the task an update turns `current` into, for every store to save
+    pub fn apply(current: &Task, tsk: TaskUpdate, tz: &Tz) -> Result<Self, TaskError> {
+        //the version the client read, or else the updated_at it sent back
+        let fresh = match tsk.version {
+            Some(expected) => expected == current.version,
+            None => tsk.updated_at == current.updated_at
+        };
+        if !fresh {
+            return Err(TaskError::Conflict(Box::new(current.clone())))
         }
+        let parsed_due = match &tsk.due {
+            Some(input) => Some(input.resolve(tz).ok_or(TaskError::InvalidDue)?),
+            None => None
+        };
+        let new_due = parsed_due.map(|p| p.due);
+        let new_scheduled = resolve_optional(tsk.scheduled.as_ref(), tz)?.unwrap_or(current.scheduled);
+        let new_deadline = resolve_optional(tsk.deadline.as_ref(), tz)?.unwrap_or(current.deadline);
+        //a due sent back unchanged keeps its all day flag
+        let new_all_day = match (tsk.all_day, parsed_due) {
+            (Some(flag), _) => flag,
+            (None, Some(parsed)) if current.due != Some(parsed.due) => parsed.all_day,
+            (None, _) => current.all_day
+        };
+        Ok(Task {
+            name: tsk.name,
+            description: tsk.description,
+            status: tsk.status,
+            due: new_due,
+            tags: tsk.tags.map_or(current.tags.clone(), normalize_tags),
+            //an empty string clears project and recurrence
+            project: tsk.project.map_or(current.project.clone(), |p| Some(p).filter(|p| !p.is_empty())),
+            priority: tsk.priority.unwrap_or(current.priority),
+            recurrence: tsk.recurrence.map_or(current.recurrence.clone(), |r| Some(r).filter(|r| !r.is_empty())),
+            scheduled: new_scheduled,
+            deadline: new_deadline,
+            all_day: new_all_day,
+            updated_at: Utc::now(),
+            ..current.clone()
+        })
+    }
 
-        match diesel::update(task_dsl.find(&tsk.id))
-            .set((name.eq(tsk.name), description.eq(tsk.description), status.eq(tsk.status), due.eq(tsk.due)))
-            .execute(conn) {
-                Ok(_) => Self::by_id(tsk.id.as_str(), conn),
-                Err(_) => None
-            }
+    //open tasks take their status from the dates, done and deleted ones keep theirs
+    pub fn settle_status(&mut self, at: DateTime<Utc>) {
+        let open = self.status == TaskStatus::Created.to_store() || self.status == TaskStatus::Overdue.to_store();
+        if open {
+            self.status = match self.is_overdue(at) {
+                true => TaskStatus::Overdue.to_store(),
+                false => TaskStatus::Created.to_store()
+            };
+        }
     }
 
-    pub fn set_status(task_id: &str, new_status: i32, conn: &mut PgConnection) -> Option<Self> {
-        use super::schema::tasks::dsl::status;
-        
-        match diesel::update(task_dsl.find(task_id))
-            .set(status.eq(new_status))
-            .execute(conn) {
-                Ok(_) => {
-                    let tsk = Self::by_id(task_id, conn).unwrap();
-                    Some(tsk)
-                },
-                Err(_) => None
+    //stores a whole task the caller changed, e.g. from an import or a note, on top of
+    //version `expected`. every write that can move dates ends up here: open tasks get
+    //their status from the dates, pending reminders move along and events are recorded
+    pub fn save(mut changed: Task, expected: i32, conn: &mut PgConnection) -> Result<Self, TaskError> {
+        use super::schema::tasks::dsl::version;
+        write_transaction(conn, |conn| {
+            let current = task_dsl.find(&changed.id).first::<Task>(conn)?;
+            if current.version != expected {
+                return Err(TaskError::Conflict(Box::new(current)))
             }
+            changed.settle_status(Utc::now());
+            changed.version = current.version + 1;
+            let updated = diesel::update(task_dsl.find(&changed.id))
+                .filter(version.eq(expected))
+                .set(&changed)
+                .get_result::<Task>(conn)?;
+            Reminder::reschedule(&updated, conn);
+            Event::emit(EventKind::Updated, &updated, conn)?;
+            if updated.status != current.status {
+                Event::emit(EventKind::StatusChanged, &updated, conn)?;
+            }
+            Ok(updated)
+        })
     }
 
+    //`expected` is the version the caller last read, None skips the check
+    pub fn set_status(task_id: &str, new_status: i32, expected: Option<i32>, conn: &mut PgConnection) -> Result<Self, TaskError> {
+        use super::schema::tasks::dsl::{status, updated_at, version};
 
-    pub fn filter(text: &str, conn: &mut PgConnection) -> Vec<Task> {
-        if text.starts_with(":") {
-            use super::schema::tasks::dsl::{status, due, updated_at};
-            Task::set_overdues(conn);
-            let (_column, values) = parse_search_value(text);
-            let mut statuses = Vec::<i32>::new();
-            for value in values {
-                if let Some(stat) = TaskStatus::from_str(&value) {
-                    statuses.push(stat.to_store())
-                }
+        write_transaction(conn, |conn| {
+            let current = Self::by_id(task_id, conn).ok_or(TaskError::NotFound)?;
+            if expected.is_some_and(|v| v != current.version) {
+                return Err(TaskError::Conflict(Box::new(current)))
             }
-            let length = statuses.len();
-            if  length > 0 {
-                let base = Box::new(status.eq(statuses[0]));
-                let query: Box<dyn BoxableExpression<tasks::table, diesel::pg::Pg, SqlType = diesel::sql_types::Bool>> = statuses 
-                    .into_iter()
-                    .map(|st| status.eq(st))
-                    .fold(base, |query, item| {
-                        Box::new(query.or(item))
-                    });
-                if let Ok(records) = task_dsl
-                        .filter(query)
-                        .order((due.asc(), status.asc(), updated_at.desc()))
-                        .get_results(conn) {
-                        records
-                } else {
-                    vec![]
+            match diesel::update(task_dsl.find(task_id))
+                .filter(version.eq(current.version))
+                .set((status.eq(new_status), updated_at.eq(Utc::now()), version.eq(version + 1)))
+                .get_result::<Task>(conn) {
+                    Ok(tsk) => {
+                        Event::emit(EventKind::StatusChanged, &tsk, conn)?;
+                        Ok(tsk)
+                    },
+                    Err(diesel::result::Error::NotFound) => Err(Self::by_id(task_id, conn).map_or(TaskError::NotFound, |t| TaskError::Conflict(Box::new(t)))),
+                    Err(err) => Err(err.into())
                 }
-            } else {
-                vec![]
+        })
+    }
+
+
+    pub fn filter(text: &str, tz: &Tz, conn: &mut PgConnection) -> Vec<Task> {
+        if text.starts_with(':') {
+            Task::set_overdues(conn);
+            let (column, values) = parse_search_value(text);
+            match column.as_str() {
+                "due" | "scheduled" | "deadline" => Task::date_filter(&column, &values, tz, conn),
+                "tag" | "project" | "priority" => Task::detail_filter(&column, &values, conn),
+                "snoozed" => Task::snoozed_filter(conn),
+                _ => Task::status_filter(&values, conn)
             }
         } else {
             Task::text_filter(text, conn)
         }
     }
+
+    //:snoozed: lists what is still hidden, the earliest to come back first
+    fn snoozed_filter(conn: &mut PgConnection) -> Vec<Task> {
+        use super::schema::tasks::dsl::snoozed_until;
+        Task::wake_snoozed(conn);
+        task_dsl
+            .filter(snoozed_until.is_not_null())
+            .order(snoozed_until.asc())
+            .load::<Task>(conn)
+            .unwrap_or_default()
+    }
+
+    //:tag:finance;home, :project:home or :priority:high;medium
+    fn detail_filter(column: &str, values: &[String], conn: &mut PgConnection) -> Vec<Task> {
+        use super::schema::tasks::dsl::{tags, project, priority, status, due, updated_at};
+        let query = task_dsl
+            .filter(not(status.eq(TaskStatus::Deleted.to_store())))
+            .order((due.asc(), status.asc(), updated_at.desc()))
+            .into_boxed();
+        let query = match column {
+            "tag" => query.filter(tags.overlaps_with(values.to_vec())),
+            "project" => query.filter(lower(project.assume_not_null()).eq_any(values.to_vec())),
+            _ => {
+                let priorities = values
+                    .iter()
+                    .filter_map(|value| TaskPriority::from_str(value))
+                    .map(|p| p.to_store())
+                    .collect::<Vec<i32>>();
+                query.filter(priority.eq_any(priorities))
+            }
+        };
+        query.load::<Task>(conn).unwrap_or_default()
+    }
+
+    fn status_filter(values: &[String], conn: &mut PgConnection) -> Vec<Task> {
+        use super::schema::tasks::dsl::{status, due, updated_at};
+        let mut statuses = Vec::<i32>::new();
+        for value in values {
+            if let Some(stat) = TaskStatus::from_str(value) {
+                statuses.push(stat.to_store())
+            }
+        }
+        let length = statuses.len();
+        if  length > 0 {
+            let base = Box::new(status.eq(statuses[0]));
+            let query: Box<dyn BoxableExpression<tasks::table, Pg, SqlType = Bool>> = statuses 
+                .into_iter()
+                .map(|st| status.eq(st))
+                .fold(base, |query, item| {
+                    Box::new(query.or(item))
+                });
+            task_dsl
+                .filter(query)
+                .order((due.asc(), status.asc(), updated_at.desc()))
+                .get_results(conn)
+                .unwrap_or_default()
+        } else {
+            vec![]
+        }
+    }
+
+    //day windows are computed in the client's zone, e.g. :due:today;tomorrow or :deadline:week.
+    //:due: leaves out tasks that are scheduled after the window
+    fn date_filter(column: &str, values: &[String], tz: &Tz, conn: &mut PgConnection) -> Vec<Task> {
+        use super::schema::tasks::dsl::{status, due, scheduled, deadline, updated_at};
+        let current = today(tz);
+        let mut query: Option<Condition> = None;
+        for value in values {
+            let range = match value.as_str() {
+                "today"    => day_range(current, 1, tz),
+                "tomorrow" => current.succ_opt().and_then(|d| day_range(d, 1, tz)),
+                "week"     => day_range(current, 7, tz),
+                _          => None
+            };
+            let Some((start, end)) = range else { continue };
+            let window: Condition = match column {
+                "scheduled" => Box::new(scheduled.ge(start).and(scheduled.lt(end))),
+                "deadline"  => Box::new(deadline.ge(start).and(deadline.lt(end))),
+                _           => Box::new(due.ge(start).and(due.lt(end)).and(scheduled.lt(end).or(scheduled.is_null())))
+            };
+            query = Some(match query {
+                Some(q) => Box::new(q.or(window)),
+                None => window
+            });
+        }
+        match query {
+            Some(q) => task_dsl
+                .filter(not(status.eq(TaskStatus::Deleted.to_store())))
+                .filter(q)
+                .order((due.asc(), status.asc(), updated_at.desc()))
+                .get_results(conn)
+                .unwrap_or_default(),
+            None => vec![]
+        }
+    }
     
 
 
@@ -253,12 +672,100 @@ impl Task {
     }
     
     pub fn delete_task(trg_id: &str, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
-        diesel::delete(task_dsl.find(trg_id))
-            .execute(conn)
+        write_transaction(conn, |conn| {
+            let deleted = diesel::delete(task_dsl.find(trg_id))
+                .get_results::<Task>(conn)?;
+            for task in &deleted {
+                Event::emit(EventKind::Deleted, task, conn)?;
+            }
+            Ok(deleted.len())
+        })
     }
 }
 
 
+//every transaction that records events goes through here. it takes the event lock
+//before anything else, so writers commit one after another and event ids become
+//visible in order: whoever saw id n will never find a lower one committed later
+pub fn write_transaction<T, E, F>(conn: &mut PgConnection, f: F) -> Result<T, E>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, E>,
+    E: From<diesel::result::Error>,
+{
+    conn.transaction(|conn| {
+        Event::lock(conn)?;
+        f(conn)
+    })
+}
+
+//serializes transactions working on the same `key` until they commit
+pub fn advisory_lock(scope: &str, key: &str, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind::<diesel::sql_types::Text, _>(format!("{scope}:{key}"))
+        .execute(conn)
+}
+
+//None keeps the stored date, an empty string clears it
+fn resolve_optional(input: Option<&DueInput>, tz: &Tz) -> Result<Option<Option<DateTime<Utc>>>, TaskError> {
+    match input {
+        None => Ok(None),
+        Some(DueInput::Text(text)) if text.trim().is_empty() => Ok(Some(None)),
+        Some(input) => input.resolve(tz).map(|parsed| Some(Some(parsed.due))).ok_or(TaskError::InvalidDue)
+    }
+}
+
+fn overdue_at(due: Option<DateTime<Utc>>, deadline: Option<DateTime<Utc>>, all_day: bool, at: DateTime<Utc>) -> bool {
+    let grace = if all_day { Duration::days(1) } else { Duration::zero() };
+    [due, deadline].into_iter().flatten().any(|d| d + grace < at)
+}
+
+//the query side of overdue_at
+fn overdue_condition(at: DateTime<Utc>) -> Condition {
+    use super::schema::tasks::dsl::{due, deadline, all_day};
+    let day_ago = at - Duration::days(1);
+    Box::new(
+        all_day.eq(false).and(due.lt(at).or(deadline.lt(at)))
+            .or(all_day.eq(true).and(due.lt(day_ago).or(deadline.lt(day_ago))))
+    )
+}
+
+//tags are matched case insensitive, so they are kept lower case and unique
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized = Vec::<String>::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+pub mod event;
+pub mod reminder;
+pub mod webhook;
+pub mod sync;
+pub mod idempotency;
+pub mod bulk;
+pub mod backup;
+pub mod ics;
+pub mod todotxt;
+pub mod spreadsheet;
+pub mod scan;
+pub mod taskwarrior;
 
 #[cfg(test)]
+#[allow(clippy::len_zero, clippy::unnecessary_literal_unwrap)]
 mod task_tests;
+#[cfg(test)]
+mod reminder_tests;
+#[cfg(test)]
+mod webhook_tests;
+#[cfg(test)]
+mod sync_tests;
+#[cfg(test)]
+mod bulk_tests;
+#[cfg(test)]
+mod backup_tests;
+#[cfg(test)]
+mod scan_tests;
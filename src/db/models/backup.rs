@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashSet};
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::schema::tasks::dsl::tasks as task_dsl;
+use crate::db::schema::reminders::dsl::reminders as reminder_dsl;
+use crate::db::schema::webhooks::dsl::webhooks as webhook_dsl;
+use super::{Task, TaskError, TaskPriority, TaskStatus, write_transaction, event::{Event, EventKind}, reminder::Reminder, webhook::Webhook};
+
+//bumped whenever the document layout changes, `upgrade` brings older backups up to date
+pub const SCHEMA_VERSION: i32 = 1;
+
+//everything needed to move the data to another server. webhook deliveries, events
+//and idempotency keys are history and stay behind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backup {
+    pub schema_version: i32,
+    pub exported_at: DateTime<Utc>,
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>
+}
+
+//merge keeps what is there and takes tasks from the backup that are new or were changed
+//later than the copy here, replace deletes everything first
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    #[default]
+    Merge,
+    Replace,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub mode: ImportMode,
+    //gives every imported row a new id, e.g. to copy tasks next to the existing ones
+    #[serde(default)]
+    pub remap: bool,
+    #[serde(default)]
+    pub dry_run: bool
+}
+
+//something in the backup that keeps it from being imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportIssue {
+    pub entity: String,
+    pub id: Option<String>,
+    pub error: String
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportCounts {
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub deleted: usize
+}
+
+//`committed` is false for dry runs and when there are issues, then nothing was written.
+//`id_map` lists the ids given to remapped rows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub schema_version: i32,
+    pub dry_run: bool,
+    pub committed: bool,
+    pub issues: Vec<ImportIssue>,
+    pub tasks: ImportCounts,
+    pub reminders: ImportCounts,
+    pub webhooks: ImportCounts,
+    pub id_map: BTreeMap<String, String>
+}
+
+impl ImportIssue {
+    fn new(entity: &str, id: Option<&str>, error: &str) -> Self {
+        Self { entity: entity.to_string(), id: id.map(str::to_string), error: error.to_string() }
+    }
+}
+
+pub fn export(conn: &mut PgConnection) -> Backup {
+    use crate::db::schema::tasks::dsl::created_at;
+    Backup {
+        schema_version: SCHEMA_VERSION,
+        exported_at: Utc::now(),
+        tasks: task_dsl.order(created_at.asc()).load::<Task>(conn).unwrap_or_default(),
+        reminders: reminder_dsl.load::<Reminder>(conn).unwrap_or_default(),
+        webhooks: webhook_dsl.load::<Webhook>(conn).unwrap_or_default()
+    }
+}
+
+//brings a backup written by an older server to the current layout. each step takes
+//the document one version further, there are none yet as 1 is the first version
+pub fn upgrade(doc: serde_json::Value) -> Result<Backup, ImportIssue> {
+    let version = doc["schema_version"].as_i64().unwrap_or(0);
+    if version < 1 {
+        return Err(ImportIssue::new("backup", None, "schema_version is missing"))
+    }
+    if version > SCHEMA_VERSION as i64 {
+        return Err(ImportIssue::new("backup", None, "Backup is newer than this server"))
+    }
+    serde_json::from_value::<Backup>(doc).map_err(|err| ImportIssue::new("backup", None, &err.to_string()))
+}
+
+//runs before anything is written. a reminder must point at a task in the backup, or
+//at one already here when merging without remapping
+pub fn validate(backup: &Backup, options: &ImportOptions, conn: &mut PgConnection) -> Vec<ImportIssue> {
+    let mut issues = Vec::<ImportIssue>::new();
+    let mut task_ids = HashSet::<&str>::new();
+    for task in &backup.tasks {
+        if !task_ids.insert(task.id.as_str()) {
+            issues.push(ImportIssue::new("task", Some(&task.id), "Duplicate id"));
+        }
+        if task.name.trim().is_empty() {
+            issues.push(ImportIssue::new("task", Some(&task.id), "name is missing"));
+        }
+        if TaskStatus::from_store(task.status).is_none() {
+            issues.push(ImportIssue::new("task", Some(&task.id), "Unknown status"));
+        }
+        if TaskPriority::from_store(task.priority).is_none() {
+            issues.push(ImportIssue::new("task", Some(&task.id), "Unknown priority"));
+        }
+    }
+    let mut reminder_ids = HashSet::<&str>::new();
+    for reminder in &backup.reminders {
+        if !reminder_ids.insert(reminder.id.as_str()) {
+            issues.push(ImportIssue::new("reminder", Some(&reminder.id), "Duplicate id"));
+        }
+        let known = task_ids.contains(reminder.task_id.as_str())
+            || (options.mode == ImportMode::Merge && !options.remap && Task::by_id(&reminder.task_id, conn).is_some());
+        if !known {
+            issues.push(ImportIssue::new("reminder", Some(&reminder.id), "Unknown task"));
+        }
+        if reminder.remind_at.is_none() && reminder.minutes_before.is_none() {
+            issues.push(ImportIssue::new("reminder", Some(&reminder.id), "remind_at or minutes_before required"));
+        }
+    }
+    let mut webhook_ids = HashSet::<&str>::new();
+    for webhook in &backup.webhooks {
+        if !webhook_ids.insert(webhook.id.as_str()) {
+            issues.push(ImportIssue::new("webhook", Some(&webhook.id), "Duplicate id"));
+        }
+        if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+            issues.push(ImportIssue::new("webhook", Some(&webhook.id), "url must be http or https"));
+        }
+    }
+    issues
+}
+
+//one transaction, a dry run or a backup with issues is rolled back. a store error
+//rolls it back too and is the error, the report is only for the document
+pub fn restore(mut backup: Backup, options: ImportOptions, conn: &mut PgConnection) -> Result<ImportReport, TaskError> {
+    let mut report = ImportReport { schema_version: backup.schema_version, dry_run: options.dry_run, ..Default::default() };
+    report.issues = validate(&backup, &options, conn);
+    if !report.issues.is_empty() {
+        return Ok(report)
+    }
+    if options.remap {
+        remap(&mut backup, &mut report.id_map);
+    }
+    let outcome = write_transaction::<(), diesel::result::Error, _>(conn, |conn| {
+        if options.mode == ImportMode::Replace {
+            for task in task_dsl.load::<Task>(conn)? {
+                report.tasks.deleted += Task::delete_task(&task.id, conn)?;
+            }
+            report.webhooks.deleted = diesel::delete(webhook_dsl).execute(conn)?;
+        }
+        for task in &backup.tasks {
+            restore_task(task, &mut report.tasks, conn)?;
+        }
+        for reminder in &backup.reminders {
+            match Reminder::by_id(&reminder.id, conn) {
+                Some(_) => report.reminders.skipped += 1,
+                None => {
+                    diesel::insert_into(reminder_dsl).values(reminder).execute(conn)?;
+                    report.reminders.created += 1;
+                }
+            }
+        }
+        for webhook in &backup.webhooks {
+            match Webhook::by_id(&webhook.id, conn) {
+                Some(_) => report.webhooks.skipped += 1,
+                None => {
+                    //backups leave secrets out, a restored webhook gets a new one
+                    let secret = Some(webhook.secret.clone()).filter(|s| !s.is_empty()).unwrap_or_else(Webhook::new_secret);
+                    let webhook = Webhook { secret, ..webhook.clone() };
+                    diesel::insert_into(webhook_dsl).values(&webhook).execute(conn)?;
+                    report.webhooks.created += 1;
+                }
+            }
+        }
+        match options.dry_run {
+            true => Err(diesel::result::Error::RollbackTransaction),
+            false => Ok(())
+        }
+    });
+    match outcome {
+        Ok(()) => report.committed = true,
+        Err(diesel::result::Error::RollbackTransaction) if options.dry_run => {},
+        Err(err) => return Err(err.into())
+    }
+    Ok(report)
+}
+
+//a task already here is only replaced by a copy changed after it, its version keeps counting up
+fn restore_task(task: &Task, counts: &mut ImportCounts, conn: &mut PgConnection) -> QueryResult<()> {
+    match Task::by_id(&task.id, conn) {
+        None => {
+            let created = diesel::insert_into(task_dsl).values(task).get_result::<Task>(conn)?;
+            Event::emit(EventKind::Created, &created, conn)?;
+            counts.created += 1;
+        },
+        Some(current) if task.updated_at > current.updated_at => {
+            Task::save(task.clone(), current.version, conn).map_err(|_| diesel::result::Error::RollbackTransaction)?;
+            counts.updated += 1;
+        },
+        Some(_) => counts.skipped += 1
+    }
+    Ok(())
+}
+
+fn remap(backup: &mut Backup, id_map: &mut BTreeMap<String, String>) {
+    let mut fresh = |old: &str| -> String {
+        let new = Uuid::new_v4().hyphenated().to_string();
+        id_map.insert(old.to_string(), new.clone());
+        new
+    };
+    for task in &mut backup.tasks {
+        task.id = fresh(&task.id);
+    }
+    for reminder in &mut backup.reminders {
+        reminder.id = fresh(&reminder.id);
+    }
+    for webhook in &mut backup.webhooks {
+        webhook.id = fresh(&webhook.id);
+    }
+    for reminder in &mut backup.reminders {
+        if let Some(task_id) = id_map.get(&reminder.task_id) {
+            reminder.task_id = task_id.clone();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::Utc;
+use serial_test::serial;
+
+use crate::db::establish_connection;
+use super::Task;
+use super::reminder::{Reminder, ReminderTime};
+use super::backup::{export, restore, upgrade, Backup, ImportMode, ImportOptions, SCHEMA_VERSION};
+
+fn backup_of(tasks: Vec<Task>, reminders: Vec<Reminder>) -> Backup {
+    Backup { schema_version: SCHEMA_VERSION, exported_at: Utc::now(), tasks, reminders, webhooks: Vec::new() }
+}
+
+#[test]
+#[serial]
+fn export_contains_tasks_and_reminders() {
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_backup_export", None, Some(Utc::now() + chrono::Duration::days(2)), &mut conn).unwrap();
+    let reminder = Reminder::create(&task.id, ReminderTime::Before(30), &mut conn).unwrap();
+    let backup = export(&mut conn);
+    assert_eq!(backup.schema_version, SCHEMA_VERSION);
+    assert!(backup.tasks.iter().any(|t| t.id == task.id));
+    assert!(backup.reminders.iter().any(|r| r.id == reminder.id));
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn merge_and_remap() {
+    let mut conn = establish_connection().get().unwrap();
+    let mut task = Task::new("test_backup_merge", None, Some(Utc::now() + chrono::Duration::days(2)));
+    let reminder = Reminder::new(&task, ReminderTime::Before(15));
+    let report = restore(backup_of(vec![task.clone()], vec![reminder.clone()]), ImportOptions::default(), &mut conn).unwrap();
+    assert!(report.committed, "{:?}", report.issues);
+    assert_eq!((report.tasks.created, report.reminders.created), (1, 1));
+    assert_eq!(Reminder::for_task(&task.id, &mut conn).len(), 1);
+    task = Task::by_id(&task.id, &mut conn).unwrap();
+
+    //an unchanged copy is skipped, a newer one wins
+    let skipped = restore(backup_of(vec![task.clone()], Vec::new()), ImportOptions::default(), &mut conn).unwrap();
+    assert_eq!(skipped.tasks.skipped, 1);
+    task.name = "test_backup_merged".to_string();
+    task.updated_at = Utc::now() + chrono::Duration::seconds(5);
+    let merged = restore(backup_of(vec![task.clone()], Vec::new()), ImportOptions::default(), &mut conn).unwrap();
+    assert_eq!(merged.tasks.updated, 1);
+    let stored = Task::by_id(&task.id, &mut conn).unwrap();
+    assert_eq!((stored.name.as_str(), stored.version), ("test_backup_merged", 2));
+
+    let options = ImportOptions { mode: ImportMode::Merge, remap: true, dry_run: false };
+    let copied = restore(backup_of(vec![task.clone()], vec![reminder.clone()]), options, &mut conn).unwrap();
+    assert_eq!(copied.tasks.created, 1);
+    let copy_id = copied.id_map.get(&task.id).unwrap();
+    assert_ne!(copy_id, &task.id);
+    assert_eq!(Reminder::for_task(copy_id, &mut conn).len(), 1);
+
+    let _ = Task::delete_task(&task.id, &mut conn);
+    let _ = Task::delete_task(copy_id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn invalid_backups_are_reported() {
+    let mut conn = establish_connection().get().unwrap();
+    let mut task = Task::new(" ", None, None);
+    task.status = 9;
+    let mut orphan = Reminder::new(&task, ReminderTime::Before(5));
+    orphan.task_id = "missing".to_string();
+    let report = restore(backup_of(vec![task.clone()], vec![orphan]), ImportOptions::default(), &mut conn).unwrap();
+    assert!(!report.committed);
+    assert_eq!(report.issues.len(), 3);
+    assert!(Task::by_id(&task.id, &mut conn).is_none());
+
+    assert!(upgrade(serde_json::json!({"tasks": []})).is_err());
+    assert!(upgrade(serde_json::json!({"schema_version": SCHEMA_VERSION + 1, "tasks": []})).is_err());
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+
+use crate::db::schema::tasks::dsl::tasks as task_dsl;
+use crate::services::task::TaskUpdate;
+use crate::utils::date::{localize, DueInput};
+use super::{Task, TaskError, TaskStatus, normalize_tags, write_transaction};
+
+//one operation applied to every targeted task
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOp {
+    SetStatus { status: String },
+    AddTag { tag: String },
+    RemoveTag { tag: String },
+    //null or an empty string takes tasks out of their project
+    MoveProject { project: Option<String> },
+    ShiftDue { days: i64 },
+    Delete,
+}
+
+//the tasks to work on, either by id or everything a `Task::filter` expression finds
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkTarget {
+    Ids(Vec<String>),
+    Filter(String),
+}
+
+//applied: written. unchanged: nothing to do, e.g. the tag was already there.
+//not_found: no task with that id. failed: see error, nothing was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkOutcome {
+    Applied,
+    Unchanged,
+    NotFound,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkResult {
+    pub id: String,
+    pub outcome: BulkOutcome,
+    pub task: Option<Task>,
+    pub error: Option<String>
+}
+
+//`committed` is false for dry runs and when a task failed, then every write was rolled back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkReport {
+    pub dry_run: bool,
+    pub committed: bool,
+    pub results: Vec<BulkResult>
+}
+
+impl BulkResult {
+    fn new(id: &str, outcome: BulkOutcome, task: Option<Task>) -> Self {
+        Self { id: id.to_string(), outcome, task, error: None }
+    }
+
+    fn failed(id: &str, error: &str) -> Self {
+        Self { error: Some(error.to_string()), ..Self::new(id, BulkOutcome::Failed, None) }
+    }
+}
+
+impl BulkOp {
+    //checked before anything is touched, the error when the operation is invalid
+    pub fn validate(&self) -> Option<&'static str> {
+        match self {
+            BulkOp::SetStatus { status } if TaskStatus::from_str(status).is_none() => Some("Unknown status"),
+            BulkOp::AddTag { tag } | BulkOp::RemoveTag { tag } if tag.trim().is_empty() => Some("tag is missing"),
+            _ => None
+        }
+    }
+}
+
+//everything runs in one transaction with the targeted rows locked. a dry run does
+//all the writes and rolls them back, so the results show exactly what would happen
+pub fn apply(target: &BulkTarget, op: &BulkOp, dry_run: bool, tz: &Tz, conn: &mut PgConnection) -> BulkReport {
+    let mut results = Vec::<BulkResult>::new();
+    let outcome = write_transaction::<(), diesel::result::Error, _>(conn, |conn| {
+        let ids = match target {
+            BulkTarget::Ids(ids) => ids.clone(),
+            BulkTarget::Filter(text) => Task::filter(text, tz, conn).into_iter().map(|t| t.id).collect()
+        };
+        task_dsl
+            .filter(crate::db::schema::tasks::dsl::id.eq_any(&ids))
+            .for_update()
+            .load::<Task>(conn)?;
+        results = ids.iter().map(|id| apply_one(id, op, tz, conn)).collect();
+        match dry_run || results.iter().any(|r| r.outcome == BulkOutcome::Failed) {
+            true => Err(diesel::result::Error::RollbackTransaction),
+            false => Ok(())
+        }
+    });
+    BulkReport { dry_run, committed: outcome.is_ok(), results }
+}
+
+fn apply_one(id: &str, op: &BulkOp, tz: &Tz, conn: &mut PgConnection) -> BulkResult {
+    let Some(current) = Task::by_id(id, conn) else {
+        return BulkResult::new(id, BulkOutcome::NotFound, None)
+    };
+    let result = match op {
+        BulkOp::Delete => {
+            return match Task::delete_task(id, conn) {
+                Ok(_) => BulkResult::new(id, BulkOutcome::Applied, Some(current)),
+                Err(_) => BulkResult::failed(id, "Could not delete task")
+            }
+        },
+        BulkOp::SetStatus { status } => {
+            let Some(status) = TaskStatus::from_str(status).map(|s| s.to_store()) else {
+                return BulkResult::failed(id, "Unknown status")
+            };
+            if status == current.status {
+                return BulkResult::new(id, BulkOutcome::Unchanged, Some(current))
+            }
+            Task::set_status(id, status, Some(current.version), conn)
+        },
+        BulkOp::ShiftDue { days } if current.due.is_some_and(|due| shift(due, *days, tz).is_none()) => {
+            return BulkResult::failed(id, "Due date out of range")
+        },
+        _ => match change(op, &current, tz) {
+            Some(update) => Task::update(update, tz, conn),
+            None => return BulkResult::new(id, BulkOutcome::Unchanged, Some(current))
+        }
+    };
+    match result {
+        Ok(task) => BulkResult::new(id, BulkOutcome::Applied, Some(task)),
+        Err(TaskError::Conflict(_)) => BulkResult::failed(id, "Task changed meanwhile"),
+        Err(TaskError::InvalidDue) => BulkResult::failed(id, "Could not parse due date"),
+        Err(TaskError::NotFound) => BulkResult::new(id, BulkOutcome::NotFound, None),
+        Err(TaskError::Duplicate(_)) => BulkResult::failed(id, "Duplicate name"),
+        Err(TaskError::Database) => BulkResult::failed(id, "Could not store task")
+    }
+}
+
+//the update for tag, project and due operations, None when the task already looks like that
+fn change(op: &BulkOp, current: &Task, tz: &Tz) -> Option<TaskUpdate> {
+    let mut update = TaskUpdate {
+        id: current.id.clone(),
+        name: current.name.clone(),
+        description: current.description.clone(),
+        status: current.status,
+        due: current.due.map(DueInput::from),
+        tags: None,
+        project: None,
+        priority: None,
+        recurrence: None,
+        scheduled: None,
+        deadline: None,
+        all_day: Some(current.all_day),
+        version: Some(current.version),
+        created_at: current.created_at,
+        updated_at: current.updated_at
+    };
+    match op {
+        BulkOp::AddTag { tag } => {
+            let tags = normalize_tags([current.tags.clone(), vec![tag.clone()]].concat());
+            update.tags = Some(tags).filter(|t| t != &current.tags);
+            update.tags.as_ref()?;
+        },
+        BulkOp::RemoveTag { tag } => {
+            let tag = tag.trim().to_lowercase();
+            current.tags.contains(&tag).then_some(())?;
+            update.tags = Some(current.tags.iter().filter(|t| **t != tag).cloned().collect());
+        },
+        BulkOp::MoveProject { project } => {
+            let project = project.clone().filter(|p| !p.is_empty());
+            (project != current.project).then_some(())?;
+            update.project = Some(project.unwrap_or_default());
+        },
+        BulkOp::ShiftDue { days } => {
+            let due = current.due?;
+            (*days != 0).then_some(())?;
+            update.due = Some(DueInput::from(shift(due, *days, tz)?));
+        },
+        BulkOp::SetStatus { .. } | BulkOp::Delete => return None
+    }
+    Some(update)
+}
+
+//moves the local date in `tz` and keeps the local time, so a DST change in between does
+//not move the task an hour. None when the result is past what a date can hold
+fn shift(due: DateTime<Utc>, days: i64, tz: &Tz) -> Option<DateTime<Utc>> {
+    let local = due.with_timezone(tz);
+    let date = local.date_naive().checked_add_signed(Duration::try_days(days)?)?;
+    Some(localize(date.and_time(local.time()), tz))
+}
@@ -0,0 +1,87 @@
+use chrono::{Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use serial_test::serial;
+
+use crate::db::establish_connection;
+use super::Task;
+use super::bulk::{apply, BulkOp, BulkOutcome, BulkTarget};
+
+#[test]
+#[serial]
+fn bulk_tags_and_dry_run() {
+    let mut conn = establish_connection().get().unwrap();
+    let first = Task::create("test_bulk_first", None, None, &mut conn).unwrap();
+    let second = Task::create("test_bulk_second", None, None, &mut conn).unwrap();
+    let target = BulkTarget::Ids(vec![first.id.clone(), second.id.clone(), "missing".to_string()]);
+    let op = BulkOp::AddTag { tag: "Sprint".to_string() };
+
+    let report = apply(&target, &op, true, &Tz::UTC, &mut conn);
+    assert!(!report.committed);
+    let outcomes = report.results.iter().map(|r| r.outcome).collect::<Vec<BulkOutcome>>();
+    assert_eq!(outcomes, vec![BulkOutcome::Applied, BulkOutcome::Applied, BulkOutcome::NotFound]);
+    assert_eq!(report.results[0].task.as_ref().unwrap().tags, vec!["sprint".to_string()]);
+    assert!(Task::by_id(&first.id, &mut conn).unwrap().tags.is_empty());
+
+    let report = apply(&target, &op, false, &Tz::UTC, &mut conn);
+    assert!(report.committed);
+    assert_eq!(Task::by_id(&second.id, &mut conn).unwrap().tags, vec!["sprint".to_string()]);
+    let again = apply(&target, &op, false, &Tz::UTC, &mut conn);
+    assert_eq!(again.results[0].outcome, BulkOutcome::Unchanged);
+
+    let removed = apply(&target, &BulkOp::RemoveTag { tag: "sprint".to_string() }, false, &Tz::UTC, &mut conn);
+    assert_eq!(removed.results[1].outcome, BulkOutcome::Applied);
+    assert!(Task::by_id(&second.id, &mut conn).unwrap().tags.is_empty());
+
+    let deleted = apply(&target, &BulkOp::Delete, false, &Tz::UTC, &mut conn);
+    assert!(deleted.committed);
+    assert!(Task::by_id(&first.id, &mut conn).is_none());
+    assert!(Task::by_id(&second.id, &mut conn).is_none());
+}
+
+#[test]
+#[serial]
+fn bulk_shift_due_and_project() {
+    let mut conn = establish_connection().get().unwrap();
+    let due = Utc.with_ymd_and_hms(2030, 5, 10, 9, 0, 0).unwrap();
+    let dated = Task::create("test_bulk_dated", None, Some(due), &mut conn).unwrap();
+    let undated = Task::create("test_bulk_undated", None, None, &mut conn).unwrap();
+    let target = BulkTarget::Ids(vec![dated.id.clone(), undated.id.clone()]);
+
+    let report = apply(&target, &BulkOp::ShiftDue { days: 3 }, false, &Tz::UTC, &mut conn);
+    assert_eq!(report.results[0].outcome, BulkOutcome::Applied);
+    assert_eq!(report.results[1].outcome, BulkOutcome::Unchanged);
+    assert_eq!(Task::by_id(&dated.id, &mut conn).unwrap().due, Some(due + Duration::days(3)));
+    let overflow = apply(&target, &BulkOp::ShiftDue { days: i64::MAX }, false, &Tz::UTC, &mut conn);
+    assert_eq!(overflow.results[0].outcome, BulkOutcome::Failed);
+    assert!(!overflow.committed);
+    assert_eq!(Task::by_id(&dated.id, &mut conn).unwrap().due, Some(due + Duration::days(3)));
+
+    let moved = apply(&target, &BulkOp::MoveProject { project: Some("bulkhome".to_string()) }, false, &Tz::UTC, &mut conn);
+    assert!(moved.results.iter().all(|r| r.outcome == BulkOutcome::Applied));
+    let by_filter = BulkTarget::Filter(":project:bulkhome".to_string());
+    let done = apply(&by_filter, &BulkOp::SetStatus { status: "done".to_string() }, false, &Tz::UTC, &mut conn);
+    assert_eq!(done.results.len(), 2);
+    assert!(Task::by_id(&undated.id, &mut conn).unwrap().status == super::TaskStatus::Done.to_store());
+
+    let _ = Task::delete_task(&dated.id, &mut conn);
+    let _ = Task::delete_task(&undated.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn bulk_shift_keeps_local_time_over_dst() {
+    let mut conn = establish_connection().get().unwrap();
+    let tz: Tz = "Europe/Berlin".parse().unwrap();
+    //local midnight on the 29th of March 2030, clocks go forward on the 31st
+    let due = tz.with_ymd_and_hms(2030, 3, 29, 0, 0, 0).unwrap().with_timezone(&Utc);
+    let task = Task::create("test_bulk_dst", None, Some(due), &mut conn).unwrap();
+    let target = BulkTarget::Ids(vec![task.id.clone()]);
+
+    let report = apply(&target, &BulkOp::ShiftDue { days: 3 }, false, &tz, &mut conn);
+    assert_eq!(report.results[0].outcome, BulkOutcome::Applied);
+    let shifted = Task::by_id(&task.id, &mut conn).unwrap().due.unwrap();
+    assert_eq!(shifted, tz.with_ymd_and_hms(2030, 4, 1, 0, 0, 0).unwrap().with_timezone(&Utc));
+    assert_eq!(shifted - due, Duration::days(3) - Duration::hours(1));
+
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Utc};
+
+use crate::db::schema::events;
+use crate::db::schema::events::dsl::events as event_dsl;
+use super::{Task, advisory_lock};
+use super::webhook::Delivery;
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Identifiable)]
+#[diesel(table_name = events)]
+pub struct Event {
+    pub id: i64,
+    pub kind: String,
+    pub task_id: String,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = events)]
+struct NewEvent<'a> {
+    kind: &'a str,
+    task_id: &'a str,
+    payload: serde_json::Value
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EventKind {
+    Created,
+    Updated,
+    StatusChanged,
+    Overdue,
+    Deleted,
+    Snoozed,
+    Unsnoozed,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_store())
+    }
+}
+
+impl EventKind {
+    pub fn to_store(&self) -> &'static str {
+        match *self {
+            EventKind::Created          => "task.created",
+            EventKind::Updated          => "task.updated",
+            EventKind::StatusChanged    => "task.status_changed",
+            EventKind::Overdue          => "task.overdue",
+            EventKind::Deleted          => "task.deleted",
+            EventKind::Snoozed          => "task.snoozed",
+            EventKind::Unsnoozed        => "task.unsnoozed",
+        }
+    }
+
+    pub fn from_str(kind: &str) -> Option<Self> {
+        match kind {
+            "task.created"          => Some(EventKind::Created),
+            "task.updated"          => Some(EventKind::Updated),
+            "task.status_changed"   => Some(EventKind::StatusChanged),
+            "task.overdue"          => Some(EventKind::Overdue),
+            "task.deleted"          => Some(EventKind::Deleted),
+            "task.snoozed"          => Some(EventKind::Snoozed),
+            "task.unsnoozed"        => Some(EventKind::Unsnoozed),
+            _ => None
+        }
+    }
+}
+
+impl Event {
+
+    //records what happened to a task together with a snapshot of it and queues it
+    //for every subscribed webhook. callers run it in the write_transaction of the change
+    //itself, so the event and the change commit or roll back together
+    pub fn emit(kind: EventKind, task: &Task, conn: &mut PgConnection) -> QueryResult<Self> {
+        //already held when the caller went through write_transaction
+        Event::lock(conn)?;
+        let payload = serde_json::to_value(task).map_err(|err| diesel::result::Error::SerializationError(Box::new(err)))?;
+        let event = diesel::insert_into(event_dsl)
+            .values(&NewEvent { kind: kind.to_store(), task_id: &task.id, payload })
+            .get_result::<Event>(conn)?;
+        Delivery::enqueue(&event, conn)?;
+        Ok(event)
+    }
+
+    //held until commit by every transaction that inserts events, see write_transaction
+    pub fn lock(conn: &mut PgConnection) -> QueryResult<usize> {
+        advisory_lock("events", "order", conn)
+    }
+
+    //events after `after` in the order they happened, for resuming streams
+    pub fn since(after: i64, limit: i64, conn: &mut PgConnection) -> Vec<Self> {
+        use crate::db::schema::events::dsl::id;
+        event_dsl
+            .filter(id.gt(after))
+            .order(id.asc())
+            .limit(limit)
+            .load::<Event>(conn)
+            .unwrap_or_default()
+    }
+
+    //ids of the tasks touched by events in (after, until]
+    pub fn tasks_between(after: i64, until: i64, conn: &mut PgConnection) -> Vec<String> {
+        use crate::db::schema::events::dsl::{id, task_id};
+        event_dsl
+            .filter(id.gt(after))
+            .filter(id.le(until))
+            .select(task_id)
+            .distinct()
+            .load::<String>(conn)
+            .unwrap_or_default()
+    }
+
+    pub fn latest_id(conn: &mut PgConnection) -> i64 {
+        use crate::db::schema::events::dsl::id;
+        event_dsl
+            .select(diesel::dsl::max(id))
+            .first::<Option<i64>>(conn)
+            .ok()
+            .flatten()
+            .unwrap_or(0)
+    }
+
+    //what webhooks and the event stream send out
+    pub fn to_message(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "event": self.kind,
+            "created_at": self.created_at,
+            "task": self.payload
+        })
+    }
+
+    pub fn for_task(trg_id: &str, conn: &mut PgConnection) -> Vec<Self> {
+        use crate::db::schema::events::dsl::{task_id, id};
+        event_dsl
+            .filter(task_id.eq(trg_id))
+            .order(id.asc())
+            .load::<Event>(conn)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono_tz::Tz;
+
+use crate::utils::ical;
+use super::{Task, TaskError, write_transaction};
+
+//a calendar entry that did not become a task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IcsSkipped {
+    pub uid: Option<String>,
+    pub summary: Option<String>,
+    pub reason: String
+}
+
+//properties and nested components of an imported entry that were left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IcsUnsupported {
+    pub uid: String,
+    pub properties: Vec<String>
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IcsReport {
+    pub created: Vec<Task>,
+    pub skipped: Vec<IcsSkipped>,
+    pub unsupported: Vec<IcsUnsupported>
+}
+
+//creates a task per VTODO. the UID becomes the task id, so entries imported before
+//and repeated UIDs are skipped. other components like VEVENT are reported as skipped.
+//a store error rolls the whole file back
+pub fn import(text: &str, tz: &Tz, conn: &mut PgConnection) -> Result<IcsReport, TaskError> {
+    let mut report = IcsReport::default();
+    let mut seen = HashSet::<String>::new();
+    write_transaction::<(), diesel::result::Error, _>(conn, |conn| {
+        for component in ical::parse(text) {
+            let uid = component.text("UID");
+            let summary = component.text("SUMMARY");
+            let skip = |reason: &str| IcsSkipped { uid: uid.clone(), summary: summary.clone(), reason: reason.to_string() };
+            if component.name == "VTIMEZONE" {
+                continue
+            }
+            if component.name != "VTODO" {
+                report.skipped.push(skip(&format!("{} is not supported", component.name)));
+                continue
+            }
+            let (task, unsupported) = match ical::todo_task(&component, tz) {
+                Ok(parsed) => parsed,
+                Err(reason) => {
+                    report.skipped.push(skip(&reason));
+                    continue
+                }
+            };
+            if !seen.insert(task.id.clone()) {
+                report.skipped.push(skip("Duplicate UID"));
+                continue
+            }
+            if Task::by_id(&task.id, conn).is_some() {
+                report.skipped.push(skip("Already imported"));
+                continue
+            }
+            let id = task.id.clone();
+            //a savepoint, so a failed insert does not abort the whole import
+            match conn.transaction(|conn| Task::insert(task, conn).ok_or(diesel::result::Error::RollbackTransaction)) {
+                Ok(created) => report.created.push(created),
+                Err(_) => {
+                    report.skipped.push(skip("Could not create task"));
+                    continue
+                }
+            }
+            if !unsupported.is_empty() {
+                report.unsupported.push(IcsUnsupported { uid: id, properties: unsupported });
+            }
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::db::schema::idempotency_keys;
+use crate::db::schema::idempotency_keys::dsl::idempotency_keys as key_dsl;
+use super::{advisory_lock, write_transaction};
+
+//keys are forgotten after a day, a retry after that is a new request
+const KEEP_HOURS: i64 = 24;
+
+//the response sent for a request made with an Idempotency-Key header. `fingerprint`
+//is a hash of the request body, reusing a key with another body is refused
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Insertable)]
+#[diesel(table_name = idempotency_keys)]
+pub struct IdempotencyKey {
+    pub key: String,
+    pub fingerprint: String,
+    pub status_code: i32,
+    pub response: serde_json::Value,
+    pub created_at: DateTime<Utc>
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyError {
+    //the key was used before for a different request
+    Mismatch,
+    Database,
+}
+
+impl IdempotencyKey {
+
+    pub fn by_key(key_query: &str, conn: &mut PgConnection) -> Option<Self> {
+        key_dsl.find(key_query).first::<IdempotencyKey>(conn).ok()
+    }
+
+    pub fn purge(before: DateTime<Utc>, conn: &mut PgConnection) -> usize {
+        use crate::db::schema::idempotency_keys::dsl::created_at;
+        diesel::delete(key_dsl.filter(created_at.lt(before))).execute(conn).unwrap_or(0)
+    }
+
+    //runs `handle` once per key and replays its response afterwards. the key is locked
+    //for the whole transaction so a retry arriving while the first request is still
+    //running waits for it instead of doing the work again. server errors are not kept
+    pub fn run<F>(key: &str, fingerprint: &str, conn: &mut PgConnection, handle: F) -> Result<(i32, serde_json::Value), KeyError>
+    where
+        F: FnOnce(&mut PgConnection) -> (i32, serde_json::Value)
+    {
+        Self::purge(Utc::now() - Duration::hours(KEEP_HOURS), conn);
+        write_transaction::<Result<(i32, serde_json::Value), KeyError>, diesel::result::Error, _>(conn, |conn| {
+            advisory_lock("idempotency", key, conn)?;
+            if let Some(stored) = Self::by_key(key, conn) {
+                return Ok(match stored.fingerprint == fingerprint {
+                    true => Ok((stored.status_code, stored.response)),
+                    false => Err(KeyError::Mismatch)
+                })
+            }
+            let (status_code, response) = handle(conn);
+            if status_code < 500 {
+                let stored = IdempotencyKey {
+                    key: key.to_string(),
+                    fingerprint: fingerprint.to_string(),
+                    status_code,
+                    response: response.clone(),
+                    created_at: Utc::now()
+                };
+                diesel::insert_into(key_dsl).values(&stored).execute(conn)?;
+            }
+            Ok(Ok((status_code, response)))
+        })
+        .unwrap_or(Err(KeyError::Database))
+    }
+}
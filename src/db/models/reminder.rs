@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::schema::reminders;
+use crate::db::schema::reminders::dsl::reminders as reminder_dsl;
+use crate::db::schema::tasks::dsl::tasks as task_dsl;
+use super::{Task, TaskStatus};
+
+//reminders fired per dispatcher run, the rest wait for the next one
+const BATCH: usize = 100;
+
+//a reminder fires at `remind_at` or `minutes_before` the task is due, `fire_at` holds
+//the resolved time and `fired_at` marks it as sent
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Insertable, Identifiable)]
+#[diesel(table_name = reminders)]
+pub struct Reminder {
+    pub id: String,
+    pub task_id: String,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub minutes_before: Option<i32>,
+    pub fire_at: Option<DateTime<Utc>>,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReminderTime {
+    At(DateTime<Utc>),
+    Before(i32),
+}
+
+impl Reminder {
+
+    pub fn new(task: &Task, time: ReminderTime) -> Self {
+        let (remind_at, minutes_before) = match time {
+            ReminderTime::At(at) => (Some(at), None),
+            ReminderTime::Before(minutes) => (None, Some(minutes))
+        };
+        let mut reminder = Self {
+            id: Uuid::new_v4().hyphenated().to_string(),
+            task_id: task.id.clone(),
+            remind_at,
+            minutes_before,
+            fire_at: None,
+            fired_at: None,
+            created_at: Utc::now()
+        };
+        reminder.fire_at = reminder.fire_time(task);
+        reminder
+    }
+
+    //relative reminders follow the due date, or the deadline if there is none
+    fn fire_time(&self, task: &Task) -> Option<DateTime<Utc>> {
+        match (self.remind_at, self.minutes_before) {
+            (Some(at), _) => Some(at),
+            (None, Some(minutes)) => task.due.or(task.deadline).map(|d| d - Duration::minutes(minutes as i64)),
+            (None, None) => None
+        }
+    }
+
+    pub fn create(task_id: &str, time: ReminderTime, conn: &mut PgConnection) -> Option<Self> {
+        let task = task_dsl.find(task_id).first::<Task>(conn).ok()?;
+        diesel::insert_into(reminder_dsl)
+            .values(&Reminder::new(&task, time))
+            .get_result::<Reminder>(conn)
+            .ok()
+    }
+
+    pub fn by_id(trg_id: &str, conn: &mut PgConnection) -> Option<Self> {
+        reminder_dsl.find(trg_id).first::<Reminder>(conn).ok()
+    }
+
+    pub fn for_task(trg_id: &str, conn: &mut PgConnection) -> Vec<Self> {
+        use crate::db::schema::reminders::dsl::{task_id, created_at};
+        reminder_dsl
+            .filter(task_id.eq(trg_id))
+            .order(created_at.asc())
+            .load::<Reminder>(conn)
+            .unwrap_or_default()
+    }
+
+    //reminders still to be sent for open tasks, the next one first
+    pub fn upcoming(limit: i64, conn: &mut PgConnection) -> Vec<(Self, Task)> {
+        use crate::db::schema::reminders::dsl::{fire_at, fired_at};
+        use crate::db::schema::tasks::dsl::status;
+        reminder_dsl
+            .inner_join(task_dsl)
+            .filter(fired_at.is_null())
+            .filter(fire_at.is_not_null())
+            .filter(status.eq_any(open_statuses()))
+            .order(fire_at.asc())
+            .limit(limit)
+            .load::<(Reminder, Task)>(conn)
+            .unwrap_or_default()
+    }
+
+    //moves pending relative reminders along when the task's dates change
+    pub fn reschedule(task: &Task, conn: &mut PgConnection) {
+        use crate::db::schema::reminders::dsl::{task_id, minutes_before, fired_at, fire_at};
+        let pending = reminder_dsl
+            .filter(task_id.eq(&task.id))
+            .filter(minutes_before.is_not_null())
+            .filter(fired_at.is_null())
+            .load::<Reminder>(conn)
+            .unwrap_or_default();
+        for reminder in pending {
+            let _ = diesel::update(reminder_dsl.find(&reminder.id))
+                .set(fire_at.eq(reminder.fire_time(task)))
+                .execute(conn);
+        }
+    }
+
+    //marks up to BATCH reminders that are due at `at` as fired, each in its own short
+    //transaction, and hands them to `deliver` after the commit. parallel dispatchers skip
+    //the locked row and a slow delivery holds no lock, a crash in between loses that one
+    pub fn fire_due<F>(at: DateTime<Utc>, conn: &mut PgConnection, mut deliver: F) -> usize
+    where
+        F: FnMut(&Reminder, &Task),
+    {
+        use crate::db::schema::reminders::dsl::{task_id, fire_at, fired_at};
+        use crate::db::schema::tasks::dsl::{id, status};
+        let mut fired = 0;
+        while fired < BATCH {
+            let claimed = conn.transaction::<Option<(Reminder, Task)>, diesel::result::Error, _>(|conn| {
+                let open_tasks = task_dsl.select(id).filter(status.eq_any(open_statuses()));
+                let Some(reminder) = reminder_dsl
+                    .filter(fired_at.is_null())
+                    .filter(fire_at.le(at))
+                    .filter(task_id.eq_any(open_tasks))
+                    .order(fire_at.asc())
+                    .for_update()
+                    .skip_locked()
+                    .first::<Reminder>(conn)
+                    .optional()? else {
+                    return Ok(None)
+                };
+                let task = task_dsl.find(&reminder.task_id).first::<Task>(conn)?;
+                diesel::update(reminder_dsl.find(&reminder.id))
+                    .set(fired_at.eq(Utc::now()))
+                    .execute(conn)?;
+                Ok(Some((reminder, task)))
+            });
+            match claimed {
+                Ok(Some((reminder, task))) => {
+                    deliver(&reminder, &task);
+                    fired += 1;
+                },
+                Ok(None) => break,
+                Err(err) => {
+                    log::warn!("could not fire reminder: {err}");
+                    break
+                }
+            }
+        }
+        fired
+    }
+
+    pub fn delete_reminder(trg_id: &str, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+        diesel::delete(reminder_dsl.find(trg_id))
+            .execute(conn)
+    }
+}
+
+fn open_statuses() -> [i32; 2] {
+    [TaskStatus::Created.to_store(), TaskStatus::Overdue.to_store()]
+}
@@ -0,0 +1,81 @@
+use crate::{db::{establish_connection, models::{Task, TaskStatus, reminder::{Reminder, ReminderTime}}}, services::task::TaskUpdate};
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn relative_reminder_follows_due() {
+    let mut conn = establish_connection().get().unwrap();
+    let due = Utc::now() + Duration::days(2);
+    let task = Task::create("test_reminder_relative", None, Some(due), &mut conn).unwrap();
+    let reminder = Reminder::create(&task.id, ReminderTime::Before(30), &mut conn).unwrap();
+    assert_eq!(reminder.fire_at, task.due.map(|d| d - Duration::minutes(30)));
+
+    let moved = task.due.unwrap() + Duration::days(1);
+    let update = TaskUpdate {
+        id: task.id.clone(),
+        name: task.name.clone(),
+        description: task.description.clone(),
+        status: TaskStatus::Created.to_store(),
+        due: Some(moved.into()),
+        tags: None,
+        project: None,
+        priority: None,
+        recurrence: None,
+        scheduled: None,
+        deadline: None,
+        all_day: None,
+        version: None,
+        created_at: task.created_at,
+        updated_at: task.updated_at
+    };
+    Task::update(update, &Tz::UTC, &mut conn).unwrap();
+    let reminders = Reminder::for_task(&task.id, &mut conn);
+    assert_eq!(reminders[0].fire_at, Some(moved - Duration::minutes(30)));
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+//imports, notes and restores write whole tasks, reminders move with them too
+#[test]
+#[serial]
+fn saved_task_moves_reminders() {
+    use crate::db::models::bulk::{apply, BulkOp, BulkTarget};
+    let mut conn = establish_connection().get().unwrap();
+    let due = Utc::now() + Duration::days(2);
+    let task = Task::create("test_reminder_saved", None, Some(due), &mut conn).unwrap();
+    Reminder::create(&task.id, ReminderTime::Before(30), &mut conn).unwrap();
+
+    let moved = task.due.unwrap() + Duration::days(1);
+    let saved = Task::save(Task { due: Some(moved), ..task.clone() }, task.version, &mut conn).unwrap();
+    assert_eq!(saved.version, task.version + 1);
+    assert_eq!(Reminder::for_task(&task.id, &mut conn)[0].fire_at, Some(moved - Duration::minutes(30)));
+
+    apply(&BulkTarget::Ids(vec![task.id.clone()]), &BulkOp::ShiftDue { days: 1 }, false, &Tz::UTC, &mut conn);
+    assert_eq!(Reminder::for_task(&task.id, &mut conn)[0].fire_at, Some(moved + Duration::days(1) - Duration::minutes(30)));
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn due_reminders_fire_once() {
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_reminder_fire", None, None, &mut conn).unwrap();
+    let reminder = Reminder::create(&task.id, ReminderTime::At(Utc::now() - Duration::minutes(1)), &mut conn).unwrap();
+    let later = Reminder::create(&task.id, ReminderTime::At(Utc::now() + Duration::hours(1)), &mut conn).unwrap();
+    let upcoming = Reminder::upcoming(100, &mut conn).into_iter().map(|(r, _)| r.id).collect::<Vec<String>>();
+    assert!(upcoming.contains(&reminder.id));
+
+    let mut delivered = Vec::new();
+    Reminder::fire_due(Utc::now(), &mut conn, |r, _| delivered.push(r.id.clone()));
+    assert!(delivered.contains(&reminder.id));
+    assert!(!delivered.contains(&later.id));
+
+    let mut again = Vec::new();
+    Reminder::fire_due(Utc::now(), &mut conn, |r, _| again.push(r.id.clone()));
+    assert!(!again.contains(&reminder.id));
+    let fired = Reminder::for_task(&task.id, &mut conn);
+    assert!(fired.iter().find(|r| r.id == reminder.id).unwrap().fired_at.is_some());
+    let _ = Task::delete_task(&task.id, &mut conn);
+    assert!(Reminder::for_task(&task.id, &mut conn).is_empty());
+}
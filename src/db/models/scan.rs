@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Utc};
+
+use crate::db::schema::code_comments;
+use crate::db::schema::code_comments::dsl::code_comments as comment_dsl;
+use crate::db::schema::tasks::dsl::tasks as task_dsl;
+use crate::utils::comments::{self, CodeComment};
+use super::{Task, TaskStatus, advisory_lock, write_transaction};
+
+//a comment found by an earlier scan. `task_id` is null once its task was deleted, the
+//comment then stays dismissed. `present` is false while the comment is gone
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Insertable, Identifiable, AsChangeset)]
+#[diesel(table_name = code_comments, primary_key(fingerprint), treat_none_as_null = true)]
+pub struct ScannedComment {
+    pub fingerprint: String,
+    pub root: String,
+    pub task_id: Option<String>,
+    pub path: String,
+    pub line: i32,
+    pub present: bool,
+    pub seen_at: DateTime<Utc>
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub root: String,
+    pub comments: usize,
+    pub created: Vec<Task>,
+    //moved to another line
+    pub updated: Vec<Task>,
+    //closed by an earlier scan and back now
+    pub reopened: Vec<Task>,
+    //their comment is gone
+    pub closed: Vec<Task>,
+    pub unchanged: usize,
+    pub dismissed: usize
+}
+
+fn location(path: &str, line: i32) -> String {
+    format!("{path}:{line}")
+}
+
+//scans `root` and brings its tasks in line with the comments found. the error when
+//`root` is not a readable directory
+pub fn run(root: &Path, project: Option<&str>, conn: &mut PgConnection) -> Result<ScanReport, String> {
+    let root = root.canonicalize().map_err(|e| format!("{}: {e}", root.display()))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()))
+    }
+    let found = comments::scan(&root).map_err(|e| e.to_string())?;
+    let project = project
+        .map(String::from)
+        .or_else(|| root.file_name().map(|n| n.to_string_lossy().to_string()));
+    sync(&root.to_string_lossy(), &found, project.as_deref(), conn).map_err(|e| e.to_string())
+}
+
+//one transaction per root, scans of the same tree wait for each other. the stable
+//fingerprint makes running it again with the same comments change nothing
+pub fn sync(root: &str, found: &[CodeComment], project: Option<&str>, conn: &mut PgConnection) -> QueryResult<ScanReport> {
+    use crate::db::schema::code_comments::dsl::root as root_col;
+    write_transaction(conn, |conn| {
+        advisory_lock("scan", root, conn)?;
+        let mut report = ScanReport { root: root.to_string(), comments: found.len(), ..Default::default() };
+        let known = comment_dsl
+            .filter(root_col.eq(root))
+            .load::<ScannedComment>(conn)?
+            .into_iter()
+            .map(|c| (c.fingerprint.clone(), c))
+            .collect::<HashMap<String, ScannedComment>>();
+        let now = Utc::now();
+        let mut seen = HashSet::<&str>::new();
+        for comment in found {
+            seen.insert(&comment.fingerprint);
+            let here = location(&comment.path, comment.line);
+            let Some(row) = known.get(&comment.fingerprint) else {
+                let name = match comment.text.is_empty() {
+                    true => format!("{} at {here}", comment.marker),
+                    false => comment.text.clone()
+                };
+                let mut task = Task::new(&name, Some(&here), None);
+                task.tags = vec![comment.marker.to_lowercase()];
+                task.project = project.map(String::from);
+                let created = Task::insert(task, conn).ok_or(diesel::result::Error::RollbackTransaction)?;
+                diesel::insert_into(comment_dsl)
+                    .values(&ScannedComment {
+                        fingerprint: comment.fingerprint.clone(),
+                        root: root.to_string(),
+                        task_id: Some(created.id.clone()),
+                        path: comment.path.clone(),
+                        line: comment.line,
+                        present: true,
+                        seen_at: now
+                    })
+                    .execute(conn)?;
+                report.created.push(created);
+                continue
+            };
+            let task = row.task_id.as_deref().and_then(|id| task_dsl.find(id).first::<Task>(conn).ok());
+            match task {
+                None => report.dismissed += 1,
+                Some(mut task) => {
+                    let mut touched = false;
+                    //the location is the first line of the description unless someone changed it
+                    let old = location(&row.path, row.line);
+                    if old != here && task.description.lines().next() == Some(old.as_str()) {
+                        let rest = task.description[old.len()..].to_string();
+                        let expected = task.version;
+                        task = Task { description: format!("{here}{rest}"), updated_at: now, ..task };
+                        task = Task::save(task, expected, conn).map_err(|_| diesel::result::Error::RollbackTransaction)?;
+                        touched = true;
+                    }
+                    if !row.present && task.status == TaskStatus::Done.to_store() {
+                        if let Ok(reopened) = Task::set_status(&task.id, TaskStatus::Created.to_store(), None, conn) {
+                            report.reopened.push(reopened);
+                        }
+                    } else if touched {
+                        report.updated.push(task);
+                    } else {
+                        report.unchanged += 1;
+                    }
+                }
+            }
+            diesel::update(comment_dsl.find(&row.fingerprint))
+                .set(&ScannedComment { path: comment.path.clone(), line: comment.line, present: true, seen_at: now, ..row.clone() })
+                .execute(conn)?;
+        }
+        for row in known.values().filter(|r| r.present && !seen.contains(r.fingerprint.as_str())) {
+            diesel::update(comment_dsl.find(&row.fingerprint))
+                .set(&ScannedComment { present: false, ..row.clone() })
+                .execute(conn)?;
+            let Some(task) = row.task_id.as_deref().and_then(|id| task_dsl.find(id).first::<Task>(conn).ok()) else { continue };
+            let open = task.status == TaskStatus::Created.to_store() || task.status == TaskStatus::Overdue.to_store();
+            if open {
+                if let Ok(closed) = Task::set_status(&task.id, TaskStatus::Done.to_store(), None, conn) {
+                    report.closed.push(closed);
+                }
+            }
+        }
+        Ok(report)
+    })
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use serial_test::serial;
+use uuid::Uuid;
+
+use crate::db::establish_connection;
+use super::{Task, TaskStatus};
+use super::scan::run;
+
+#[test]
+#[serial]
+fn comments_become_tasks_and_close_when_gone() {
+    let mut conn = establish_connection().get().unwrap();
+    let root = std::env::temp_dir().join(format!("doit-scan-{}", Uuid::new_v4().simple()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    let project = format!("test_scan_{}", Uuid::new_v4().simple());
+    let code = "fn main() {\n    // TODO: handle errors\n    run(); // FIXME(ann) leaks\n}\n";
+    fs::write(root.join("src/main.rs"), code).unwrap();
+    fs::write(root.join("notes.txt"), "TODO: not a source file\n").unwrap();
+
+    let report = run(&root, Some(&project), &mut conn).unwrap();
+    assert_eq!(report.created.len(), 2);
+    let todo = report.created.iter().find(|t| t.name == "handle errors").unwrap().clone();
+    assert_eq!(todo.description, "src/main.rs:2");
+    assert_eq!(todo.tags, vec!["todo"]);
+    assert_eq!(todo.project.as_deref(), Some(project.as_str()));
+
+    //running again changes nothing
+    let again = run(&root, Some(&project), &mut conn).unwrap();
+    assert_eq!((again.created.len(), again.unchanged), (0, 2));
+
+    //moving the comment moves the reference, not the task
+    fs::write(root.join("src/main.rs"), format!("use std::io;\n\n{code}")).unwrap();
+    let moved = run(&root, Some(&project), &mut conn).unwrap();
+    assert_eq!(moved.updated.len(), 2);
+    assert_eq!(Task::by_id(&todo.id, &mut conn).unwrap().description, "src/main.rs:4");
+
+    fs::write(root.join("src/main.rs"), "fn main() {\n    run(); // FIXME(ann) leaks\n}\n").unwrap();
+    let closed = run(&root, Some(&project), &mut conn).unwrap();
+    assert_eq!(closed.closed.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), vec![todo.id.as_str()]);
+    assert_eq!(Task::by_id(&todo.id, &mut conn).unwrap().status, TaskStatus::Done.to_store());
+
+    fs::write(root.join("src/main.rs"), code).unwrap();
+    let reopened = run(&root, Some(&project), &mut conn).unwrap();
+    assert_eq!(reopened.reopened.len(), 1);
+    assert_eq!(Task::by_id(&todo.id, &mut conn).unwrap().status, TaskStatus::Created.to_store());
+
+    //a deleted task stays deleted
+    Task::delete_task(&todo.id, &mut conn).unwrap();
+    let dismissed = run(&root, Some(&project), &mut conn).unwrap();
+    assert_eq!((dismissed.created.len(), dismissed.dismissed), (0, 1));
+
+    for task in report.created {
+        let _ = Task::delete_task(&task.id, &mut conn);
+    }
+    let _ = fs::remove_dir_all(&root);
+    assert!(run(&root, None, &mut conn).is_err());
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::Utc;
+use chrono_tz::Tz;
+
+use crate::utils::spreadsheet::{map_headers, set_cell, Column};
+use super::{Task, TaskError, normalize_tags, write_transaction};
+
+//`map` reads headers into fields like "Title:name,Due Date:due", `date_format` holds
+//strftime formats tried in turn, separated by |
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvOptions {
+    #[serde(default)]
+    pub map: String,
+    #[serde(default)]
+    pub date_format: String,
+    #[serde(default)]
+    pub dry_run: bool
+}
+
+//`row` is the line in the file, the header being line 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvRowError {
+    pub row: u64,
+    pub column: Option<String>,
+    pub error: String
+}
+
+//`committed` is false for dry runs and when a row had errors, then nothing was written.
+//`ignored` lists the headers that match no field
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvReport {
+    pub dry_run: bool,
+    pub committed: bool,
+    pub created: Vec<Task>,
+    pub updated: Vec<Task>,
+    pub unchanged: usize,
+    pub errors: Vec<CsvRowError>,
+    pub ignored: Vec<String>
+}
+
+impl CsvOptions {
+    pub fn formats(&self) -> Vec<String> {
+        self.date_format.split('|').filter(|f| !f.trim().is_empty()).map(String::from).collect()
+    }
+}
+
+//a row whose id is a task here updates it, any other row creates a task. an id column
+//needs a version column, which is checked against the task like If-Match. the error when
+//the header row cannot be read into fields
+pub fn import(text: &str, options: &CsvOptions, tz: &Tz, conn: &mut PgConnection) -> Result<CsvReport, String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.iter().map(String::from).collect::<Vec<String>>();
+    let columns = map_headers(&headers, &options.map)?;
+    if !columns.iter().any(|c| matches!(c, Some(Column::Name) | Some(Column::Id))) {
+        return Err("No column is read into name or id".to_string())
+    }
+    if columns.contains(&Some(Column::Id)) && !columns.contains(&Some(Column::Version)) {
+        return Err("A version column is needed to update tasks by id".to_string())
+    }
+    let mut report = CsvReport {
+        dry_run: options.dry_run,
+        ignored: headers.iter().zip(&columns).filter(|(_, c)| c.is_none()).map(|(h, _)| h.clone()).collect(),
+        ..Default::default()
+    };
+    let formats = options.formats();
+    let now = Utc::now();
+    let outcome = write_transaction::<(), diesel::result::Error, _>(conn, |conn| {
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    let row = err.position().map_or(0, |p| p.line());
+                    report.errors.push(CsvRowError { row, column: None, error: err.to_string() });
+                    continue
+                }
+            };
+            let row = record.position().map_or(0, |p| p.line());
+            let error = |column: Option<&String>, error: String| CsvRowError { row, column: column.cloned(), error };
+            let id = record.iter().zip(&columns).find(|(_, c)| **c == Some(Column::Id)).map(|(v, _)| v.trim()).filter(|v| !v.is_empty());
+            let current = id.and_then(|id| Task::by_id(id, conn));
+            let mut task = current.clone().unwrap_or_else(|| {
+                let mut task = Task::new("", None, None);
+                if let Some(id) = id {
+                    task.id = id.to_string();
+                }
+                task
+            });
+            let mut failed = false;
+            let mut expected = None;
+            for ((value, column), header) in record.iter().zip(&columns).zip(&headers) {
+                let Some(column) = column else { continue };
+                let result = match (column, &current) {
+                    (Column::Version, Some(_)) if value.trim().is_empty() => Err("version is missing".to_string()),
+                    (Column::Version, Some(current)) => match value.trim().parse::<i32>() {
+                        Ok(version) if version == current.version => {
+                            expected = Some(version);
+                            Ok(())
+                        },
+                        Ok(_) => Err("Task changed meanwhile".to_string()),
+                        Err(_) => Err(format!("Invalid version {}", value.trim()))
+                    },
+                    _ => set_cell(&mut task, *column, value, &formats, tz, now)
+                };
+                if let Err(message) = result {
+                    report.errors.push(error(Some(header), message));
+                    failed = true;
+                }
+            }
+            if task.name.is_empty() && !failed {
+                report.errors.push(error(None, "name is missing".to_string()));
+                failed = true;
+            }
+            if failed {
+                continue
+            }
+            task.tags = normalize_tags(task.tags);
+            match current {
+                None => match conn.transaction(|conn| Task::insert(task, conn).ok_or(diesel::result::Error::RollbackTransaction)) {
+                    Ok(created) => report.created.push(created),
+                    Err(_) => report.errors.push(error(None, "Could not create task".to_string()))
+                },
+                Some(current) if current == task => report.unchanged += 1,
+                Some(_) => {
+                    task.updated_at = now;
+                    match Task::save(task, expected.unwrap_or_default(), conn) {
+                        Ok(updated) => report.updated.push(updated),
+                        Err(TaskError::Conflict(_)) => report.errors.push(error(None, "Task changed meanwhile".to_string())),
+                        Err(_) => report.errors.push(error(None, "Could not update task".to_string()))
+                    }
+                }
+            }
+        }
+        match options.dry_run || !report.errors.is_empty() {
+            true => Err(diesel::result::Error::RollbackTransaction),
+            false => Ok(())
+        }
+    });
+    if let Err(err) = &outcome {
+        if !options.dry_run && report.errors.is_empty() {
+            report.errors.push(CsvRowError { row: 0, column: None, error: err.to_string() });
+        }
+    }
+    report.committed = outcome.is_ok();
+    Ok(report)
+}
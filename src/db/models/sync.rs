@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::db::schema::tasks::dsl::tasks as task_dsl;
+use crate::services::task::TaskUpdate;
+use crate::utils::date::DueInput;
+use super::{Task, TaskDetails, TaskError, TaskStatus, event::Event};
+
+//what changed since a change token. tokens are event ids handed out by the server,
+//`deleted` lists tasks that no longer exist
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncChanges {
+    pub token: String,
+    pub tasks: Vec<Task>,
+    pub deleted: Vec<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOp {
+    Create,
+    Update,
+    SetStatus,
+    Delete,
+}
+
+//one change made offline. `base_version` is the version the client edited,
+//`client_ts` when the edit happened on the client; fields left out are kept
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SyncMutation {
+    #[serde(rename = "ref", default)]
+    pub reference: Option<String>,
+    pub op: SyncOp,
+    #[serde(default)]
+    pub id: Option<String>,
+    pub client_ts: DateTime<Utc>,
+    #[serde(default)]
+    pub base_version: Option<i32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub status: Option<i32>,
+    #[serde(default)]
+    pub due: Option<DueInput>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    #[serde(default)]
+    pub scheduled: Option<DueInput>,
+    #[serde(default)]
+    pub deadline: Option<DueInput>,
+    #[serde(default)]
+    pub all_day: Option<bool>
+}
+
+//applied: went in as is. overwritten: the task changed on the server meanwhile but
+//the client edit is newer and won. conflict: the server copy is newer and was kept.
+//unchanged: replay of something already done. rejected: invalid, see error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOutcome {
+    Applied,
+    Overwritten,
+    Conflict,
+    Unchanged,
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncResult {
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    pub id: Option<String>,
+    pub outcome: SyncOutcome,
+    pub task: Option<Task>,
+    pub error: Option<String>
+}
+
+impl SyncResult {
+    fn new(mutation: &SyncMutation, outcome: SyncOutcome, task: Option<Task>) -> Self {
+        Self {
+            reference: mutation.reference.clone(),
+            id: task.as_ref().map(|t| t.id.clone()).or_else(|| mutation.id.clone()),
+            outcome,
+            task,
+            error: None
+        }
+    }
+
+    fn rejected(mutation: &SyncMutation, error: &str) -> Self {
+        Self { error: Some(error.to_string()), ..Self::new(mutation, SyncOutcome::Rejected, None) }
+    }
+}
+
+pub fn parse_token(token: &str) -> Option<i64> {
+    token.trim().parse::<i64>().ok().filter(|t| *t >= 0)
+}
+
+//without a token everything is sent, tasks with status deleted included
+pub fn changes_since(since: Option<i64>, conn: &mut PgConnection) -> SyncChanges {
+    let latest = Event::latest_id(conn);
+    let (tasks, deleted) = match since {
+        None => (task_dsl.load::<Task>(conn).unwrap_or_default(), Vec::new()),
+        Some(after) => {
+            let ids = Event::tasks_between(after, latest, conn);
+            let tasks = Task::by_ids(&ids, conn);
+            let deleted = ids
+                .into_iter()
+                .filter(|id| !tasks.iter().any(|t| &t.id == id))
+                .collect::<Vec<String>>();
+            (tasks, deleted)
+        }
+    };
+    SyncChanges { token: latest.to_string(), tasks, deleted }
+}
+
+//mutations are applied oldest client_ts first and answered in the order they came,
+//so clients without refs can match results by position. an edit on an outdated version
+//is still applied when it was made after the server copy was last changed, otherwise
+//the server copy is kept, ties go to the server
+pub fn apply(mutations: Vec<SyncMutation>, tz: &Tz, conn: &mut PgConnection) -> Vec<SyncResult> {
+    let mut order = (0..mutations.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|i| mutations[*i].client_ts);
+    let mut results = vec![None; mutations.len()];
+    for i in order {
+        results[i] = Some(apply_one(&mutations[i], tz, conn));
+    }
+    results.into_iter().flatten().collect()
+}
+
+fn apply_one(m: &SyncMutation, tz: &Tz, conn: &mut PgConnection) -> SyncResult {
+    if m.status.is_some_and(|s| TaskStatus::from_store(s).is_none()) {
+        return SyncResult::rejected(m, "Unknown status")
+    }
+    if m.op == SyncOp::Create {
+        return create(m, tz, conn)
+    }
+    let Some(id) = m.id.as_deref() else {
+        return SyncResult::rejected(m, "id is missing")
+    };
+    let Some(current) = Task::by_id(id, conn) else {
+        return match m.op {
+            SyncOp::Delete => SyncResult::new(m, SyncOutcome::Unchanged, None),
+            _ => SyncResult::rejected(m, "Not Found")
+        }
+    };
+    let stale = m.base_version.is_some_and(|v| v != current.version);
+    if stale && m.client_ts <= current.updated_at {
+        return SyncResult::new(m, SyncOutcome::Conflict, Some(current))
+    }
+    let outcome = if stale { SyncOutcome::Overwritten } else { SyncOutcome::Applied };
+    let result = match m.op {
+        SyncOp::Delete => {
+            return match Task::delete_task(id, conn) {
+                Ok(_) => SyncResult::new(m, outcome, None),
+                Err(_) => SyncResult::rejected(m, "Could not delete task")
+            }
+        },
+        SyncOp::SetStatus => match m.status {
+            Some(status) => Task::set_status(id, status, Some(current.version), conn),
+            None => return SyncResult::rejected(m, "status is missing")
+        },
+        _ => Task::update(merge(m, &current), tz, conn)
+    };
+    match result {
+        Ok(task) => SyncResult::new(m, outcome, Some(task)),
+        Err(TaskError::Conflict(task)) => SyncResult::new(m, SyncOutcome::Conflict, Some(*task)),
+        Err(TaskError::InvalidDue) => SyncResult::rejected(m, "Could not parse due date"),
+        Err(TaskError::NotFound) => SyncResult::rejected(m, "Not Found"),
+        Err(TaskError::Duplicate(_)) => SyncResult::rejected(m, "Duplicate name"),
+        Err(TaskError::Database) => SyncResult::rejected(m, "Could not store task")
+    }
+}
+
+//a client-chosen id makes replaying the same create harmless
+fn create(m: &SyncMutation, tz: &Tz, conn: &mut PgConnection) -> SyncResult {
+    if let Some(existing) = m.id.as_deref().and_then(|id| Task::by_id(id, conn)) {
+        return SyncResult::new(m, SyncOutcome::Unchanged, Some(existing))
+    }
+    let Some(name) = m.name.as_deref().filter(|n| !n.trim().is_empty()) else {
+        return SyncResult::rejected(m, "name is missing")
+    };
+    let resolve = |input: &Option<DueInput>| match input {
+        Some(input) => input.resolve(tz).map(Some).ok_or(()),
+        None => Ok(None)
+    };
+    let (Ok(due), Ok(scheduled), Ok(deadline)) = (resolve(&m.due), resolve(&m.scheduled), resolve(&m.deadline)) else {
+        return SyncResult::rejected(m, "Could not parse due date")
+    };
+    let details = TaskDetails {
+        tags: m.tags.clone().unwrap_or_default(),
+        project: m.project.clone(),
+        priority: m.priority.unwrap_or_default(),
+        recurrence: m.recurrence.clone(),
+        scheduled: scheduled.map(|p| p.due),
+        deadline: deadline.map(|p| p.due),
+        all_day: m.all_day.unwrap_or_else(|| due.or(deadline).is_some_and(|p| p.all_day))
+    };
+    let mut task = Task::new(name, m.description.as_deref(), due.map(|p| p.due)).with_details(details);
+    if let Some(id) = &m.id {
+        task.id = id.clone();
+    }
+    if let Some(status) = m.status {
+        task.status = status;
+    }
+    match Task::insert(task, conn) {
+        Some(task) => SyncResult::new(m, SyncOutcome::Applied, Some(task)),
+        None => SyncResult::rejected(m, "Could not create task")
+    }
+}
+
+//the server copy with the fields the client sent laid over it
+fn merge(m: &SyncMutation, current: &Task) -> TaskUpdate {
+    TaskUpdate {
+        id: current.id.clone(),
+        name: m.name.clone().unwrap_or_else(|| current.name.clone()),
+        description: m.description.clone().unwrap_or_else(|| current.description.clone()),
+        status: m.status.unwrap_or(current.status),
+        due: match &m.due {
+            Some(DueInput::Text(text)) if text.trim().is_empty() => None,
+            Some(input) => Some(input.clone()),
+            None => current.due.map(DueInput::from)
+        },
+        tags: m.tags.clone(),
+        project: m.project.clone(),
+        priority: m.priority,
+        recurrence: m.recurrence.clone(),
+        scheduled: m.scheduled.clone(),
+        deadline: m.deadline.clone(),
+        all_day: m.all_day,
+        version: Some(current.version),
+        created_at: current.created_at,
+        updated_at: current.updated_at
+    }
+}
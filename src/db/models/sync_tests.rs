@@ -0,0 +1,114 @@
+use std::sync::mpsc::channel;
+use std::thread;
+use crate::db::{establish_connection, models::{Task, TaskStatus, write_transaction, event::Event, sync::{self, SyncMutation, SyncOp, SyncOutcome}}};
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+use serial_test::serial;
+use uuid::Uuid;
+
+fn mutation(op: SyncOp, id: &str) -> SyncMutation {
+    serde_json::from_value(serde_json::json!({"op": op, "id": id, "client_ts": Utc::now()})).unwrap()
+}
+
+#[test]
+#[serial]
+fn changes_since_token() {
+    let mut conn = establish_connection().get().unwrap();
+    let token = Event::latest_id(&mut conn);
+    let kept = Task::create("test_sync_kept", None, None, &mut conn).unwrap();
+    let gone = Task::create("test_sync_gone", None, None, &mut conn).unwrap();
+    let _ = Task::delete_task(&gone.id, &mut conn);
+    let changes = sync::changes_since(Some(token), &mut conn);
+    assert!(changes.tasks.iter().any(|t| t.id == kept.id));
+    assert!(changes.deleted.contains(&gone.id));
+    let later = sync::changes_since(sync::parse_token(&changes.token), &mut conn);
+    assert!(!later.tasks.iter().any(|t| t.id == kept.id));
+    let _ = Task::delete_task(&kept.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn mutations_resolve_by_version_then_time() {
+    let mut conn = establish_connection().get().unwrap();
+    let id = Uuid::new_v4().hyphenated().to_string();
+    let mut create = mutation(SyncOp::Create, &id);
+    create.name = Some("test_sync_create".to_string());
+    let results = sync::apply(vec![create.clone(), create], &Tz::UTC, &mut conn);
+    assert_eq!(results[0].outcome, SyncOutcome::Applied);
+    assert_eq!(results[1].outcome, SyncOutcome::Unchanged);
+    let task = results[0].task.clone().unwrap();
+    assert_eq!(task.id, id);
+
+    //applied by client_ts: early goes in, rename was made before that change reached
+    //the server and loses, done is newer and wins although its version is outdated.
+    //results keep the request order
+    let mut early = mutation(SyncOp::Update, &id);
+    early.client_ts = task.updated_at - Duration::minutes(5);
+    early.base_version = Some(task.version);
+    early.name = Some("test_sync_early".to_string());
+    let mut rename = mutation(SyncOp::Update, &id);
+    rename.base_version = Some(task.version);
+    rename.description = Some("from the phone".to_string());
+    let mut done = mutation(SyncOp::SetStatus, &id);
+    done.client_ts = Utc::now() + Duration::seconds(1);
+    done.base_version = Some(task.version);
+    done.status = Some(TaskStatus::Done.to_store());
+    let results = sync::apply(vec![done, rename, early], &Tz::UTC, &mut conn);
+    let outcomes = results.iter().map(|r| r.outcome).collect::<Vec<SyncOutcome>>();
+    assert_eq!(outcomes, vec![SyncOutcome::Overwritten, SyncOutcome::Conflict, SyncOutcome::Applied]);
+    let last = results[0].task.clone().unwrap();
+    assert_eq!(last.name, "test_sync_early");
+    assert_eq!(last.description, "");
+    assert_eq!(last.status, TaskStatus::Done.to_store());
+
+    let mut stale = mutation(SyncOp::Update, &id);
+    stale.client_ts = last.updated_at - Duration::seconds(1);
+    stale.base_version = Some(task.version);
+    stale.name = Some("test_sync_stale".to_string());
+    let results = sync::apply(vec![stale, mutation(SyncOp::Delete, "missing")], &Tz::UTC, &mut conn);
+    assert_eq!(results[0].outcome, SyncOutcome::Conflict);
+    assert_eq!(results[0].task.as_ref().unwrap().name, "test_sync_early");
+    assert_eq!(results[1].outcome, SyncOutcome::Unchanged);
+    let mut unknown = mutation(SyncOp::SetStatus, &id);
+    unknown.status = Some(7);
+    let mut created = mutation(SyncOp::Create, &Uuid::new_v4().hyphenated().to_string());
+    created.name = Some("test_sync_unknown".to_string());
+    created.status = Some(-1);
+    let results = sync::apply(vec![unknown, created.clone()], &Tz::UTC, &mut conn);
+    assert!(results.iter().all(|r| r.outcome == SyncOutcome::Rejected && r.error.as_deref() == Some("Unknown status")));
+    assert!(Task::by_id(created.id.as_deref().unwrap(), &mut conn).is_none());
+    let results = sync::apply(vec![mutation(SyncOp::Delete, &id)], &Tz::UTC, &mut conn);
+    assert_eq!(results[0].outcome, SyncOutcome::Applied);
+    assert!(Task::by_id(&id, &mut conn).is_none());
+}
+
+//a writer that took its event id first but commits last must not fall behind a
+//token handed out in between
+#[test]
+#[serial]
+fn tokens_keep_overlapping_writes() {
+    let pool = establish_connection();
+    let mut conn = pool.get().unwrap();
+    let token = Event::latest_id(&mut conn);
+    let (started, wait) = channel();
+    let slow = thread::spawn({
+        let pool = pool.clone();
+        move || {
+            let mut conn = pool.get().unwrap();
+            write_transaction::<_, diesel::result::Error, _>(&mut conn, |conn| {
+                let task = Task::insert(Task::new("test_sync_slow", None, None), conn).unwrap();
+                started.send(()).unwrap();
+                thread::sleep(std::time::Duration::from_millis(300));
+                Ok(task)
+            }).unwrap()
+        }
+    });
+    wait.recv().unwrap();
+    let fast = Task::create("test_sync_fast", None, None, &mut conn).unwrap();
+    let changes = sync::changes_since(Some(token), &mut conn);
+    let slow = slow.join().unwrap();
+    assert!(changes.tasks.iter().any(|t| t.id == fast.id));
+    assert!(changes.tasks.iter().any(|t| t.id == slow.id));
+    let _ = Task::delete_task(&fast.id, &mut conn);
+    let _ = Task::delete_task(&slow.id, &mut conn);
+}
@@ -1,4 +1,5 @@
-use crate::{db::{establish_connection, models::{Task, TaskStatus}}, services::task::TaskUpdate};
+use crate::{db::{establish_connection, models::{DuplicateMode, Task, TaskDetails, TaskPriority, TaskStatus}}, services::task::TaskUpdate};
+use chrono_tz::Tz;
 use serial_test::serial;
 
 #[test]
@@ -7,7 +8,7 @@ fn create_task_with_description() {
     let mut conn = establish_connection().get().unwrap();
     let name = "test_1";
     let description = Some("test 1 description");
-    let dt = chrono::Local::now().naive_local();
+    let dt = chrono::Utc::now();
     let task = Task::create(name, description, Some(dt), &mut conn);
     let result = task.unwrap();
     assert_eq!(result.name.as_str(), name);
@@ -65,24 +66,67 @@ fn do_update() {
     let mut conn = establish_connection().get().unwrap();
     let name= "test_6";
     let description = "test 6 description";
-    let due = chrono::Local::now().naive_local() - chrono::Duration::hours(1);
+    let due = chrono::Utc::now() - chrono::Duration::hours(1);
     let task_init = Task::create(name, Some(description), Some(due), &mut conn).unwrap();
     let task = Task::by_id(task_init.id.as_str(), &mut conn).unwrap();
     let update = TaskUpdate {
         id: task.id.clone(),
         name: "test_6_upd".to_string(),
         description:  "test 6 description update.".to_owned(),
-        due: Some(chrono::Local::now().naive_local() + chrono::Duration::hours(1)),
+        due: Some((chrono::Utc::now() + chrono::Duration::hours(1)).into()),
         status: TaskStatus::Created.to_store(),
         created_at: task_init.created_at,
-        updated_at: task_init.updated_at
+        updated_at: task_init.updated_at,
+        tags: Some(vec!["Work".to_string()]),
+        project: None,
+        priority: None,
+        recurrence: None,
+        scheduled: None,
+        deadline: None,
+        all_day: None,
+        version: None
     };
     assert_eq!(task.status, TaskStatus::Overdue.to_store());
-    let result = Task::update(update, &mut conn).unwrap();
+    let result = Task::update(update, &Tz::UTC, &mut conn).unwrap();
     assert_eq!(result.name.as_str(), "test_6_upd");
     assert_eq!(result.description.as_str(), "test 6 description update.");
     assert_eq!(result.status, TaskStatus::Created.to_store());
+    assert_eq!(result.tags, vec!["work".to_string()]);
+    assert!(result.updated_at > task.updated_at);
+    assert_eq!(result.version, task.version + 1);
+}
 
+#[test]
+#[serial]
+fn stale_writes_conflict() {
+    use crate::db::models::TaskError;
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_version", None, None, &mut conn).unwrap();
+    let done = Task::set_status(&task.id, TaskStatus::Done.to_store(), Some(task.version), &mut conn).unwrap();
+    assert_eq!(done.version, task.version + 1);
+    match Task::set_status(&task.id, TaskStatus::Created.to_store(), Some(task.version), &mut conn) {
+        Err(TaskError::Conflict(current)) => assert_eq!(*current, done),
+        other => panic!("expected a conflict, got {other:?}")
+    }
+    let update = TaskUpdate {
+        id: task.id.clone(),
+        name: task.name.clone(),
+        description: task.description.clone(),
+        due: None,
+        status: TaskStatus::Created.to_store(),
+        created_at: task.created_at,
+        updated_at: task.updated_at,
+        tags: None,
+        project: None,
+        priority: None,
+        recurrence: None,
+        scheduled: None,
+        deadline: None,
+        all_day: None,
+        version: None
+    };
+    assert!(matches!(Task::update(update, &Tz::UTC, &mut conn), Err(TaskError::Conflict(_))));
+    let _ = Task::delete_task(&task.id, &mut conn);
 }
 
 #[test]
@@ -90,7 +134,7 @@ fn do_update() {
 fn change_status() {
     let mut conn = establish_connection().get().unwrap();
     let task_init = Task::create("test_7",None, None, &mut conn).unwrap();
-    let result = Task::set_status(&task_init.id, TaskStatus::Done.to_store(), &mut conn).unwrap();
+    let result = Task::set_status(&task_init.id, TaskStatus::Done.to_store(), None, &mut conn).unwrap();
     assert_eq!(result.status, TaskStatus::Done.to_store());
 }
 
@@ -118,7 +162,7 @@ fn test_delete() {
 #[test]
 #[serial]
 fn test_overdue() {
-    let due = chrono::Local::now().naive_local() - chrono::Duration::hours(1);
+    let due = chrono::Utc::now() - chrono::Duration::hours(1);
     let mut conn = establish_connection().get().unwrap();
     let task_init = Task::create("test_11", None, Some(due), &mut conn).unwrap();
     assert_eq!(task_init.status, TaskStatus::Overdue.to_store());
@@ -153,18 +197,18 @@ fn test_text_filter_text_and_status() {
     let mut conn = establish_connection().get().unwrap();
     let task5 = Task::create("test_status_5", None, None, &mut conn).unwrap();
     let task1 = Task::create("test_status_1", None, None, &mut conn).unwrap();
-    Task::set_status(&task1.id, TaskStatus::Done.to_store(), &mut conn);
+    let _ = Task::set_status(&task1.id, TaskStatus::Done.to_store(), None, &mut conn);
     let task2 = Task::create("test_status_2", None, None, &mut conn).unwrap();
-    Task::set_status(&task2.id, TaskStatus::Done.to_store(), &mut conn);
+    let _ = Task::set_status(&task2.id, TaskStatus::Done.to_store(), None, &mut conn);
     let task3 = Task::create("test_status_3", None, None, &mut conn).unwrap();
-    Task::set_status(&task3.id, TaskStatus::Deleted.to_store(), &mut conn);
+    let _ = Task::set_status(&task3.id, TaskStatus::Deleted.to_store(), None, &mut conn);
     let task4 = Task::create("test_status_4", None, None, &mut conn).unwrap();
-    Task::set_status(&task4.id, TaskStatus::Deleted.to_store(), &mut conn);
+    let _ = Task::set_status(&task4.id, TaskStatus::Deleted.to_store(), None, &mut conn);
 
 
     let query = ":status:Done;Deleted";
     let mut conn_2 = establish_connection().get().unwrap();
-    let result = Task::filter(query, &mut conn_2);
+    let result = Task::filter(query, &Tz::UTC, &mut conn_2);
     assert!(result.len() > 0);
     assert!(!result.contains(&task5));
     let dones = result.clone().into_iter().filter(|t| t.status == TaskStatus::Done.to_store()).map(|t| t.id).collect::<Vec<String>>();
@@ -176,3 +220,149 @@ fn test_text_filter_text_and_status() {
     assert!(deleteds.contains(&task3.id));
     assert!(deleteds.contains(&task4.id));
 }
+
+#[test]
+#[serial]
+fn test_due_filter_in_zone() {
+    let mut conn = establish_connection().get().unwrap();
+    let tz: Tz = "Pacific/Kiritimati".parse().unwrap();
+    let (start, end) = crate::utils::date::day_range(crate::utils::date::today(&tz), 1, &tz).unwrap();
+    let due_today = start + chrono::Duration::minutes(1);
+    let due_tomorrow = end + chrono::Duration::minutes(1);
+    let task_today = Task::create("test_due_today", None, Some(due_today), &mut conn).unwrap();
+    let task_tomorrow = Task::create("test_due_tomorrow", None, Some(due_tomorrow), &mut conn).unwrap();
+    let today = Task::filter(":due:today", &tz, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    let tomorrow = Task::filter(":due:tomorrow", &tz, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    assert!(today.contains(&task_today.id));
+    assert!(!today.contains(&task_tomorrow.id));
+    assert!(tomorrow.contains(&task_tomorrow.id));
+    let _ = Task::delete_task(&task_today.id, &mut conn);
+    let _ = Task::delete_task(&task_tomorrow.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn test_detail_filters() {
+    let mut conn = establish_connection().get().unwrap();
+    let details = TaskDetails {
+        tags: vec!["Finance".to_string(), "finance".to_string(), "bills".to_string()],
+        project: Some("Home".to_string()),
+        priority: TaskPriority::High.to_store(),
+        recurrence: Some("FREQ=MONTHLY".to_string()),
+        ..Default::default()
+    };
+    let task = Task::create_with_details("test_details", None, None, details, DuplicateMode::Existing, &mut conn).unwrap();
+    assert_eq!(task.tags, vec!["finance".to_string(), "bills".to_string()]);
+    let by_tag = Task::filter(":tag:Finance", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    let by_project = Task::filter(":project:home", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    let by_priority = Task::filter(":priority:high", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    let by_other_tag = Task::filter(":tag:garden", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    assert!(by_tag.contains(&task.id));
+    assert!(by_project.contains(&task.id));
+    assert!(by_priority.contains(&task.id));
+    assert!(!by_other_tag.contains(&task.id));
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn test_all_day_scheduled_and_deadline() {
+    let mut conn = establish_connection().get().unwrap();
+    let (start, end) = crate::utils::date::day_range(crate::utils::date::today(&Tz::UTC), 1, &Tz::UTC).unwrap();
+    let all_day = TaskDetails { all_day: true, ..Default::default() };
+    let task_all_day = Task::create_with_details("test_all_day", None, Some(start), all_day, DuplicateMode::Existing, &mut conn).unwrap();
+    assert_eq!(task_all_day.status, TaskStatus::Created.to_store());
+    let later = TaskDetails { scheduled: Some(end + chrono::Duration::hours(1)), ..Default::default() };
+    let task_later = Task::create_with_details("test_scheduled_later", None, Some(start + chrono::Duration::hours(23)), later, DuplicateMode::Existing, &mut conn).unwrap();
+    let missed = TaskDetails { deadline: Some(chrono::Utc::now() - chrono::Duration::hours(1)), ..Default::default() };
+    let task_missed = Task::create_with_details("test_deadline_missed", None, None, missed, DuplicateMode::Existing, &mut conn).unwrap();
+    assert_eq!(task_missed.status, TaskStatus::Overdue.to_store());
+
+    let today = Task::filter(":due:today", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    assert!(today.contains(&task_all_day.id));
+    assert!(!today.contains(&task_later.id));
+    let scheduled = Task::filter(":scheduled:tomorrow", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    assert!(scheduled.contains(&task_later.id));
+    let tsk = Task::by_id(&task_all_day.id, &mut conn).unwrap();
+    assert_eq!(tsk.status, TaskStatus::Created.to_store());
+    for task in [task_all_day, task_later, task_missed] {
+        let _ = Task::delete_task(&task.id, &mut conn);
+    }
+}
+
+#[test]
+#[serial]
+fn test_snooze_and_wake() {
+    use crate::db::models::event::{Event, EventKind};
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_snooze", None, None, &mut conn).unwrap();
+    let snoozed = Task::snooze(&task.id, Some(chrono::Utc::now() + chrono::Duration::hours(1)), None, &mut conn).unwrap();
+    assert!(snoozed.snoozed_until.is_some());
+    assert!(!Task::list(&mut conn).contains(&snoozed));
+    assert_ne!(Task::next(&mut conn).map(|t| t.id), Some(task.id.clone()));
+    let pending = Task::filter(":snoozed:", &Tz::UTC, &mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    assert!(pending.contains(&task.id));
+
+    Task::snooze(&task.id, Some(chrono::Utc::now() - chrono::Duration::seconds(1)), None, &mut conn).unwrap();
+    let listed = Task::list(&mut conn).into_iter().map(|t| t.id).collect::<Vec<String>>();
+    assert!(listed.contains(&task.id));
+    let kinds = Event::for_task(&task.id, &mut conn).into_iter().map(|e| e.kind).collect::<Vec<String>>();
+    assert_eq!(kinds, vec![EventKind::Created.to_store(), EventKind::Snoozed.to_store(), EventKind::Snoozed.to_store(), EventKind::Unsnoozed.to_store()]);
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+//overdue and waking up follow from the dates, a client's version stays good
+#[test]
+#[serial]
+fn derived_changes_keep_version() {
+    use diesel::prelude::*;
+    use crate::db::schema::tasks::dsl::{tasks, status};
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_derived_version", None, Some(chrono::Utc::now() - chrono::Duration::hours(1)), &mut conn).unwrap();
+    diesel::update(tasks.find(&task.id)).set(status.eq(TaskStatus::Created.to_store())).execute(&mut conn).unwrap();
+    let overdue = Task::by_id(&task.id, &mut conn).unwrap();
+    assert_eq!(overdue.status, TaskStatus::Overdue.to_store());
+    assert_eq!(overdue.version, task.version);
+
+    let snoozed = Task::snooze(&task.id, Some(chrono::Utc::now() - chrono::Duration::seconds(1)), Some(task.version), &mut conn).unwrap();
+    Task::list(&mut conn);
+    let woken = Task::by_id(&task.id, &mut conn).unwrap();
+    assert!(woken.snoozed_until.is_none());
+    assert_eq!(woken.version, snoozed.version);
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn sweep_marks_overdue_without_a_read() {
+    use diesel::prelude::*;
+    use crate::db::schema::{events, tasks::dsl::{tasks, status}};
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("test_overdue_sweep", None, Some(chrono::Utc::now() - chrono::Duration::hours(1)), &mut conn).unwrap();
+    diesel::update(tasks.find(&task.id)).set(status.eq(TaskStatus::Created.to_store())).execute(&mut conn).unwrap();
+
+    assert!(Task::sweep_overdue(&mut conn).unwrap() >= 1);
+    let stored = tasks.find(&task.id).select(status).first::<i32>(&mut conn).unwrap();
+    assert_eq!(stored, TaskStatus::Overdue.to_store());
+    let kinds = events::table.filter(events::task_id.eq(&task.id)).select(events::kind).load::<String>(&mut conn).unwrap();
+    assert!(kinds.contains(&"task.overdue".to_string()));
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
+
+#[test]
+#[serial]
+fn duplicate_names() {
+    use crate::db::models::TaskError;
+    let mut conn = establish_connection().get().unwrap();
+    let name = "test_duplicate";
+    let first = Task::create_with_details(name, None, None, TaskDetails::default(), DuplicateMode::Allow, &mut conn).unwrap();
+    let second = Task::create_with_details(name, None, None, TaskDetails::default(), DuplicateMode::Allow, &mut conn).unwrap();
+    assert_ne!(first.id, second.id);
+    let existing = Task::create_with_details(name, None, None, TaskDetails::default(), DuplicateMode::Existing, &mut conn).unwrap();
+    assert_eq!(Task::by_name(name, &mut conn).map(|t| t.id), Some(existing.id.clone()));
+    let rejected = Task::create_with_details(name, None, None, TaskDetails::default(), DuplicateMode::Reject, &mut conn);
+    assert!(matches!(rejected, Err(TaskError::Duplicate(t)) if t.id == existing.id));
+    let _ = Task::delete_task(&first.id, &mut conn);
+    let _ = Task::delete_task(&second.id, &mut conn);
+    let _ = Task::delete_task(&existing.id, &mut conn);
+}
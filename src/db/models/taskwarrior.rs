@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::Utc;
+
+use crate::db::schema::tasks::dsl::tasks as task_dsl;
+use crate::utils::taskwarrior;
+use super::{Task, TaskError, write_transaction};
+
+//a task that was not imported, `index` counts from 0 in the document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskwarriorSkipped {
+    pub index: usize,
+    pub uuid: Option<String>,
+    pub reason: String
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskwarriorReport {
+    pub created: Vec<Task>,
+    pub updated: Vec<Task>,
+    //already here as they are, or changed here after Taskwarrior did
+    pub unchanged: usize,
+    pub skipped: Vec<TaskwarriorSkipped>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskwarriorError {
+    //the document could not be read
+    Invalid(String),
+    //the store failed, nothing was imported
+    Database
+}
+
+//the fields Taskwarrior knows about, deadline, all_day and recurrence stay as they are
+fn merge(current: &Task, incoming: Task) -> Task {
+    Task {
+        deadline: current.deadline,
+        all_day: current.all_day && incoming.due == current.due,
+        recurrence: current.recurrence.clone(),
+        version: current.version,
+        ..incoming
+    }
+}
+
+//imports what `task export` wrote. a uuid that is a task here updates it when the
+//Taskwarrior copy was modified later, others create tasks keeping their uuid
+pub fn import(text: &str, conn: &mut PgConnection) -> Result<TaskwarriorReport, TaskwarriorError> {
+    let objects = taskwarrior::parse_document(text).map_err(TaskwarriorError::Invalid)?;
+    let mut report = TaskwarriorReport::default();
+    let now = Utc::now();
+    let outcome = write_transaction::<(), diesel::result::Error, _>(conn, |conn| {
+        for (index, object) in objects.iter().enumerate() {
+            let uuid = object.get("uuid").and_then(|u| u.as_str()).map(String::from);
+            let skip = |reason: &str| TaskwarriorSkipped { index, uuid: uuid.clone(), reason: reason.to_string() };
+            let task = match taskwarrior::from_json(object, now) {
+                Ok(task) => task,
+                Err(reason) => {
+                    report.skipped.push(skip(&reason));
+                    continue
+                }
+            };
+            let Some(current) = task_dsl.find(&task.id).first::<Task>(conn).optional()? else {
+                //a savepoint, so a failed insert does not abort the whole import
+                match conn.transaction(|conn| Task::insert(task, conn).ok_or(diesel::result::Error::RollbackTransaction)) {
+                    Ok(created) => report.created.push(created),
+                    Err(_) => report.skipped.push(skip("Could not create task"))
+                }
+                continue
+            };
+            let changed = merge(&current, task);
+            if changed.updated_at <= current.updated_at || changed == current {
+                report.unchanged += 1;
+                continue
+            }
+            match Task::save(changed, current.version, conn) {
+                Ok(updated) => report.updated.push(updated),
+                Err(TaskError::Database) => return Err(diesel::result::Error::RollbackTransaction),
+                Err(_) => report.skipped.push(skip("Could not update task"))
+            }
+        }
+        Ok(())
+    });
+    match outcome {
+        Ok(()) => Ok(report),
+        Err(err) => {
+            log::error!("taskwarrior import rolled back: {err}");
+            Err(TaskwarriorError::Database)
+        }
+    }
+}
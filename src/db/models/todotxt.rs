@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::Utc;
+use chrono_tz::Tz;
+
+use crate::utils::todotxt;
+use super::{advisory_lock, write_transaction, DuplicateMode, Task, TaskError};
+
+//a line that did not become a task, `line` counts from 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoTxtSkipped {
+    pub line: usize,
+    pub text: String,
+    pub reason: String
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoTxtReport {
+    pub created: Vec<Task>,
+    pub skipped: Vec<TodoTxtSkipped>
+}
+
+//creates a task per non empty line. unless `on_duplicate` is allow, lines whose
+//task name is already taken are skipped, so a file can be imported again. a store error
+//rolls the whole file back
+pub fn import(text: &str, tz: &Tz, on_duplicate: DuplicateMode, conn: &mut PgConnection) -> Result<TodoTxtReport, TaskError> {
+    let mut report = TodoTxtReport::default();
+    let now = Utc::now();
+    write_transaction::<(), diesel::result::Error, _>(conn, |conn| {
+        for (index, text) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let skip = |reason: &str| TodoTxtSkipped { line: index + 1, text: text.to_string(), reason: reason.to_string() };
+            let task = match todotxt::parse_line(text, tz, now) {
+                Ok(task) => task,
+                Err(reason) => {
+                    report.skipped.push(skip(&reason));
+                    continue
+                }
+            };
+            if on_duplicate != DuplicateMode::Allow {
+                advisory_lock("task.name", &task.name, conn)?;
+                if Task::by_name(&task.name, conn).is_some() {
+                    report.skipped.push(skip("Duplicate name"));
+                    continue
+                }
+            }
+            //a savepoint, so a failed insert does not abort the whole import
+            match conn.transaction(|conn| Task::insert(task, conn).ok_or(diesel::result::Error::RollbackTransaction)) {
+                Ok(created) => report.created.push(created),
+                Err(_) => report.skipped.push(skip("Could not create task"))
+            }
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::schema::{webhooks, webhook_deliveries};
+use crate::db::schema::webhooks::dsl::webhooks as webhook_dsl;
+use crate::db::schema::webhook_deliveries::dsl::webhook_deliveries as delivery_dsl;
+use crate::db::schema::events::dsl::events as event_dsl;
+use super::event::Event;
+
+//attempts before a delivery is given up, waiting 30s, 1m, 2m, ... in between
+const MAX_ATTEMPTS: i32 = 8;
+const BACKOFF_SECS: i64 = 30;
+//deliveries handed out per run, and how long a claimed one is left to its worker
+const BATCH: i64 = 50;
+const CLAIM_SECS: i64 = 120;
+
+//`events` lists the event kinds like "task.created" it receives, empty means all.
+//the secret is never serialized, only the create response carries it
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Insertable, Identifiable)]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    #[serde(default, skip_serializing)]
+    pub secret: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>
+}
+
+//one event on its way to one webhook, doubles as the delivery log
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, Queryable, Identifiable)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: String,
+    pub event_id: i64,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = webhook_deliveries)]
+struct NewDelivery<'a> {
+    webhook_id: &'a str,
+    event_id: i64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_store())
+    }
+}
+
+impl DeliveryStatus {
+    pub fn to_store(&self) -> &'static str {
+        match *self {
+            DeliveryStatus::Pending     => "pending",
+            DeliveryStatus::Delivered   => "delivered",
+            DeliveryStatus::Failed      => "failed",
+        }
+    }
+}
+
+//what the receiving end answered, a response code of None means it was not reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt {
+    pub response_code: Option<i32>,
+    pub error: Option<String>
+}
+
+impl Webhook {
+
+    pub fn new_secret() -> String {
+        Uuid::new_v4().simple().to_string()
+    }
+
+    pub fn new(url: &str, events: Vec<String>, secret: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().hyphenated().to_string(),
+            url: url.to_string(),
+            secret: secret.unwrap_or_else(Webhook::new_secret),
+            events,
+            active: true,
+            created_at: Utc::now()
+        }
+    }
+
+    pub fn create(url: &str, events: Vec<String>, secret: Option<String>, conn: &mut PgConnection) -> Option<Self> {
+        diesel::insert_into(webhook_dsl)
+            .values(&Webhook::new(url, events, secret))
+            .get_result::<Webhook>(conn)
+            .ok()
+    }
+
+    pub fn list(conn: &mut PgConnection) -> Vec<Self> {
+        use crate::db::schema::webhooks::dsl::created_at;
+        webhook_dsl
+            .order(created_at.asc())
+            .load::<Webhook>(conn)
+            .unwrap_or_default()
+    }
+
+    pub fn by_id(trg_id: &str, conn: &mut PgConnection) -> Option<Self> {
+        webhook_dsl.find(trg_id).first::<Webhook>(conn).ok()
+    }
+
+    pub fn delete_webhook(trg_id: &str, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+        diesel::delete(webhook_dsl.find(trg_id))
+            .execute(conn)
+    }
+
+    pub fn wants(&self, kind: &str) -> bool {
+        self.active && (self.events.is_empty() || self.events.iter().any(|e| e == kind))
+    }
+}
+
+impl Delivery {
+
+    //queues the event for every active webhook subscribed to its kind
+    pub fn enqueue(event: &Event, conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+        use crate::db::schema::webhooks::dsl::active;
+        let hooks = webhook_dsl
+            .filter(active.eq(true))
+            .load::<Webhook>(conn)?;
+        let rows = hooks
+            .iter()
+            .filter(|hook| hook.wants(&event.kind))
+            .map(|hook| NewDelivery { webhook_id: &hook.id, event_id: event.id })
+            .collect::<Vec<NewDelivery>>();
+        if rows.is_empty() {
+            return Ok(0)
+        }
+        diesel::insert_into(delivery_dsl)
+            .values(&rows)
+            .execute(conn)
+    }
+
+    pub fn for_webhook(trg_id: &str, conn: &mut PgConnection) -> Vec<Self> {
+        use crate::db::schema::webhook_deliveries::dsl::{webhook_id, id};
+        delivery_dsl
+            .filter(webhook_id.eq(trg_id))
+            .order(id.desc())
+            .load::<Delivery>(conn)
+            .unwrap_or_default()
+    }
+
+    //puts a delivery back into the queue, whatever happened to it before
+    pub fn redeliver(trg_id: i64, conn: &mut PgConnection) -> Option<Self> {
+        use crate::db::schema::webhook_deliveries::dsl::{status, attempts, next_attempt_at};
+        diesel::update(delivery_dsl.find(trg_id))
+            .set((
+                status.eq(DeliveryStatus::Pending.to_store()),
+                attempts.eq(0),
+                next_attempt_at.eq(Utc::now())
+            ))
+            .get_result::<Delivery>(conn)
+            .ok()
+    }
+
+    //hands up to BATCH pending deliveries that are due at `at` to `send`. a short
+    //transaction claims them by pushing next_attempt_at past the send timeout, so parallel
+    //workers skip them. sending happens outside any transaction and every outcome commits
+    //on its own, a crash in between leaves the delivery pending until the claim runs out
+    pub fn send_due<F>(at: DateTime<Utc>, conn: &mut PgConnection, mut send: F) -> usize
+    where
+        F: FnMut(&Delivery, &Webhook, &Event) -> Attempt,
+    {
+        use crate::db::schema::webhook_deliveries::dsl::{
+            id, status, next_attempt_at, attempts, response_code, last_error, delivered_at
+        };
+        let claimed = conn.transaction::<Vec<Delivery>, diesel::result::Error, _>(|conn| {
+            let due = delivery_dsl
+                .filter(status.eq(DeliveryStatus::Pending.to_store()))
+                .filter(next_attempt_at.le(at))
+                .order(next_attempt_at.asc())
+                .limit(BATCH)
+                .for_update()
+                .skip_locked()
+                .load::<Delivery>(conn)?;
+            let ids = due.iter().map(|d| d.id).collect::<Vec<i64>>();
+            diesel::update(delivery_dsl.filter(id.eq_any(&ids)))
+                .set(next_attempt_at.eq(Utc::now() + Duration::seconds(CLAIM_SECS)))
+                .execute(conn)?;
+            Ok(due)
+        }).unwrap_or_default();
+        for delivery in &claimed {
+            let target = webhook_dsl.find(&delivery.webhook_id).first::<Webhook>(conn)
+                .and_then(|hook| Ok((hook, event_dsl.find(delivery.event_id).first::<Event>(conn)?)));
+            let Ok((hook, event)) = target else {
+                continue
+            };
+            let attempt = send(delivery, &hook, &event);
+            let tries = delivery.attempts + 1;
+            let ok = attempt.error.is_none();
+            let next_status = match (ok, tries >= MAX_ATTEMPTS) {
+                (true, _) => DeliveryStatus::Delivered,
+                (false, true) => DeliveryStatus::Failed,
+                (false, false) => DeliveryStatus::Pending
+            };
+            let stored = diesel::update(delivery_dsl.find(delivery.id))
+                .set((
+                    status.eq(next_status.to_store()),
+                    attempts.eq(tries),
+                    next_attempt_at.eq(Utc::now() + backoff(tries)),
+                    response_code.eq(attempt.response_code),
+                    last_error.eq(attempt.error),
+                    delivered_at.eq(Some(Utc::now()).filter(|_| ok))
+                ))
+                .execute(conn);
+            if let Err(err) = stored {
+                log::warn!("could not record delivery {}: {err}", delivery.id);
+            }
+        }
+        claimed.len()
+    }
+}
+
+//30s after the first failure, doubling with every further one
+pub fn backoff(attempts: i32) -> Duration {
+    Duration::seconds(BACKOFF_SECS << (attempts - 1).clamp(0, 16))
+}
@@ -0,0 +1,43 @@
+use crate::db::{establish_connection, models::{Task, event::Event, webhook::{Attempt, Delivery, DeliveryStatus, Webhook}}};
+use chrono::{Duration, Utc};
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn deliveries_retry_until_sent() {
+    let mut conn = establish_connection().get().unwrap();
+    let hook = Webhook::create("http://127.0.0.1:9/hook", vec!["task.created".to_string()], None, &mut conn).unwrap();
+    assert!(!hook.secret.is_empty());
+    let task = Task::create("test_webhook_created", None, None, &mut conn).unwrap();
+    //other tests may create tasks meanwhile, only look at this one's delivery
+    let created = Event::for_task(&task.id, &mut conn)[0].id;
+    let ours = |conn: &mut _| Delivery::for_webhook(&hook.id, conn).into_iter().filter(|d| d.event_id == created).collect::<Vec<Delivery>>();
+    let queued = ours(&mut conn);
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].status, DeliveryStatus::Pending.to_store());
+
+    let failing = |_: &_, h: &Webhook, _: &_| Attempt {
+        response_code: Some(500),
+        error: Some("responded with 500".to_string()).filter(|_| h.id == hook.id)
+    };
+    Delivery::send_due(Utc::now(), &mut conn, failing);
+    let retried = &ours(&mut conn)[0];
+    assert_eq!(retried.attempts, 1);
+    assert_eq!(retried.status, DeliveryStatus::Pending.to_store());
+    assert!(retried.next_attempt_at > Utc::now());
+
+    let ok = |_: &_, _: &Webhook, _: &_| Attempt { response_code: Some(200), error: None };
+    Delivery::send_due(Utc::now() + Duration::hours(1), &mut conn, ok);
+    let sent = &ours(&mut conn)[0];
+    assert_eq!(sent.status, DeliveryStatus::Delivered.to_store());
+    assert!(sent.delivered_at.is_some());
+
+    //task.deleted is not subscribed
+    let _ = Task::delete_task(&task.id, &mut conn);
+    let deleted = Event::for_task(&task.id, &mut conn).pop().unwrap().id;
+    assert!(!Delivery::for_webhook(&hook.id, &mut conn).iter().any(|d| d.event_id == deleted));
+    let again = Delivery::redeliver(sent.id, &mut conn).unwrap();
+    assert_eq!(again.status, DeliveryStatus::Pending.to_store());
+    assert_eq!(again.attempts, 0);
+    let _ = Webhook::delete_webhook(&hook.id, &mut conn);
+}
@@ -1,5 +1,49 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    code_comments (fingerprint) {
+        fingerprint -> Varchar,
+        root -> Varchar,
+        task_id -> Nullable<Varchar>,
+        path -> Varchar,
+        line -> Int4,
+        present -> Bool,
+        seen_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    events (id) {
+        id -> Int8,
+        kind -> Varchar,
+        task_id -> Varchar,
+        payload -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    idempotency_keys (key) {
+        key -> Varchar,
+        fingerprint -> Varchar,
+        status_code -> Int4,
+        response -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reminders (id) {
+        id -> Varchar,
+        task_id -> Varchar,
+        remind_at -> Nullable<Timestamptz>,
+        minutes_before -> Nullable<Int4>,
+        fire_at -> Nullable<Timestamptz>,
+        fired_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     tasks (id) {
         id -> Varchar,
@@ -9,5 +53,56 @@ diesel::table! {
         due -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        tags -> Array<Text>,
+        project -> Nullable<Varchar>,
+        priority -> Int4,
+        recurrence -> Nullable<Varchar>,
+        scheduled -> Nullable<Timestamptz>,
+        deadline -> Nullable<Timestamptz>,
+        all_day -> Bool,
+        snoozed_until -> Nullable<Timestamptz>,
+        version -> Int4,
+        extensions -> Jsonb,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int8,
+        webhook_id -> Varchar,
+        event_id -> Int8,
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        response_code -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
     }
 }
+
+diesel::table! {
+    webhooks (id) {
+        id -> Varchar,
+        url -> Varchar,
+        secret -> Varchar,
+        events -> Array<Text>,
+        active -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::joinable!(code_comments -> tasks (task_id));
+diesel::joinable!(reminders -> tasks (task_id));
+diesel::joinable!(webhook_deliveries -> events (event_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    code_comments,
+    events,
+    idempotency_keys,
+    reminders,
+    tasks,
+    webhook_deliveries,
+    webhooks,
+);
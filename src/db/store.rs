@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use diesel::PgConnection;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+
+use crate::services::task::TaskUpdate;
+use super::DbPool;
+use super::models::{DuplicateMode, Task, TaskDetails, TaskError};
+
+//where the task endpoints keep their tasks, the same calls as the `Task` methods
+//without the connection. Postgres is the default, a vault directory is the other one
+pub trait TaskStore: Send + Sync {
+    fn list(&self) -> Vec<Task>;
+    fn by_id(&self, id: &str) -> Option<Task>;
+    fn by_name(&self, name: &str) -> Option<Task>;
+    fn next(&self) -> Option<Task>;
+    fn filter(&self, text: &str, tz: &Tz) -> Vec<Task>;
+    fn find_or_create(&self, name: &str, description: Option<&str>, due: Option<DateTime<Utc>>, details: TaskDetails, on_duplicate: DuplicateMode) -> Result<(Task, bool), TaskError>;
+    fn update(&self, update: TaskUpdate, tz: &Tz) -> Result<Task, TaskError>;
+    fn set_status(&self, id: &str, status: i32, expected: Option<i32>) -> Result<Task, TaskError>;
+    fn snooze(&self, id: &str, until: Option<DateTime<Utc>>, expected: Option<i32>) -> Result<Task, TaskError>;
+    fn delete(&self, id: &str) -> Result<usize, TaskError>;
+
+    //the database behind the store, for what only Postgres does like Idempotency-Key
+    fn pool(&self) -> Option<&DbPool> {
+        None
+    }
+}
+
+pub struct PgStore(pub DbPool);
+
+impl PgStore {
+    fn conn(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.0.get().expect("Failed to connect")
+    }
+}
+
+impl TaskStore for PgStore {
+    fn list(&self) -> Vec<Task> {
+        Task::list(&mut self.conn())
+    }
+
+    fn by_id(&self, id: &str) -> Option<Task> {
+        Task::by_id(id, &mut self.conn())
+    }
+
+    fn by_name(&self, name: &str) -> Option<Task> {
+        Task::by_name(name, &mut self.conn())
+    }
+
+    fn next(&self) -> Option<Task> {
+        Task::next(&mut self.conn())
+    }
+
+    fn filter(&self, text: &str, tz: &Tz) -> Vec<Task> {
+        Task::filter(text, tz, &mut self.conn())
+    }
+
+    fn find_or_create(&self, name: &str, description: Option<&str>, due: Option<DateTime<Utc>>, details: TaskDetails, on_duplicate: DuplicateMode) -> Result<(Task, bool), TaskError> {
+        Task::find_or_create(name, description, due, details, on_duplicate, &mut self.conn())
+    }
+
+    fn update(&self, update: TaskUpdate, tz: &Tz) -> Result<Task, TaskError> {
+        Task::update(update, tz, &mut self.conn())
+    }
+
+    fn set_status(&self, id: &str, status: i32, expected: Option<i32>) -> Result<Task, TaskError> {
+        Task::set_status(id, status, expected, &mut self.conn())
+    }
+
+    fn snooze(&self, id: &str, until: Option<DateTime<Utc>>, expected: Option<i32>) -> Result<Task, TaskError> {
+        Task::snooze(id, until, expected, &mut self.conn())
+    }
+
+    fn delete(&self, id: &str) -> Result<usize, TaskError> {
+        Ok(Task::delete_task(id, &mut self.conn())?)
+    }
+
+    fn pool(&self) -> Option<&DbPool> {
+        Some(&self.0)
+    }
+}
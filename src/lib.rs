@@ -0,0 +1,10 @@
+//the enums read names with from_str returning an Option, not FromStr
+#![allow(clippy::should_implement_trait)]
+
+pub mod db;
+pub mod services;
+pub mod utils;
+pub mod notify;
+pub mod collab;
+pub mod vault;
+pub mod client;
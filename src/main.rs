@@ -1,6 +1,4 @@
-mod db;
-mod services;
-mod utils;
+use todo_api::{db, services, notify, collab, vault};
 
 use services::task::{
     create, 
@@ -8,20 +6,117 @@ use services::task::{
     get_by_id, 
     task_update, 
     set_status,
-    filter_text
+    filter_text,
+    parse_due,
+    quick_add,
+    snooze,
+    next_task
 };
+use services::reminder::{
+    create_reminder,
+    task_reminders,
+    upcoming_reminders,
+    delete_reminder
+};
+use services::webhook::{
+    create_webhook,
+    list_webhooks,
+    delete_webhook,
+    webhook_deliveries,
+    redeliver
+};
+use services::stream::event_stream;
+use services::collab::collab_socket;
+use services::sync::{pull, push};
+use services::bulk::bulk_tasks;
+use services::backup::{export, import_resource};
+use services::calendar::{calendar_feed, import_ics};
+use services::todotxt::{export_todotxt, import_todotxt};
+use services::spreadsheet::{export_csv, import_csv};
+use services::notes::{export_markdown, export_org};
+use services::scan::scan_tree;
+use services::taskwarrior::{export_taskwarrior, import_taskwarrior};
+use services::caldav::{
+    well_known,
+    dav_options,
+    root_propfind,
+    collection_propfind,
+    collection_report,
+    task_propfind,
+    get_task_ics,
+    put_task_ics,
+    delete_task_ics
+};
+
+//the task endpoints on Markdown files, the features that need Postgres are left out
+async fn serve_vault(dir: std::path::PathBuf, rest_host: String, rest_port: u16) -> std::io::Result<()> {
+    use actix_cors::Cors;
+    use actix_web::{App, web, HttpServer};
+    use actix_web::middleware::Logger;
+    let store: std::sync::Arc<dyn db::store::TaskStore> = std::sync::Arc::new(vault::VaultStore::open(&dir)?);
+    log::info!("vault keeps tasks in {}", dir.display());
+    HttpServer::new(move || {
+        App::new()
+            .wrap(Logger::default())
+            .wrap(Cors::permissive())
+            .app_data(web::Data::from(store.clone()))
+            .service(index)
+            .service(filter_text)
+            .service(parse_due)
+            .service(create)
+            .service(quick_add)
+            .service(next_task)
+            .service(get_by_id)
+            .service(set_status)
+            .service(task_update)
+            .service(snooze)
+    })
+        .bind((rest_host, rest_port))?
+        .run()
+        .await
+}
 
 const HOST: &str = "127.0.0.1";
 const PORT: u16 = 8080;
 
+//`scan <dir> [--project <name>]` prints what a scan of the tree changed
+fn scan_command(args: &[String]) -> i32 {
+    let (mut dir, mut project) = (None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--project" => project = args.next().cloned(),
+            _ => dir = Some(arg.clone())
+        }
+    }
+    let Some(dir) = dir else {
+        eprintln!("usage: scan <dir> [--project <name>]");
+        return 2
+    };
+    let mut conn = db::establish_connection().get().expect("Failed to connect");
+    match db::models::scan::run(std::path::Path::new(&dir), project.as_deref(), &mut conn) {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            0
+        },
+        Err(err) => {
+            eprintln!("{err}");
+            1
+        }
+    }
+}
+
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
     use dotenv::dotenv;
     use actix_cors::Cors;
     use actix_web::{App, web, HttpServer};
     use actix_web::middleware::Logger;
-    use env_logger;
     dotenv().ok(); 
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(String::as_str) == Some("scan") {
+        std::process::exit(scan_command(&args[2..]));
+    }
     let rest_host = std::env::var("REST_HOST").unwrap_or(HOST.to_string());
     let rest_port = std::env::var("REST_PORT")
         .unwrap_or(PORT.to_string())
@@ -30,7 +125,17 @@ async fn main() -> std::io::Result<()> {
  
     println!("INFO: will connect to host: {rest_host} and port: {rest_port}");
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-    
+    if let Some(dir) = vault::from_env() {
+        return serve_vault(dir, rest_host, rest_port).await
+    }
+    notify::dispatch::spawn(db::establish_connection(), notify::from_env(), notify::dispatch::interval());
+    let hub = {
+        let pool = db::establish_connection();
+        let latest = db::models::event::Event::latest_id(&mut pool.get().expect("Failed to connect"));
+        web::Data::new(collab::Hub::new(latest))
+    };
+    collab::spawn_pump(hub.clone().into_inner(), db::establish_connection());
+
     HttpServer::new(move || {
         let conn_pool = db::establish_connection();
         let cors = Cors::permissive();
@@ -38,12 +143,53 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .wrap(cors)
             .app_data(web::Data::new(conn_pool))
+            .app_data(hub.clone())
             .service(index)
             .service(filter_text)
+            .service(parse_due)
             .service(create)
+            .service(quick_add)
+            .service(next_task)
+            .service(upcoming_reminders)
+            .service(delete_reminder)
+            .service(event_stream)
+            .service(collab_socket)
+            .service(pull)
+            .service(push)
+            .service(bulk_tasks)
+            .service(export)
+            .service(import_resource())
+            .service(calendar_feed)
+            .service(import_ics)
+            .service(export_todotxt)
+            .service(import_todotxt)
+            .service(export_csv)
+            .service(import_csv)
+            .service(export_markdown)
+            .service(export_org)
+            .service(export_taskwarrior)
+            .service(import_taskwarrior)
+            .service(scan_tree)
+            .service(well_known)
+            .service(dav_options)
+            .service(root_propfind)
+            .service(collection_propfind)
+            .service(collection_report)
+            .service(task_propfind)
+            .service(get_task_ics)
+            .service(put_task_ics)
+            .service(delete_task_ics)
+            .service(list_webhooks)
+            .service(create_webhook)
+            .service(delete_webhook)
+            .service(webhook_deliveries)
+            .service(redeliver)
             .service(get_by_id)
             .service(set_status)
             .service(task_update)
+            .service(snooze)
+            .service(create_reminder)
+            .service(task_reminders)
     })
         .bind((rest_host, rest_port))?
         .run()
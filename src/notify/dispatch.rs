@@ -0,0 +1,47 @@
+use std::env;
+use std::thread;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use diesel::PgConnection;
+
+use crate::db::DbPool;
+use crate::db::models::{Task, reminder::Reminder};
+use super::{Notifier, webhook};
+
+const INTERVAL_SECS: u64 = 30;
+
+//REMINDER_INTERVAL in seconds, how often the dispatcher looks for overdue tasks,
+//due reminders and pending webhook deliveries
+pub fn interval() -> Duration {
+    let secs = env::var("REMINDER_INTERVAL")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+//sends every reminder due at `at`, a failed delivery is logged and not retried
+pub fn dispatch(notifier: &dyn Notifier, at: DateTime<Utc>, conn: &mut PgConnection) -> usize {
+    Reminder::fire_due(at, conn, |reminder, task| {
+        if let Err(err) = notifier.notify(reminder, task) {
+            log::warn!("reminder {} for task {} failed: {err}", reminder.id, task.id);
+        }
+    })
+}
+
+pub fn spawn(pool: DbPool, notifier: Box<dyn Notifier>, every: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        match pool.get() {
+            Ok(mut conn) => {
+                if let Err(err) = Task::sweep_overdue(&mut conn) {
+                    log::warn!("overdue sweep failed: {err}");
+                }
+                dispatch(notifier.as_ref(), Utc::now(), &mut conn);
+                webhook::dispatch(Utc::now(), &mut conn);
+            },
+            Err(err) => log::warn!("reminder dispatcher has no connection: {err}")
+        }
+        thread::sleep(every);
+    })
+}
@@ -0,0 +1,136 @@
+use std::env;
+use std::error::Error;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::json;
+
+use crate::db::models::{Task, reminder::Reminder};
+
+pub mod dispatch;
+pub mod webhook;
+
+pub type NotifyError = Box<dyn Error + Send + Sync + 'static>;
+
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+//a command still running after this is killed
+const COMMAND_TIMEOUT_SECS: u64 = 10;
+const COMMAND_POLL_MILLIS: u64 = 50;
+
+//how a fired reminder reaches the user, REMINDER_NOTIFIER picks one of
+//"log" (default), "webhook" or "command"
+pub trait Notifier: Send + Sync {
+    fn notify(&self, reminder: &Reminder, task: &Task) -> Result<(), NotifyError>;
+}
+
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, reminder: &Reminder, task: &Task) -> Result<(), NotifyError> {
+        log::info!("reminder {}: {} (due {:?})", reminder.id, task.name, task.due);
+        Ok(())
+    }
+}
+
+//POSTs {"reminder": ..., "task": ...} to REMINDER_WEBHOOK_URL
+pub struct WebhookNotifier {
+    pub url: String
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, reminder: &Reminder, task: &Task) -> Result<(), NotifyError> {
+        ureq::post(&self.url)
+            .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .send_json(json!({ "reminder": reminder, "task": task }))?;
+        Ok(())
+    }
+}
+
+//runs REMINDER_COMMAND, e.g. "notify-send doit", with the task name as last argument
+//and DOIT_TASK_ID, DOIT_TASK_NAME and DOIT_TASK_DUE set
+pub struct CommandNotifier {
+    pub program: String,
+    pub args: Vec<String>,
+    pub timeout: Duration
+}
+
+impl CommandNotifier {
+    pub fn parse(command: &str) -> Option<Self> {
+        let mut parts = command.split_whitespace().map(String::from);
+        let program = parts.next()?;
+        Some(Self { program, args: parts.collect(), timeout: Duration::from_secs(COMMAND_TIMEOUT_SECS) })
+    }
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&self, _reminder: &Reminder, task: &Task) -> Result<(), NotifyError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(&task.name)
+            .env("DOIT_TASK_ID", &task.id)
+            .env("DOIT_TASK_NAME", &task.name)
+            .env("DOIT_TASK_DUE", task.due.map(|d| d.to_rfc3339()).unwrap_or_default())
+            .spawn()?;
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status
+            }
+            if started.elapsed() >= self.timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} did not finish within {:?}", self.program, self.timeout).into())
+            }
+            thread::sleep(Duration::from_millis(COMMAND_POLL_MILLIS));
+        };
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} exited with {status}", self.program).into())
+        }
+    }
+}
+
+pub fn from_env() -> Box<dyn Notifier> {
+    let kind = env::var("REMINDER_NOTIFIER").unwrap_or_default();
+    match kind.as_str() {
+        "webhook" => match env::var("REMINDER_WEBHOOK_URL") {
+            Ok(url) => return Box::new(WebhookNotifier { url }),
+            Err(_) => log::warn!("REMINDER_WEBHOOK_URL is not set, logging reminders instead")
+        },
+        "command" => match env::var("REMINDER_COMMAND").ok().as_deref().and_then(CommandNotifier::parse) {
+            Some(command) => return Box::new(command),
+            None => log::warn!("REMINDER_COMMAND is not set, logging reminders instead")
+        },
+        _ => {}
+    }
+    Box::new(LogNotifier)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_command_times_out() {
+        let command = CommandNotifier {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), "sleep 5".to_string()],
+            timeout: Duration::from_millis(200)
+        };
+        let task = Task::new("Pay rent", None, None);
+        let reminder = Reminder {
+            id: "r1".to_string(),
+            task_id: task.id.clone(),
+            remind_at: None,
+            minutes_before: None,
+            fire_at: None,
+            fired_at: None,
+            created_at: Utc::now()
+        };
+        let started = Instant::now();
+        assert!(command.notify(&reminder, &task).is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use diesel::PgConnection;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::db::models::{event::Event, webhook::{Attempt, Delivery, Webhook}};
+
+const TIMEOUT_SECS: u64 = 10;
+pub const SIGNATURE_HEADER: &str = "X-Doit-Signature";
+pub const EVENT_HEADER: &str = "X-Doit-Event";
+pub const DELIVERY_HEADER: &str = "X-Doit-Delivery";
+
+//"sha256=<hex>" of the raw body keyed with the webhook secret, receivers compute
+//the same over the bytes they got and compare
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+//anything but a 2xx answer counts as failed and is retried. the delivery id stays the
+//same across retries, so receivers can drop one they already handled
+pub fn send(delivery: &Delivery, hook: &Webhook, event: &Event) -> Attempt {
+    let body = event.to_message().to_string();
+    let result = ureq::post(&hook.url)
+        .timeout(Duration::from_secs(TIMEOUT_SECS))
+        .set("Content-Type", "application/json")
+        .set(EVENT_HEADER, &event.kind)
+        .set(DELIVERY_HEADER, &delivery.id.to_string())
+        .set(SIGNATURE_HEADER, &sign(&hook.secret, body.as_bytes()))
+        .send_string(&body);
+    match result {
+        Ok(resp) => Attempt { response_code: Some(resp.status() as i32), error: None },
+        Err(ureq::Error::Status(code, _)) => Attempt {
+            response_code: Some(code as i32),
+            error: Some(format!("responded with {code}"))
+        },
+        Err(err) => Attempt { response_code: None, error: Some(err.to_string()) }
+    }
+}
+
+pub fn dispatch(at: DateTime<Utc>, conn: &mut PgConnection) -> usize {
+    Delivery::send_due(at, conn, send)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign() {
+        //echo -n '{"id":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", br#"{"id":1}"#),
+            "sha256=03def589620c813f198fd03d7967e292b163ef0435ebf43071ce0e9519763cb7"
+        );
+    }
+
+    #[test]
+    fn test_send_signs_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break
+                }
+                headers.push(line.trim().to_ascii_lowercase());
+            }
+            let length = headers
+                .iter()
+                .find_map(|h| h.strip_prefix("content-length: ").map(|l| l.parse::<usize>().unwrap()))
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").unwrap();
+            (headers, body)
+        });
+        let hook = Webhook::new(&url, Vec::new(), Some("secret".to_string()));
+        let event = Event {
+            id: 7,
+            kind: "task.created".to_string(),
+            task_id: "abc".to_string(),
+            payload: json!({"id": "abc"}),
+            created_at: Utc::now()
+        };
+        let delivery = Delivery {
+            id: 11,
+            webhook_id: hook.id.clone(),
+            event_id: event.id,
+            status: "pending".to_string(),
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+            response_code: None,
+            last_error: None,
+            delivered_at: None,
+            created_at: Utc::now()
+        };
+        let attempt = send(&delivery, &hook, &event);
+        assert_eq!(attempt, Attempt { response_code: Some(204), error: None });
+        let (headers, body) = server.join().unwrap();
+        let signature = format!("x-doit-signature: {}", sign("secret", &body));
+        assert!(headers.contains(&signature));
+        assert!(headers.contains(&"x-doit-event: task.created".to_string()));
+        assert!(headers.contains(&"x-doit-delivery: 11".to_string()));
+    }
+}
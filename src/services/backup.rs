@@ -0,0 +1,41 @@
+use actix_web::{Resource, Responder, web, get, HttpResponse, http::header};
+use serde_json::Value;
+
+use crate::db::{DbPool, models::backup::{self, ImportOptions, ImportReport}};
+
+//backups are far bigger than the default 2MB json limit
+const IMPORT_LIMIT: usize = 64 * 1024 * 1024;
+
+//every task, reminder and webhook as one versioned JSON document
+#[get("/export")]
+pub async fn export(pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    let backup = backup::export(&mut conn);
+    let filename = format!("doit-{}.json", backup.exported_at.format("%Y%m%d-%H%M%S"));
+    HttpResponse::Ok()
+        .insert_header(header::ContentDisposition::attachment(filename))
+        .json(backup)
+}
+
+//POST /import, registered with its own json limit so other routes keep the default
+pub fn import_resource() -> Resource {
+    web::resource("/import")
+        .app_data(web::JsonConfig::default().limit(IMPORT_LIMIT))
+        .route(web::post().to(import))
+}
+
+//?mode=merge|replace&remap=true&dry_run=true, a backup with issues gets 422 and the report,
+//500 when the store failed and the restore was rolled back
+async fn import(doc: web::Json<Value>, options: web::Query<ImportOptions>, pool: web::Data<DbPool>) -> impl Responder {
+    let options = options.into_inner();
+    let backup = match backup::upgrade(doc.into_inner()) {
+        Ok(backup) => backup,
+        Err(issue) => return HttpResponse::UnprocessableEntity().json(ImportReport { dry_run: options.dry_run, issues: vec![issue], ..Default::default() })
+    };
+    let mut conn = pool.get().unwrap();
+    match backup::restore(backup, options, &mut conn) {
+        Ok(report) if report.issues.is_empty() => HttpResponse::Ok().json(report),
+        Ok(report) => HttpResponse::UnprocessableEntity().json(report),
+        Err(_) => HttpResponse::InternalServerError().json("Could not restore backup")
+    }
+}
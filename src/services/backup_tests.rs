@@ -0,0 +1,64 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body_json, init_service, TestRequest}
+};
+use serde_json::{json, Value};
+use crate::db::{models::Task, establish_connection};
+
+use super::backup::{export, import_resource};
+use super::task::create;
+
+#[actix_rt::test]
+async fn export_then_import() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(export).service(import_resource())).await;
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("endpoint_test_backup", None, None, &mut conn).unwrap();
+
+    let resp = TestRequest::get().uri("/export").send_request(&app).await;
+    assert!(resp.status().is_success());
+    assert!(resp.headers().contains_key("content-disposition"));
+    let mut backup: Value = read_body_json(resp).await;
+    let exported = backup["tasks"].as_array().unwrap().iter().find(|t| t["id"] == task.id.as_str()).unwrap().clone();
+    backup["tasks"] = json!([exported]);
+    backup["reminders"] = json!([]);
+    backup["webhooks"] = json!([]);
+
+    let resp = TestRequest::post().uri("/import?dry_run=true&remap=true").set_json(&backup).send_request(&app).await;
+    assert!(resp.status().is_success());
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["committed"], false);
+    assert_eq!(report["tasks"]["created"], 1);
+    let copy_id = report["id_map"][task.id.as_str()].as_str().unwrap().to_string();
+    assert!(Task::by_id(&copy_id, &mut conn).is_none());
+
+    let resp = TestRequest::post().uri("/import").set_json(&backup).send_request(&app).await;
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["committed"], true);
+    assert_eq!(report["tasks"]["skipped"], 1);
+
+    backup["schema_version"] = json!(99);
+    let resp = TestRequest::post().uri("/import").set_json(&backup).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 422);
+
+    //a valid document the database refuses, postgres text cannot hold NUL
+    backup["schema_version"] = json!(1);
+    backup["tasks"][0]["id"] = json!(uuid::Uuid::new_v4().to_string());
+    backup["tasks"][0]["name"] = json!("endpoint_test_backup\u{0}");
+    let resp = TestRequest::post().uri("/import").set_json(&backup).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 500);
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+//only /import takes big documents
+#[actix_rt::test]
+async fn import_limit_is_scoped() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(import_resource()).service(create)).await;
+    let big = json!({"name": "endpoint_test_big", "description": "x".repeat(3 * 1024 * 1024)});
+    let resp = TestRequest::post().uri("/create").set_json(&big).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 413);
+    let resp = TestRequest::post().uri("/import?dry_run=true").set_json(json!({"schema_version": 1, "tasks": [big]})).send_request(&app).await;
+    assert_ne!(resp.status().as_u16(), 413);
+}
@@ -0,0 +1,39 @@
+use actix_web::{Responder, web, post, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::bulk::{self, BulkOp, BulkTarget}};
+use super::extract::UserTz;
+
+//{"ids": [...]} or {"filter": ":project:home"}, the operation as "op" with its fields,
+//e.g. {"filter": ":tag:sprint", "op": "set_status", "status": "done", "dry_run": true}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkForm {
+    #[serde(default)]
+    ids: Option<Vec<String>>,
+    #[serde(default)]
+    filter: Option<String>,
+    #[serde(flatten)]
+    op: BulkOp,
+    #[serde(default)]
+    dry_run: bool
+}
+
+//all or nothing, when one task fails the report comes back with 422 and nothing is written
+#[post("/tasks/bulk")]
+pub async fn bulk_tasks(form: web::Json<BulkForm>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let form = form.into_inner();
+    let target = match (form.ids, form.filter) {
+        (Some(ids), None) => BulkTarget::Ids(ids),
+        (None, Some(filter)) if !filter.trim().is_empty() => BulkTarget::Filter(filter),
+        _ => return HttpResponse::BadRequest().json("Either ids or filter required")
+    };
+    if let Some(error) = form.op.validate() {
+        return HttpResponse::BadRequest().json(error)
+    }
+    let mut conn = pool.get().unwrap();
+    let report = bulk::apply(&target, &form.op, form.dry_run, &tz.0, &mut conn);
+    match report.committed || report.dry_run {
+        true => HttpResponse::Ok().json(report),
+        false => HttpResponse::UnprocessableEntity().json(report)
+    }
+}
@@ -0,0 +1,51 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body_json, init_service, TestRequest}
+};
+use serde_json::{json, Value};
+use crate::db::{models::{Task, TaskStatus}, establish_connection};
+
+use super::bulk::bulk_tasks;
+
+#[actix_rt::test]
+async fn bulk_set_status() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(bulk_tasks)).await;
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("endpoint_test_bulk", None, None, &mut conn).unwrap();
+
+    let resp = TestRequest::post()
+        .uri("/tasks/bulk")
+        .set_json(json!({"op": "set_status", "status": "done"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = TestRequest::post()
+        .uri("/tasks/bulk")
+        .set_json(json!({"ids": [task.id], "op": "set_status", "status": "someday"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    let resp = TestRequest::post()
+        .uri("/tasks/bulk")
+        .set_json(json!({"ids": [task.id], "op": "set_status", "status": "done", "dry_run": true}))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success());
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["committed"], false);
+    assert_eq!(report["results"][0]["outcome"], "applied");
+    assert_eq!(Task::by_id(&task.id, &mut conn).unwrap().status, TaskStatus::Created.to_store());
+
+    let resp = TestRequest::post()
+        .uri("/tasks/bulk")
+        .set_json(json!({"ids": [task.id], "op": "set_status", "status": "done"}))
+        .send_request(&app)
+        .await;
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["committed"], true);
+    assert_eq!(Task::by_id(&task.id, &mut conn).unwrap().status, TaskStatus::Done.to_store());
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
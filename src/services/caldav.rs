@@ -0,0 +1,278 @@
+use actix_web::{Responder, web, route, HttpRequest, HttpResponse, http::{StatusCode, header}};
+use chrono::{DateTime, Utc};
+
+use crate::db::{DbPool, models::{Task, TaskError, event::Event}};
+use crate::utils::date::{default_tz, DueInput};
+use crate::utils::dav::{self, CALDAV_NS, CS_NS, DAV_NS, DavResponse, PropName, PropRequest, Report, prop};
+use crate::utils::ical;
+use super::extract::{DavAuth, IfMatch};
+use super::task::{etag, TaskUpdate};
+
+//a single task collection holding one VTODO resource per task. the root doubles
+//as principal and calendar home
+const ROOT: &str = "/dav/";
+const COLLECTION: &str = "/dav/tasks/";
+const ICS_TYPE: &str = "text/calendar; charset=utf-8";
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+enum Depth {
+    Zero,
+    One,
+}
+
+//no header means infinity, which is answered like 1 as the tree is only that deep
+fn depth(req: &HttpRequest) -> Depth {
+    match req.headers().get("Depth").and_then(|v| v.to_str().ok()).map(str::trim) {
+        Some("0") => Depth::Zero,
+        _ => Depth::One
+    }
+}
+
+fn href(task: &Task) -> String {
+    format!("{COLLECTION}{}.ics", dav::encode(&task.id))
+}
+
+//the task id of a resource name like abc.ics
+fn name_id(name: &str) -> Option<String> {
+    let name = name.strip_suffix(".ics").unwrap_or(name);
+    (!name.is_empty() && !name.contains('/')).then(|| dav::decode(name))
+}
+
+//the task id of a resource href, which may be a full URL
+fn task_id(href: &str) -> Option<String> {
+    name_id(&href[href.find(COLLECTION)? + COLLECTION.len()..])
+}
+
+fn http_date(at: DateTime<Utc>) -> String {
+    at.format(HTTP_DATE).to_string()
+}
+
+fn quoted(version: i32) -> String {
+    format!("\"{version}\"")
+}
+
+fn calendar_data(task: &Task) -> String {
+    ical::calendar(std::slice::from_ref(task), &default_tz(), Utc::now())
+}
+
+//splits what a resource has into the requested properties it has and those it has not
+fn respond(href: String, known: Vec<(PropName, String)>, request: &PropRequest) -> DavResponse {
+    if request.all {
+        return DavResponse { href, found: known, ..Default::default() }
+    }
+    let mut response = DavResponse { href, ..Default::default() };
+    for name in &request.props {
+        match known.iter().find(|(k, _)| k == name) {
+            Some(found) => response.found.push(found.clone()),
+            None => response.missing.push(name.clone())
+        }
+    }
+    response
+}
+
+fn principal() -> String {
+    format!("<d:href>{ROOT}</d:href>")
+}
+
+fn root_response(request: &PropRequest) -> DavResponse {
+    let known = vec![
+        (prop(DAV_NS, "resourcetype"), "<d:collection/>".to_string()),
+        (prop(DAV_NS, "displayname"), "doit".to_string()),
+        (prop(DAV_NS, "current-user-principal"), principal()),
+        (prop(DAV_NS, "principal-URL"), principal()),
+        (prop(CALDAV_NS, "calendar-home-set"), principal()),
+    ];
+    respond(ROOT.to_string(), known, request)
+}
+
+//the ctag changes with every event, clients compare it to skip a full listing
+fn collection_response(request: &PropRequest, ctag: i64) -> DavResponse {
+    let reports = ["calendar-query", "calendar-multiget"]
+        .iter()
+        .map(|r| format!("<d:supported-report><d:report><c:{r}/></d:report></d:supported-report>"))
+        .collect::<String>();
+    let known = vec![
+        (prop(DAV_NS, "resourcetype"), "<d:collection/><c:calendar/>".to_string()),
+        (prop(DAV_NS, "displayname"), "doit".to_string()),
+        (prop(DAV_NS, "current-user-principal"), principal()),
+        (prop(DAV_NS, "current-user-privilege-set"), "<d:privilege><d:read/></d:privilege><d:privilege><d:write/></d:privilege>".to_string()),
+        (prop(DAV_NS, "supported-report-set"), reports),
+        (prop(CALDAV_NS, "supported-calendar-component-set"), "<c:comp name=\"VTODO\"/>".to_string()),
+        (prop(CS_NS, "getctag"), ctag.to_string()),
+    ];
+    respond(COLLECTION.to_string(), known, request)
+}
+
+//calendar-data only when asked for by name, it is not part of allprop
+fn task_response(task: &Task, request: &PropRequest) -> DavResponse {
+    let mut known = vec![
+        (prop(DAV_NS, "resourcetype"), String::new()),
+        (prop(DAV_NS, "getetag"), dav::escape_xml(&quoted(task.version))),
+        (prop(DAV_NS, "getcontenttype"), "text/calendar; charset=utf-8; component=VTODO".to_string()),
+        (prop(DAV_NS, "getlastmodified"), http_date(task.updated_at)),
+    ];
+    if !request.all && request.wants(CALDAV_NS, "calendar-data") {
+        //XML parsers turn CRLF into LF unless the CR is a character reference
+        known.push((prop(CALDAV_NS, "calendar-data"), dav::escape_xml(&calendar_data(task)).replace('\r', "&#13;")));
+    }
+    respond(href(task), known, request)
+}
+
+fn multistatus(responses: &[DavResponse]) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(dav::multistatus(responses))
+}
+
+fn bad_xml() -> HttpResponse {
+    HttpResponse::BadRequest().json("Could not parse XML body")
+}
+
+#[route("/.well-known/caldav", method = "GET", method = "PROPFIND")]
+pub async fn well_known() -> impl Responder {
+    HttpResponse::MovedPermanently().insert_header((header::LOCATION, ROOT)).finish()
+}
+
+#[route("/dav/{tail:.*}", method = "OPTIONS")]
+pub async fn dav_options() -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(("DAV", "1, calendar-access"))
+        .insert_header((header::ALLOW, "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT"))
+        .finish()
+}
+
+#[route("/dav/", method = "PROPFIND")]
+pub async fn root_propfind(_auth: DavAuth, req: HttpRequest, body: String, pool: web::Data<DbPool>) -> impl Responder {
+    let Some(request) = dav::parse_propfind(&body) else { return bad_xml() };
+    let mut responses = vec![root_response(&request)];
+    if let Depth::One = depth(&req) {
+        let ctag = Event::latest_id(&mut pool.get().unwrap());
+        responses.push(collection_response(&request, ctag));
+    }
+    multistatus(&responses)
+}
+
+#[route("/dav/tasks/", method = "PROPFIND")]
+pub async fn collection_propfind(_auth: DavAuth, req: HttpRequest, body: String, pool: web::Data<DbPool>) -> impl Responder {
+    let Some(request) = dav::parse_propfind(&body) else { return bad_xml() };
+    let mut conn = pool.get().unwrap();
+    let mut responses = vec![collection_response(&request, Event::latest_id(&mut conn))];
+    if let Depth::One = depth(&req) {
+        responses.extend(Task::all(&mut conn).iter().map(|t| task_response(t, &request)));
+    }
+    multistatus(&responses)
+}
+
+#[route("/dav/tasks/", method = "REPORT")]
+pub async fn collection_report(_auth: DavAuth, body: String, pool: web::Data<DbPool>) -> impl Responder {
+    let Some(report) = dav::parse_report(&body) else { return bad_xml() };
+    let mut conn = pool.get().unwrap();
+    let responses = match report {
+        Report::Query { component: Some(component), .. } if component != "VTODO" => Vec::new(),
+        Report::Query { props, .. } => Task::all(&mut conn).iter().map(|t| task_response(t, &props)).collect(),
+        Report::Multiget { props, hrefs } => hrefs
+            .iter()
+            .map(|h| match task_id(h).and_then(|id| Task::by_id(&id, &mut conn)) {
+                Some(task) => task_response(&task, &props),
+                None => DavResponse { href: h.clone(), status: Some(404), ..Default::default() }
+            })
+            .collect()
+    };
+    multistatus(&responses)
+}
+
+#[route("/dav/tasks/{name}", method = "PROPFIND")]
+pub async fn task_propfind(_auth: DavAuth, name: web::Path<String>, body: String, pool: web::Data<DbPool>) -> impl Responder {
+    let Some(request) = dav::parse_propfind(&body) else { return bad_xml() };
+    let id = name_id(&name).unwrap_or_default();
+    match Task::by_id(&id, &mut pool.get().unwrap()) {
+        Some(task) => multistatus(&[task_response(&task, &request)]),
+        None => HttpResponse::NotFound().finish()
+    }
+}
+
+#[route("/dav/tasks/{name}", method = "GET")]
+pub async fn get_task_ics(_auth: DavAuth, name: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+    let id = name_id(&name).unwrap_or_default();
+    match Task::by_id(&id, &mut pool.get().unwrap()) {
+        Some(task) => HttpResponse::Ok()
+            .content_type(ICS_TYPE)
+            .insert_header(etag(&task))
+            .body(calendar_data(&task)),
+        None => HttpResponse::NotFound().finish()
+    }
+}
+
+//creates or replaces the task named by the path from the first VTODO in the body.
+//If-None-Match: * only creates, If-Match only replaces that version
+#[route("/dav/tasks/{name}", method = "PUT")]
+pub async fn put_task_ics(_auth: DavAuth, req: HttpRequest, name: web::Path<String>, if_match: IfMatch, body: String, pool: web::Data<DbPool>) -> impl Responder {
+    let Some(id) = name_id(&name) else { return HttpResponse::NotFound().finish() };
+    let tz = default_tz();
+    let Some(todo) = ical::parse(&body).into_iter().find(|c| c.name == "VTODO") else {
+        return HttpResponse::Forbidden().json("Only VTODO resources are supported")
+    };
+    let mut parsed = match ical::todo_task(&todo, &tz) {
+        Ok((task, _)) => task,
+        Err(reason) => return HttpResponse::BadRequest().json(reason)
+    };
+    parsed.id = id.clone();
+    let create_only = req.headers().get(header::IF_NONE_MATCH).is_some_and(|v| v.as_bytes() == b"*");
+    let mut conn = pool.get().unwrap();
+    let Some(current) = Task::by_id(&id, &mut conn) else {
+        if matches!(if_match, IfMatch::Version(_) | IfMatch::Any) {
+            return HttpResponse::PreconditionFailed().finish()
+        }
+        return match Task::insert(parsed, &mut conn) {
+            Some(task) => HttpResponse::Created().insert_header(etag(&task)).finish(),
+            None => HttpResponse::InternalServerError().json("Could not create task")
+        }
+    };
+    let stale = matches!(if_match, IfMatch::Version(v) if v != current.version);
+    if create_only || stale {
+        return HttpResponse::PreconditionFailed().insert_header(etag(&current)).finish()
+    }
+    match Task::update(replacement(&parsed, &current), &tz, &mut conn) {
+        Ok(task) => HttpResponse::NoContent().insert_header(etag(&task)).finish(),
+        Err(TaskError::Conflict(task)) => HttpResponse::PreconditionFailed().insert_header(etag(&task)).finish(),
+        Err(TaskError::InvalidDue) => HttpResponse::BadRequest().json("Could not parse due date"),
+        Err(_) => HttpResponse::NotFound().finish()
+    }
+}
+
+#[route("/dav/tasks/{name}", method = "DELETE")]
+pub async fn delete_task_ics(_auth: DavAuth, name: web::Path<String>, if_match: IfMatch, pool: web::Data<DbPool>) -> impl Responder {
+    let id = name_id(&name).unwrap_or_default();
+    let mut conn = pool.get().unwrap();
+    let Some(current) = Task::by_id(&id, &mut conn) else { return HttpResponse::NotFound().finish() };
+    if matches!(if_match, IfMatch::Version(v) if v != current.version) {
+        return HttpResponse::PreconditionFailed().insert_header(etag(&current)).finish()
+    }
+    match Task::delete_task(&id, &mut conn) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().json("Could not delete task")
+    }
+}
+
+//the stored task with what a client sent. the project is kept as clients may drop
+//X- properties, the deadline only changes when X-DOIT-DEADLINE comes back
+fn replacement(parsed: &Task, current: &Task) -> TaskUpdate {
+    TaskUpdate {
+        id: current.id.clone(),
+        name: parsed.name.clone(),
+        description: parsed.description.clone(),
+        status: parsed.status,
+        due: parsed.due.map(DueInput::from),
+        tags: Some(parsed.tags.clone()),
+        project: parsed.project.clone(),
+        priority: Some(parsed.priority),
+        recurrence: Some(parsed.recurrence.clone().unwrap_or_default()),
+        scheduled: Some(parsed.scheduled.map(DueInput::from).unwrap_or(DueInput::Text(String::new()))),
+        //clients that drop X-DOIT-DEADLINE leave the stored one alone
+        deadline: parsed.deadline.map(DueInput::from),
+        all_day: Some(parsed.all_day),
+        version: Some(current.version),
+        created_at: current.created_at,
+        updated_at: current.updated_at
+    }
+}
@@ -0,0 +1,137 @@
+use actix_web::{
+    App,
+    web,
+    http::Method,
+    test::{read_body, init_service, TestRequest}
+};
+use base64::Engine;
+use crate::db::{models::Task, establish_connection};
+
+use super::caldav::{
+    well_known,
+    dav_options,
+    root_propfind,
+    collection_propfind,
+    collection_report,
+    task_propfind,
+    get_task_ics,
+    put_task_ics,
+    delete_task_ics
+};
+
+//request bodies in the shape DAVx5 and Thunderbird send them during account setup and sync
+const DAVX5_PRINCIPAL: &str = r#"<?xml version='1.0' encoding='UTF-8' ?><propfind xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav"><prop><current-user-principal /><CAL:calendar-home-set /><resourcetype /><displayname /></prop></propfind>"#;
+const DAVX5_COLLECTION: &str = r#"<?xml version='1.0' encoding='UTF-8' ?><propfind xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav" xmlns:CS="http://calendarserver.org/ns/" xmlns:ICAL="http://apple.com/ns/ical/"><prop><resourcetype /><displayname /><ICAL:calendar-color /><CAL:supported-calendar-component-set /><current-user-privilege-set /><CS:getctag /></prop></propfind>"#;
+const THUNDERBIRD_ETAGS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<D:propfind xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/" xmlns:C="urn:ietf:params:xml:ns:caldav"><D:prop><D:resourcetype/><D:owner/><D:current-user-principal/><D:supported-report-set/><C:supported-calendar-component-set/><CS:getctag/><D:getetag/><D:getcontenttype/></D:prop></D:propfind>"#;
+const DAVX5_MULTIGET: &str = r#"<?xml version='1.0' encoding='UTF-8' ?><CAL:calendar-multiget xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav"><prop><getetag /><CAL:calendar-data /></prop><href>/dav/tasks/{uid}.ics</href><href>/dav/tasks/missing.ics</href></CAL:calendar-multiget>"#;
+const DAVX5_EVENT_QUERY: &str = r#"<?xml version='1.0' encoding='UTF-8' ?><CAL:calendar-query xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav"><prop><getetag /></prop><CAL:filter><CAL:comp-filter name="VCALENDAR"><CAL:comp-filter name="VEVENT" /></CAL:comp-filter></CAL:filter></CAL:calendar-query>"#;
+const DAVX5_TODO_QUERY: &str = r#"<?xml version='1.0' encoding='UTF-8' ?><CAL:calendar-query xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav"><prop><getetag /></prop><CAL:filter><CAL:comp-filter name="VCALENDAR"><CAL:comp-filter name="VTODO" /></CAL:comp-filter></CAL:filter></CAL:calendar-query>"#;
+const THUNDERBIRD_TODO: &str = "BEGIN:VCALENDAR\r\nPRODID:-//Mozilla.org/NONSGML Mozilla Calendar V1.1//EN\r\nVERSION:2.0\r\n\
+    BEGIN:VTODO\r\nCREATED:20230510T081500Z\r\nLAST-MODIFIED:20230510T081512Z\r\nDTSTAMP:20230510T081512Z\r\n\
+    UID:{uid}\r\nSUMMARY:{summary}\r\nCATEGORIES:caldav\r\nDUE;VALUE=DATE:20300601\r\nX-MOZ-GENERATION:1\r\n\
+    END:VTODO\r\nEND:VCALENDAR\r\n";
+
+fn todo(uid: &str, summary: &str) -> String {
+    THUNDERBIRD_TODO.replace("{uid}", uid).replace("{summary}", summary)
+}
+
+//the same token the calendar feed tests use, as the variable is shared by the process
+const TOKEN: &str = "feed-secret";
+
+fn anonymous(method: &str, uri: &str) -> TestRequest {
+    TestRequest::default().method(Method::from_bytes(method.as_bytes()).unwrap()).uri(uri)
+}
+
+fn dav(method: &str, uri: &str) -> TestRequest {
+    let login = base64::engine::general_purpose::STANDARD.encode(format!("thunderbird:{TOKEN}"));
+    anonymous(method, uri).insert_header(("Authorization", format!("Basic {login}")))
+}
+
+#[actix_rt::test]
+async fn caldav_client_session() {
+    std::env::set_var("CALENDAR_TOKEN", TOKEN);
+    let conn_pool = establish_connection();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(conn_pool))
+            .service(well_known)
+            .service(dav_options)
+            .service(root_propfind)
+            .service(collection_propfind)
+            .service(collection_report)
+            .service(task_propfind)
+            .service(get_task_ics)
+            .service(put_task_ics)
+            .service(delete_task_ics)
+    ).await;
+    let uid = uuid::Uuid::new_v4().to_string();
+    let item = format!("/dav/tasks/{uid}.ics");
+    let body = |resp| async { String::from_utf8(read_body(resp).await.to_vec()).unwrap() };
+
+    let resp = TestRequest::get().uri("/.well-known/caldav").send_request(&app).await;
+    assert_eq!(resp.headers().get("location").unwrap(), "/dav/");
+    let resp = dav("OPTIONS", "/dav/").send_request(&app).await;
+    assert!(resp.headers().get("dav").unwrap().to_str().unwrap().contains("calendar-access"));
+
+    for (method, uri) in [("PROPFIND", "/dav/"), ("REPORT", "/dav/tasks/"), ("GET", &item), ("PUT", &item), ("DELETE", &item)] {
+        let resp = anonymous(method, uri).send_request(&app).await;
+        assert_eq!(resp.status().as_u16(), 401);
+        assert!(resp.headers().get("www-authenticate").unwrap().to_str().unwrap().starts_with("Basic"));
+    }
+    let login = base64::engine::general_purpose::STANDARD.encode("thunderbird:wrong");
+    let resp = anonymous("PROPFIND", "/dav/").insert_header(("Authorization", format!("Basic {login}"))).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 401);
+
+    let resp = dav("PROPFIND", "/dav/").insert_header(("Depth", "0")).set_payload(DAVX5_PRINCIPAL).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 207);
+    let xml = body(resp).await;
+    assert!(xml.contains("<d:current-user-principal><d:href>/dav/</d:href></d:current-user-principal>"));
+    assert!(xml.contains("<c:calendar-home-set><d:href>/dav/</d:href></c:calendar-home-set>"));
+    let resp = dav("PROPFIND", "/dav/").insert_header(("Depth", "1")).set_payload(DAVX5_COLLECTION).send_request(&app).await;
+    let xml = body(resp).await;
+    assert!(xml.contains("<d:href>/dav/tasks/</d:href>"));
+    assert!(xml.contains("<c:comp name=\"VTODO\"/>"));
+    assert!(xml.contains("<x:calendar-color xmlns:x=\"http://apple.com/ns/ical/\"/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status>"));
+
+    let resp = dav("PUT", &item).insert_header(("If-None-Match", "*")).set_payload(todo(&uid, "endpoint_test_caldav")).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 201);
+    assert_eq!(resp.headers().get("etag").unwrap(), "\"1\"");
+    let resp = dav("PUT", &item).insert_header(("If-None-Match", "*")).set_payload(todo(&uid, "endpoint_test_caldav")).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 412);
+
+    let resp = dav("PROPFIND", "/dav/tasks/").insert_header(("Depth", "1")).set_payload(THUNDERBIRD_ETAGS).send_request(&app).await;
+    let xml = body(resp).await;
+    assert!(xml.contains(&format!("<d:href>{item}</d:href><d:propstat><d:prop><d:resourcetype/><d:getetag>&quot;1&quot;</d:getetag>")));
+    assert!(roxmltree::Document::parse(&xml).is_ok());
+
+    let resp = dav("REPORT", "/dav/tasks/").set_payload(DAVX5_MULTIGET.replace("{uid}", &uid)).send_request(&app).await;
+    let xml = body(resp).await;
+    let doc = roxmltree::Document::parse(&xml).unwrap();
+    let data = doc.descendants().find(|n| n.has_tag_name(("urn:ietf:params:xml:ns:caldav", "calendar-data"))).unwrap().text().unwrap();
+    assert!(data.contains("SUMMARY:endpoint_test_caldav\r\n"));
+    assert!(data.contains("DUE;VALUE=DATE:20300601\r\n"));
+    assert!(xml.contains("<d:href>/dav/tasks/missing.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status>"));
+    let resp = dav("REPORT", "/dav/tasks/").set_payload(DAVX5_EVENT_QUERY).send_request(&app).await;
+    assert!(!body(resp).await.contains("<d:response>"));
+    let resp = dav("REPORT", "/dav/tasks/").set_payload(DAVX5_TODO_QUERY).send_request(&app).await;
+    assert!(body(resp).await.contains(&item));
+
+    let resp = dav("PUT", &item).insert_header(("If-Match", "\"9\"")).set_payload(todo(&uid, "endpoint_test_caldav edited")).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 412);
+    let resp = dav("PUT", &item).insert_header(("If-Match", "\"1\"")).set_payload(todo(&uid, "endpoint_test_caldav edited")).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 204);
+    assert_eq!(resp.headers().get("etag").unwrap(), "\"2\"");
+    let resp = dav("GET", &item).send_request(&app).await;
+    assert!(body(resp).await.contains("SUMMARY:endpoint_test_caldav edited\r\n"));
+    let resp = dav("PUT", "/dav/tasks/event.ics").set_payload("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:x\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 403);
+
+    let resp = dav("DELETE", &item).insert_header(("If-Match", "\"1\"")).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 412);
+    let resp = dav("DELETE", &item).insert_header(("If-Match", "\"2\"")).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 204);
+    let resp = dav("GET", &item).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 404);
+    assert!(Task::by_id(&uid, &mut establish_connection().get().unwrap()).is_none());
+}
@@ -0,0 +1,60 @@
+use actix_web::{Responder, web, get, post, HttpResponse};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{Task, ics}};
+use crate::utils::{date::default_tz, ical};
+use super::extract::UserTz;
+
+//the feed is off unless a token is configured, calendar apps cannot send headers
+//so it goes in the URL
+const TOKEN_ENV: &str = "CALENDAR_TOKEN";
+
+pub(crate) fn calendar_token() -> Option<String> {
+    std::env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalendarQuery {
+    token: Option<String>,
+    filter: Option<String>
+}
+
+//compares every byte so the time taken does not give away how much of a guess was right
+pub(crate) fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+//open tasks as a subscribable calendar, e.g. /calendar.ics?token=...&filter=:project:home
+#[get("/calendar.ics")]
+pub async fn calendar_feed(query: web::Query<CalendarQuery>, pool: web::Data<DbPool>) -> impl Responder {
+    let Some(expected) = calendar_token() else {
+        return HttpResponse::NotFound().json("Not Found")
+    };
+    if !query.token.as_deref().is_some_and(|t| token_matches(t, &expected)) {
+        return HttpResponse::Forbidden().json("Invalid token")
+    }
+    let tz = default_tz();
+    let mut conn = pool.get().unwrap();
+    let tasks = match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(filter) => Task::filter(filter, &tz, &mut conn),
+        None => Task::list(&mut conn)
+    };
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(ical::calendar(&tasks, &tz, Utc::now()))
+}
+
+//VTODO entries of an .ics file become tasks, the report lists what was created,
+//skipped and which properties were left out
+#[post("/import/ics")]
+pub async fn import_ics(body: String, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    if !body.contains("BEGIN:VCALENDAR") {
+        return HttpResponse::BadRequest().json("Not an iCalendar file")
+    }
+    let mut conn = pool.get().unwrap();
+    match ics::import(&body, &tz.0, &mut conn) {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(_) => HttpResponse::InternalServerError().json("Could not import tasks")
+    }
+}
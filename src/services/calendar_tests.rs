@@ -0,0 +1,62 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body, read_body_json, init_service, TestRequest}
+};
+use crate::db::{models::Task, establish_connection};
+
+use super::calendar::{calendar_feed, import_ics};
+
+#[actix_rt::test]
+async fn calendar_feed_needs_token() {
+    std::env::set_var("CALENDAR_TOKEN", "feed-secret");
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(calendar_feed)).await;
+    let mut conn = establish_connection().get().unwrap();
+    let task = Task::create("endpoint_test_calendar", None, Some(chrono::Utc::now() + chrono::Duration::days(1)), &mut conn).unwrap();
+
+    let resp = TestRequest::get().uri("/calendar.ics").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 403);
+    let resp = TestRequest::get().uri("/calendar.ics?token=wrong").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 403);
+
+    let resp = TestRequest::get().uri("/calendar.ics?token=feed-secret&filter=endpoint_test_calendar").send_request(&app).await;
+    assert!(resp.status().is_success());
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/calendar"));
+    let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains(&format!("UID:{}\r\n", task.id)));
+    assert!(body.contains("SUMMARY:endpoint_test_calendar\r\n"));
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn import_ics_entries() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(import_ics)).await;
+    let uid = uuid::Uuid::new_v4().to_string();
+    let ics = format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+        BEGIN:VTODO\r\nUID:{uid}\r\nSUMMARY:endpoint_test_ics\r\nDUE;VALUE=DATE:20300510\r\nGEO:48.2;16.3\r\nEND:VTODO\r\n\
+        BEGIN:VTODO\r\nUID:{uid}\r\nSUMMARY:endpoint_test_ics again\r\nEND:VTODO\r\n\
+        BEGIN:VTODO\r\nUID:no-summary\r\nEND:VTODO\r\n\
+        BEGIN:VEVENT\r\nUID:event\r\nSUMMARY:meeting\r\nEND:VEVENT\r\n\
+        END:VCALENDAR\r\n"
+    );
+    let resp = TestRequest::post().uri("/import/ics").set_payload(ics.clone()).send_request(&app).await;
+    assert!(resp.status().is_success());
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert_eq!(report["created"].as_array().unwrap().len(), 1);
+    assert_eq!(report["created"][0]["id"], uid.as_str());
+    assert_eq!(report["created"][0]["all_day"], true);
+    let reasons = report["skipped"].as_array().unwrap().iter().map(|s| s["reason"].as_str().unwrap()).collect::<Vec<&str>>();
+    assert_eq!(reasons, vec!["Duplicate UID", "SUMMARY is missing", "VEVENT is not supported"]);
+    assert_eq!(report["unsupported"][0]["properties"], serde_json::json!(["GEO"]));
+
+    let resp = TestRequest::post().uri("/import/ics").set_payload(ics).send_request(&app).await;
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert!(report["created"].as_array().unwrap().is_empty());
+    assert_eq!(report["skipped"][0]["reason"], "Already imported");
+    let resp = TestRequest::post().uri("/import/ics").set_payload("hello").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    Task::delete_task(&uid, &mut establish_connection().get().unwrap()).unwrap();
+}
@@ -0,0 +1,48 @@
+use actix_web::{web, get, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use tokio::sync::mpsc;
+
+use crate::collab::Hub;
+use crate::db::DbPool;
+use super::extract::UserTz;
+
+//websocket for live collaboration, the messages are described in collab::protocol
+#[get("/ws")]
+pub async fn collab_socket(req: HttpRequest, body: web::Payload, tz: UserTz, hub: web::Data<Hub>, pool: web::Data<DbPool>) -> Result<HttpResponse, actix_web::Error> {
+    let (response, session, mut stream) = actix_ws::handle(&req, body)?;
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let client = hub.join(tx, tz.0);
+
+    let mut outgoing = session.clone();
+    actix_rt::spawn(async move {
+        while let Some(text) = rx.recv().await {
+            if outgoing.text(text).await.is_err() {
+                break
+            }
+        }
+    });
+
+    let mut session = session;
+    actix_rt::spawn(async move {
+        while let Some(Ok(msg)) = stream.recv().await {
+            let open = match msg {
+                Message::Text(text) => {
+                    let (hub, pool, client) = (hub.clone(), pool.clone(), client.clone());
+                    web::block(move || match pool.get() {
+                        Ok(mut conn) => hub.handle(&client, &text, &mut conn),
+                        Err(err) => log::warn!("websocket client {client} has no connection: {err}")
+                    }).await.is_ok()
+                },
+                Message::Ping(bytes) => session.pong(&bytes).await.is_ok(),
+                Message::Close(_) => false,
+                _ => true
+            };
+            if !open {
+                break
+            }
+        }
+        hub.leave(&client);
+        let _ = session.close(None).await;
+    });
+    Ok(response)
+}
@@ -0,0 +1,123 @@
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, dev::Payload, error::InternalError, http::header, web};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono_tz::Tz;
+
+use crate::db::{DbPool, store::{PgStore, TaskStore}};
+use crate::utils::date::{TZ_HEADER, default_tz, parse_tz};
+use super::calendar::{calendar_token, token_matches};
+
+//IANA zone of the client, taken from the X-Timezone header or DEFAULT_TZ
+pub struct UserTz(pub Tz);
+
+impl FromRequest for UserTz {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let tz = match req.headers().get(TZ_HEADER) {
+            None => Ok(UserTz(default_tz())),
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(parse_tz)
+                .map(UserTz)
+                .ok_or_else(|| {
+                    InternalError::from_response(
+                        "unknown time zone",
+                        HttpResponse::BadRequest().json("Unknown time zone")
+                    ).into()
+                })
+        };
+        ready(tz)
+    }
+}
+
+//the task version from an If-Match header like "3" or W/"3"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfMatch {
+    Missing,
+    Any,
+    Version(i32),
+}
+
+pub fn parse_etag(value: &str) -> Option<i32> {
+    let value = value.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    value.trim_matches('"').parse::<i32>().ok()
+}
+
+impl FromRequest for IfMatch {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let version = match req.headers().get(header::IF_MATCH).map(|v| v.to_str().map(str::trim)) {
+            None => Ok(IfMatch::Missing),
+            Some(Ok("*")) => Ok(IfMatch::Any),
+            Some(value) => value
+                .ok()
+                .and_then(parse_etag)
+                .map(IfMatch::Version)
+                .ok_or_else(|| {
+                    InternalError::from_response(
+                        "invalid if-match",
+                        HttpResponse::BadRequest().json("Invalid If-Match")
+                    ).into()
+                })
+        };
+        ready(version)
+    }
+}
+
+//CalDAV clients log in with Basic auth, any user name and CALENDAR_TOKEN as the password.
+//without a token the DAV tree is off like the calendar feed
+pub struct DavAuth;
+
+fn basic_password(req: &HttpRequest) -> Option<String> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None
+    }
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    decoded.split_once(':').map(|(_, password)| password.to_string())
+}
+
+impl FromRequest for DavAuth {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let Some(expected) = calendar_token() else {
+            return ready(Err(InternalError::from_response("dav disabled", HttpResponse::NotFound().finish()).into()))
+        };
+        if basic_password(req).is_some_and(|p| token_matches(&p, &expected)) {
+            return ready(Ok(DavAuth))
+        }
+        let challenge = HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Basic realm=\"doit\", charset=\"UTF-8\""))
+            .finish();
+        ready(Err(InternalError::from_response("unauthorized", challenge).into()))
+    }
+}
+
+//where the task endpoints keep tasks, the TaskStore the app was given or else Postgres
+pub struct Store(pub Arc<dyn TaskStore>);
+
+impl FromRequest for Store {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        if let Some(store) = req.app_data::<web::Data<dyn TaskStore>>() {
+            return ready(Ok(Store(store.clone().into_inner())))
+        }
+        let store = match req.app_data::<web::Data<DbPool>>() {
+            Some(pool) => Ok(Store(Arc::new(PgStore(pool.get_ref().clone())))),
+            None => Err(InternalError::from_response("no task store", HttpResponse::InternalServerError().json("No task store")).into())
+        };
+        ready(store)
+    }
+}
@@ -1,4 +1,46 @@
 pub mod task;
+pub mod extract;
+pub mod reminder;
+pub mod webhook;
+pub mod stream;
+pub mod collab;
+pub mod sync;
+pub mod bulk;
+pub mod backup;
+pub mod calendar;
+pub mod caldav;
+pub mod todotxt;
+pub mod spreadsheet;
+pub mod notes;
+pub mod scan;
+pub mod taskwarrior;
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_mut_passed, clippy::len_zero, clippy::get_first)]
 mod task_tests;
+#[cfg(test)]
+mod reminder_tests;
+#[cfg(test)]
+mod webhook_tests;
+#[cfg(test)]
+mod stream_tests;
+#[cfg(test)]
+mod sync_tests;
+#[cfg(test)]
+mod bulk_tests;
+#[cfg(test)]
+mod backup_tests;
+#[cfg(test)]
+mod calendar_tests;
+#[cfg(test)]
+mod caldav_tests;
+#[cfg(test)]
+mod todotxt_tests;
+#[cfg(test)]
+mod spreadsheet_tests;
+#[cfg(test)]
+mod notes_tests;
+#[cfg(test)]
+mod scan_tests;
+#[cfg(test)]
+mod taskwarrior_tests;
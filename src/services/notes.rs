@@ -0,0 +1,36 @@
+use actix_web::{Responder, web, get, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::Task};
+use crate::utils::notes::{markdown, org, Grouping};
+use super::extract::UserTz;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NotesQuery {
+    filter: Option<String>,
+    #[serde(default)]
+    group: Grouping
+}
+
+fn tasks(query: &NotesQuery, tz: &UserTz, pool: &web::Data<DbPool>) -> Vec<Task> {
+    let mut conn = pool.get().unwrap();
+    match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(filter) => Task::filter(filter, &tz.0, &mut conn),
+        None => Task::list(&mut conn)
+    }
+}
+
+//the task list or what ?filter= finds as checklists, ?group=project|status|none
+#[get("/export/markdown")]
+pub async fn export_markdown(query: web::Query<NotesQuery>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/markdown; charset=utf-8")
+        .body(markdown(&tasks(&query, &tz, &pool), query.group, &tz.0))
+}
+
+#[get("/export/org")]
+pub async fn export_org(query: web::Query<NotesQuery>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/org; charset=utf-8")
+        .body(org(&tasks(&query, &tz, &pool), query.group, &tz.0))
+}
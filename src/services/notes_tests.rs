@@ -0,0 +1,40 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body, init_service, TestRequest}
+};
+use crate::db::{models::{Task, TaskDetails, DuplicateMode}, establish_connection};
+
+use super::notes::{export_markdown, export_org};
+
+#[actix_rt::test]
+async fn markdown_and_org_exports() {
+    let conn_pool = establish_connection();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(conn_pool))
+            .service(export_markdown)
+            .service(export_org)
+    ).await;
+    let mut conn = establish_connection().get().unwrap();
+    let project = uuid::Uuid::new_v4().simple().to_string();
+    let details = TaskDetails { project: Some(project.clone()), ..Default::default() };
+    let due = chrono::Utc::now() + chrono::Duration::days(2);
+    let task = Task::create_with_details("endpoint_test_notes", Some("first line"), Some(due), details, DuplicateMode::Allow, &mut conn).unwrap();
+    let body = |resp| async { String::from_utf8(read_body(resp).await.to_vec()).unwrap() };
+
+    let resp = TestRequest::get().uri(&format!("/export/markdown?filter=:project:{project}")).insert_header(("X-Timezone", "UTC")).send_request(&app).await;
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/markdown"));
+    let text = body(resp).await;
+    let due_text = due.format("%Y-%m-%d %H:%M");
+    assert_eq!(text, format!("## {project}\n\n- [ ] endpoint_test_notes (due {due_text})\n  first line\n\n"));
+    let resp = TestRequest::get().uri(&format!("/export/markdown?filter=:project:{project}&group=status")).send_request(&app).await;
+    assert!(body(resp).await.starts_with("## Open\n"));
+
+    let resp = TestRequest::get().uri(&format!("/export/org?filter=:project:{project}&group=none")).insert_header(("X-Timezone", "UTC")).send_request(&app).await;
+    let text = body(resp).await;
+    assert_eq!(text, format!("* TODO endpoint_test_notes\n  DEADLINE: <{}>\n  first line\n", due.format("%Y-%m-%d %a %H:%M")));
+    let resp = TestRequest::get().uri("/export/org?group=weekly").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
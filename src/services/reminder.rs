@@ -0,0 +1,72 @@
+use actix_web::{Responder, web, get, post, delete, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{Task, reminder::{Reminder, ReminderTime}}};
+use crate::utils::date::{DueInput, parse_before};
+use super::extract::UserTz;
+
+const UPCOMING_LIMIT: i64 = 50;
+
+//either an absolute "at" like "tomorrow 9am" or "before" the due date like "15 minutes"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReminderForm {
+    at: Option<DueInput>,
+    before: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpcomingQuery {
+    limit: Option<i64>
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpcomingReminder {
+    #[serde(flatten)]
+    reminder: Reminder,
+    task: Task
+}
+
+#[post("/tasks/{id}/reminders")]
+pub async fn create_reminder(id: web::Path<String>, form: web::Json<ReminderForm>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let time = match (&form.at, &form.before) {
+        (Some(at), None) => match at.resolve(&tz.0) {
+            Some(parsed) => ReminderTime::At(parsed.due),
+            None => return HttpResponse::BadRequest().json("Could not parse reminder time")
+        },
+        (None, Some(before)) => match parse_before(before) {
+            Some(minutes) => ReminderTime::Before(minutes),
+            None => return HttpResponse::BadRequest().json("Could not parse reminder offset")
+        },
+        _ => return HttpResponse::BadRequest().json("Give either at or before")
+    };
+    let mut conn = pool.get().unwrap();
+    match Reminder::create(&id, time, &mut conn) {
+        Some(reminder) => HttpResponse::Created().json(reminder),
+        _ => HttpResponse::NotFound().json("Not Found")
+    }
+}
+
+#[get("/tasks/{id}/reminders")]
+pub async fn task_reminders(id: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    HttpResponse::Ok().json(Reminder::for_task(&id, &mut conn))
+}
+
+#[get("/reminders/upcoming")]
+pub async fn upcoming_reminders(query: web::Query<UpcomingQuery>, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    let upcoming = Reminder::upcoming(query.limit.unwrap_or(UPCOMING_LIMIT), &mut conn)
+        .into_iter()
+        .map(|(reminder, task)| UpcomingReminder { reminder, task })
+        .collect::<Vec<UpcomingReminder>>();
+    HttpResponse::Ok().json(upcoming)
+}
+
+#[delete("/reminders/{id}")]
+pub async fn delete_reminder(id: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match Reminder::delete_reminder(&id, &mut conn) {
+        Ok(0) | Err(_) => HttpResponse::NotFound().json("Not Found"),
+        Ok(_) => HttpResponse::NoContent().finish()
+    }
+}
@@ -0,0 +1,56 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body_json, init_service, TestRequest}
+};
+use serde_json::json;
+use crate::db::{models::{Task, reminder::Reminder}, establish_connection};
+
+use super::task::create;
+use super::reminder::{create_reminder, task_reminders, upcoming_reminders, delete_reminder};
+
+#[actix_rt::test]
+async fn remind_before_due() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new()
+        .app_data(web::Data::new(conn_pool))
+        .service(create)
+        .service(create_reminder)
+        .service(task_reminders)
+        .service(upcoming_reminders)
+        .service(delete_reminder)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_reminder", "due": "2999-05-10T12:00:00Z"}))
+        .send_request(&app)
+        .await;
+    let task: Task = read_body_json(resp).await;
+    let resp = TestRequest::post()
+        .uri(format!("/tasks/{}/reminders", task.id).as_str())
+        .set_json(json!({"before": "1 hour before"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let reminder: Reminder = read_body_json(resp).await;
+    assert_eq!(reminder.minutes_before, Some(60));
+    assert_eq!(reminder.fire_at.unwrap().to_rfc3339(), "2999-05-10T11:00:00+00:00");
+
+    let resp = TestRequest::post()
+        .uri(format!("/tasks/{}/reminders", task.id).as_str())
+        .set_json(json!({"before": "whenever"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    let resp = TestRequest::get().uri(format!("/tasks/{}/reminders", task.id).as_str()).send_request(&app).await;
+    let reminders: Vec<Reminder> = read_body_json(resp).await;
+    assert_eq!(reminders.len(), 1);
+    let resp = TestRequest::get().uri("/reminders/upcoming?limit=1000").send_request(&app).await;
+    assert!(resp.status().is_success());
+    let upcoming: Vec<serde_json::Value> = read_body_json(resp).await;
+    assert!(upcoming.iter().any(|r| r["id"] == reminder.id.as_str() && r["task"]["id"] == task.id.as_str()));
+
+    let resp = TestRequest::delete().uri(format!("/reminders/{}", reminder.id).as_str()).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 204);
+    let _ = Task::delete_task(&task.id, &mut establish_connection().get().unwrap());
+}
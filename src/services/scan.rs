@@ -0,0 +1,48 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use actix_web::{Responder, web, post, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::scan};
+
+const ROOTS_ENV: &str = "SCAN_ROOTS";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanRequest {
+    path: String,
+    project: Option<String>
+}
+
+//the directories the endpoint may read, comma separated. unset turns it off
+fn roots() -> Vec<PathBuf> {
+    env::var(ROOTS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|r| !r.trim().is_empty())
+        .filter_map(|r| Path::new(r.trim()).canonicalize().ok())
+        .collect()
+}
+
+//turns TODO, FIXME and XXX comments below `path` into tasks and closes the ones whose
+//comment is gone. only for directories inside SCAN_ROOTS
+#[post("/scan")]
+pub async fn scan_tree(body: web::Json<ScanRequest>, pool: web::Data<DbPool>) -> impl Responder {
+    let roots = roots();
+    if roots.is_empty() {
+        return HttpResponse::NotFound().json("Scanning is off, set SCAN_ROOTS")
+    }
+    let Ok(path) = Path::new(&body.path).canonicalize() else {
+        return HttpResponse::BadRequest().json(format!("{} is not a directory", body.path))
+    };
+    if !roots.iter().any(|r| path.starts_with(r)) {
+        return HttpResponse::Forbidden().json(format!("{} is outside SCAN_ROOTS", body.path))
+    }
+    if !path.is_dir() {
+        return HttpResponse::BadRequest().json(format!("{} is not a directory", body.path))
+    }
+    let mut conn = pool.get().unwrap();
+    match scan::run(&path, body.project.as_deref().filter(|p| !p.is_empty()), &mut conn) {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::InternalServerError().json(err)
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use actix_web::{
+    App,
+    web,
+    test::{read_body_json, init_service, TestRequest}
+};
+use crate::db::{models::Task, establish_connection};
+
+use super::scan::scan_tree;
+
+#[actix_rt::test]
+async fn scan_inside_roots_only() {
+    let conn_pool = establish_connection();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(conn_pool))
+            .service(scan_tree)
+    ).await;
+    let mut conn = establish_connection().get().unwrap();
+    let base = std::env::temp_dir().join(format!("doit-scan-roots-{}", uuid::Uuid::new_v4().simple()));
+    let (root, outside) = (base.join("allowed"), base.join("other"));
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(root.join("run.sh"), "#!/bin/sh\n# XXX: quote paths\n").unwrap();
+    let scan = |path: &std::path::Path| TestRequest::post()
+        .uri("/scan")
+        .set_json(serde_json::json!({"path": path, "project": "endpoint_test_scan"}));
+
+    std::env::remove_var("SCAN_ROOTS");
+    assert_eq!(scan(&root).send_request(&app).await.status().as_u16(), 404);
+    std::env::set_var("SCAN_ROOTS", root.display().to_string());
+    assert_eq!(scan(&outside).send_request(&app).await.status().as_u16(), 403);
+    assert_eq!(scan(&root.join("run.sh")).send_request(&app).await.status().as_u16(), 400);
+
+    let report: serde_json::Value = read_body_json(scan(&root).send_request(&app).await).await;
+    let created = report["created"].as_array().unwrap();
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0]["name"], "quote paths");
+    assert_eq!(created[0]["description"], "run.sh:2");
+    std::env::remove_var("SCAN_ROOTS");
+    Task::delete_task(created[0]["id"].as_str().unwrap(), &mut conn).unwrap();
+    let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,51 @@
+use actix_web::{Responder, web, get, post, HttpResponse, http::header};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{Task, spreadsheet::{self, CsvOptions}}};
+use crate::utils::spreadsheet::{parse_columns, valid_format, write, ALL_COLUMNS};
+use super::extract::UserTz;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CsvExportQuery {
+    filter: Option<String>,
+    columns: Option<String>,
+    date_format: Option<String>
+}
+
+//the task list or what ?filter= finds as CSV, ?columns=name,due,status picks the columns
+//and ?date_format=%d.%m.%Y how dates are written, in the client's zone
+#[get("/export/csv")]
+pub async fn export_csv(query: web::Query<CsvExportQuery>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let columns = match query.columns.as_deref().filter(|c| !c.trim().is_empty()) {
+        Some(list) => match parse_columns(list) {
+            Ok(columns) => columns,
+            Err(message) => return HttpResponse::BadRequest().json(message)
+        },
+        None => ALL_COLUMNS.to_vec()
+    };
+    let format = query.date_format.as_deref().filter(|f| !f.trim().is_empty());
+    if format.is_some_and(|f| !valid_format(f)) {
+        return HttpResponse::BadRequest().json("Invalid date_format")
+    }
+    let mut conn = pool.get().unwrap();
+    let tasks = match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(filter) => Task::filter(filter, &tz.0, &mut conn),
+        None => Task::list(&mut conn)
+    };
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(header::ContentDisposition::attachment("tasks.csv"))
+        .body(write(&tasks, &columns, format, &tz.0))
+}
+
+//?map=Title:name&date_format=%d.%m.%Y&dry_run=true. rows with errors get 422 and
+//the report, nothing is written then
+#[post("/import/csv")]
+pub async fn import_csv(body: String, options: web::Query<CsvOptions>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match spreadsheet::import(&body, &options, &tz.0, &mut conn) {
+        Err(message) => HttpResponse::BadRequest().json(message),
+        Ok(report) if report.committed || report.dry_run => HttpResponse::Ok().json(report),
+        Ok(report) => HttpResponse::UnprocessableEntity().json(report)
+    }
+}
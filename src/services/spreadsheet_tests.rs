@@ -0,0 +1,82 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body, read_body_json, init_service, TestRequest}
+};
+use crate::db::{models::Task, establish_connection};
+
+use super::spreadsheet::{export_csv, import_csv};
+
+#[actix_rt::test]
+async fn csv_export_and_import() {
+    let conn_pool = establish_connection();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(conn_pool))
+            .service(export_csv)
+            .service(import_csv)
+    ).await;
+    let project = uuid::Uuid::new_v4().simple().to_string();
+    let name = format!("endpoint_test_csv {project}");
+    let sheet = format!(
+        "Title,Due Date,State,Owner\n\
+        {name},10.05.2030,created,ann\n\
+        \"{name}, second\",2030-05-11 09:30,doing,bob\n\
+        ,11.05.2030,done,bob\n"
+    );
+    let uri = "/import/csv?map=Title:name,Due%20Date:due,State:status&date_format=%25d.%25m.%25Y";
+    let resp = TestRequest::post().uri(&format!("{uri}&dry_run=true")).insert_header(("X-Timezone", "UTC")).set_payload(sheet.clone()).send_request(&app).await;
+    assert!(resp.status().is_success());
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert_eq!(report["committed"], false);
+    assert_eq!(report["ignored"], serde_json::json!(["Owner"]));
+    assert_eq!(report["created"].as_array().unwrap().len(), 1);
+    assert_eq!(report["created"][0]["all_day"], true);
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["row"], 3);
+    assert_eq!(errors[0]["column"], "State");
+    assert_eq!(errors[0]["error"], "Unknown status doing");
+    assert_eq!(errors[1]["row"], 4);
+    assert_eq!(errors[1]["error"], "name is missing");
+    let mut conn = establish_connection().get().unwrap();
+    assert!(Task::by_name(&name, &mut conn).is_none());
+
+    let resp = TestRequest::post().uri(uri).insert_header(("X-Timezone", "UTC")).set_payload(sheet).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 422);
+    assert!(Task::by_name(&name, &mut conn).is_none());
+
+    let sheet = format!("Title,Due Date,State,Project\n{name},10.05.2030,created,{project}\n");
+    let resp = TestRequest::post().uri(uri).insert_header(("X-Timezone", "UTC")).set_payload(sheet).send_request(&app).await;
+    assert!(resp.status().is_success());
+    let task = Task::by_name(&name, &mut conn).unwrap();
+
+    let resp = TestRequest::get()
+        .uri(&format!("/export/csv?filter=:project:{project}&columns=id,name,status,due,version&date_format=%25d.%25m.%25Y"))
+        .insert_header(("X-Timezone", "UTC"))
+        .send_request(&app).await;
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/csv"));
+    let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+    assert!(body.starts_with("id,name,status,due,version\n"));
+    let line = format!("{},{name},created,10.05.2030,1\n", task.id);
+    assert!(body.contains(&line));
+
+    let edited = format!("id,name,status,due,version\n{}", line.replace("created", "done"));
+    let resp = TestRequest::post().uri("/import/csv?date_format=%25d.%25m.%25Y").insert_header(("X-Timezone", "UTC")).set_payload(edited.clone()).send_request(&app).await;
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert_eq!(report["updated"][0]["status"], 2);
+    assert_eq!(report["updated"][0]["version"], 2);
+    let resp = TestRequest::post().uri("/import/csv?date_format=%25d.%25m.%25Y").insert_header(("X-Timezone", "UTC")).set_payload(edited).send_request(&app).await;
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert_eq!(report["errors"][0]["error"], "Task changed meanwhile");
+    let resp = TestRequest::post().uri("/import/csv").set_payload(format!("id,name\n{},{name}\n", task.id)).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = TestRequest::post().uri("/import/csv").set_payload(format!("id,name,version\n{},{name},\n", task.id)).send_request(&app).await;
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert_eq!(report["errors"][0]["error"], "version is missing");
+    let resp = TestRequest::get().uri("/export/csv?columns=name,owner").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = TestRequest::get().uri("/export/csv?date_format=%25Q").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+use actix_web::{Responder, web, get, HttpRequest, HttpResponse, http::header, web::Bytes};
+use futures_util::stream;
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{TaskStatus, event::Event}};
+
+const LAST_EVENT_ID: &str = "Last-Event-ID";
+const POLL_MILLIS: u64 = 1000;
+//a comment line every so many idle polls keeps proxies from closing the stream
+const KEEP_ALIVE_POLLS: u32 = 15;
+const BATCH: i64 = 100;
+
+//only events whose task is in this project and/or has this status, e.g. ?project=home&status=done
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamFilter {
+    project: Option<String>,
+    status: Option<String>
+}
+
+impl StreamFilter {
+    fn matches(&self, event: &Event, status: Option<i32>) -> bool {
+        let task = &event.payload;
+        let project_ok = self.project
+            .as_ref()
+            .is_none_or(|p| task["project"].as_str() == Some(p.as_str()));
+        let status_ok = status.is_none_or(|s| task["status"].as_i64() == Some(s as i64));
+        project_ok && status_ok
+    }
+}
+
+struct StreamState {
+    last_id: i64,
+    idle: u32,
+    first: bool,
+    status: Option<i32>,
+    filter: StreamFilter,
+    pool: web::Data<DbPool>
+}
+
+fn frame(event: &Event) -> String {
+    format!("id: {}\nevent: {}\ndata: {}\n\n", event.id, event.kind, event.to_message())
+}
+
+//waits for the next events after `last_id` and renders those passing the filter
+async fn next_frames(mut state: StreamState) -> Option<(Result<Bytes, actix_web::Error>, StreamState)> {
+    loop {
+        if state.first {
+            state.first = false;
+        } else {
+            actix_rt::time::sleep(Duration::from_millis(POLL_MILLIS)).await;
+        }
+        let pool = state.pool.clone();
+        let last_id = state.last_id;
+        let events = web::block(move || Event::since(last_id, BATCH, &mut pool.get().unwrap())).await.ok()?;
+        if let Some(last) = events.last() {
+            state.last_id = last.id;
+        }
+        let body = events
+            .iter()
+            .filter(|e| state.filter.matches(e, state.status))
+            .map(frame)
+            .collect::<String>();
+        if !body.is_empty() {
+            state.idle = 0;
+            return Some((Ok(Bytes::from(body)), state))
+        }
+        state.idle += 1;
+        if state.idle >= KEEP_ALIVE_POLLS {
+            state.idle = 0;
+            return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), state))
+        }
+    }
+}
+
+//live task changes as server-sent events, reconnecting clients send Last-Event-ID
+//and get everything they missed
+#[get("/events")]
+pub async fn event_stream(req: HttpRequest, filter: web::Query<StreamFilter>, pool: web::Data<DbPool>) -> impl Responder {
+    let status = match &filter.status {
+        Some(text) => match TaskStatus::from_str(text) {
+            Some(s) => Some(s.to_store()),
+            None => return HttpResponse::BadRequest().json("Unknown status")
+        },
+        None => None
+    };
+    let resume = req.headers()
+        .get(LAST_EVENT_ID)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok());
+    let last_id = match resume {
+        Some(id) => id,
+        None => {
+            let pool = pool.clone();
+            web::block(move || Event::latest_id(&mut pool.get().unwrap())).await.unwrap_or(0)
+        }
+    };
+    let state = StreamState { last_id, idle: 0, first: true, status, filter: filter.into_inner(), pool };
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .streaming(stream::unfold(state, next_frames))
+}
@@ -0,0 +1,38 @@
+use std::future::poll_fn;
+use std::pin::Pin;
+use actix_web::{
+    App,
+    web,
+    body::MessageBody,
+    test::{init_service, TestRequest}
+};
+use crate::db::{models::{DuplicateMode, Task, TaskDetails, event::Event}, establish_connection};
+
+use super::stream::event_stream;
+
+#[actix_rt::test]
+async fn stream_resumes_after_last_event_id() {
+    let conn_pool = establish_connection();
+    let mut conn = conn_pool.get().unwrap();
+    let details = TaskDetails { project: Some("endpoint_test_stream".to_string()), ..Default::default() };
+    let task = Task::create_with_details("endpoint_test_stream", None, None, details, DuplicateMode::Existing, &mut conn).unwrap();
+    let created = Event::for_task(&task.id, &mut conn)[0].id;
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool.clone())).service(event_stream)).await;
+
+    let resp = TestRequest::get().uri("/events?status=sleeping").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    let resp = TestRequest::get()
+        .uri("/events?project=endpoint_test_stream&status=created")
+        .insert_header(("Last-Event-ID", (created - 1).to_string()))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+    let mut body = resp.into_body();
+    let chunk = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await.unwrap().unwrap();
+    let text = String::from_utf8(chunk.to_vec()).unwrap();
+    assert!(text.starts_with(&format!("id: {created}\nevent: task.created\ndata: ")));
+    assert!(text.contains(&task.id));
+    let _ = Task::delete_task(&task.id, &mut conn);
+}
@@ -0,0 +1,43 @@
+use actix_web::{Responder, web, get, post, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::sync::{self, SyncMutation, SyncResult}};
+use super::extract::UserTz;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncQuery {
+    since: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncBatch {
+    mutations: Vec<SyncMutation>
+}
+
+//per mutation results and a token to pull what changed meanwhile
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncReport {
+    results: Vec<SyncResult>,
+    token: String
+}
+
+#[get("/sync")]
+pub async fn pull(query: web::Query<SyncQuery>, pool: web::Data<DbPool>) -> impl Responder {
+    let since = match query.since.as_deref() {
+        Some(token) => match sync::parse_token(token) {
+            Some(since) => Some(since),
+            None => return HttpResponse::BadRequest().json("Invalid sync token")
+        },
+        None => None
+    };
+    let mut conn = pool.get().unwrap();
+    HttpResponse::Ok().json(sync::changes_since(since, &mut conn))
+}
+
+#[post("/sync")]
+pub async fn push(batch: web::Json<SyncBatch>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    let results = sync::apply(batch.into_inner().mutations, &tz.0, &mut conn);
+    let token = crate::db::models::event::Event::latest_id(&mut conn).to_string();
+    HttpResponse::Ok().json(SyncReport { results, token })
+}
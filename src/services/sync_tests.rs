@@ -0,0 +1,39 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body_json, init_service, TestRequest}
+};
+use serde_json::{json, Value};
+use crate::db::{models::Task, establish_connection};
+
+use super::sync::{pull, push};
+
+#[actix_rt::test]
+async fn push_then_pull() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(pull).service(push)).await;
+    let resp = TestRequest::get().uri("/sync?since=abc").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = TestRequest::get().uri("/sync").send_request(&app).await;
+    let full: Value = read_body_json(resp).await;
+    let token = full["token"].as_str().unwrap().to_string();
+
+    let resp = TestRequest::post()
+        .uri("/sync")
+        .set_json(json!({"mutations": [
+            {"ref": "a", "op": "create", "name": "endpoint_test_sync", "due": "tomorrow", "client_ts": "2023-05-10T10:00:00Z"},
+            {"ref": "b", "op": "update", "client_ts": "2023-05-10T10:01:00Z"}
+        ]}))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success());
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["results"][0]["outcome"], "applied");
+    assert_eq!(report["results"][1]["outcome"], "rejected");
+    let id = report["results"][0]["id"].as_str().unwrap().to_string();
+
+    let resp = TestRequest::get().uri(format!("/sync?since={token}").as_str()).send_request(&app).await;
+    let changes: Value = read_body_json(resp).await;
+    assert!(changes["tasks"].as_array().unwrap().iter().any(|t| t["id"] == id.as_str()));
+    let _ = Task::delete_task(&id, &mut establish_connection().get().unwrap());
+}
@@ -1,17 +1,35 @@
 use std::fmt;
-use actix_web::{Responder, web, get, post, put, HttpResponse, http::header::ContentType};
+use actix_web::{Responder, web, get, post, put, HttpRequest, HttpResponse, http::{StatusCode, header::{self, ContentType}}};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use serde::{Serialize, Deserialize, de};
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 
 
-use crate::db::{DbPool, models::Task};
+use crate::db::{models::{DuplicateMode, Task, TaskDetails, TaskError, idempotency::{IdempotencyKey, KeyError}}};
+use crate::utils::date::{DueInput, ParsedDue, parse_snooze};
+use crate::utils::quick::{parse_quick, QuickToken};
+use super::extract::{IfMatch, Store, UserTz};
+
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskForm {
     name: String,
     description: Option<String>,
-    #[serde(deserialize_with = "deserialize_due")]
-    due: Option<chrono::NaiveDateTime>
+    due: Option<DueInput>,
+    #[serde(default)]
+    tags: Vec<String>,
+    project: Option<String>,
+    #[serde(default)]
+    priority: i32,
+    recurrence: Option<String>,
+    scheduled: Option<DueInput>,
+    deadline: Option<DueInput>,
+    all_day: Option<bool>,
+    //what to do when a task with this name exists, allow, reject or existing
+    #[serde(default)]
+    on_duplicate: DuplicateMode
 }
 
 
@@ -21,61 +39,46 @@ pub struct TaskUpdate {
     pub name: String,
     pub description: String,
     pub status: i32,
-    #[serde(deserialize_with = "deserialize_due")]
-    pub due: Option<chrono::NaiveDateTime>,
+    pub due: Option<DueInput>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    #[serde(default)]
+    pub scheduled: Option<DueInput>,
+    #[serde(default)]
+    pub deadline: Option<DueInput>,
+    #[serde(default)]
+    pub all_day: Option<bool>,
+    //the version the edit is based on, If-Match takes precedence
+    #[serde(default)]
+    pub version: Option<i32>,
     #[serde(deserialize_with = "deserialize_ats")]
-    pub created_at: chrono::NaiveDateTime,
+    pub created_at: DateTime<Utc>,
     #[serde(deserialize_with = "deserialize_ats")]
-    pub updated_at: chrono::NaiveDateTime
+    pub updated_at: DateTime<Utc>
 }
-const FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.fZ";
 const FORMATNAIVE: &str = "%Y-%m-%dT%H:%M:%S%.f";
-fn deserialize_due<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
-where
-    D: de::Deserializer<'de>,
-{
-    struct DueDTVisitor;
 
-    impl<'de> de::Visitor<'de> for DueDTVisitor {
-        type Value = Option<NaiveDateTime>;
-    
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("unix datetime str")
-        }
-
-        fn visit_none<E>(self) -> Result<Self::Value, E>
-            where
-                E: de::Error, {
-            Ok(None)
-        }
-
-        fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
-            where
-                D: serde::Deserializer<'de>, {
-                deserializer.deserialize_any(DueDTVisitor)
-        }
-    
-        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            
-            match NaiveDateTime::parse_from_str(v, FORMAT) {
-                        Ok(res) => Ok(Some(res)),
-                        Err(_) => Err(de::Error::custom("dt parse err"))
-                    }
-            }
-    }
-    deserializer.deserialize_option(DueDTVisitor)
+//RFC 3339 with any offset, e.g. 2023-05-10T23:01:00.000Z or 2023-05-11T01:01:00+02:00
+fn parse_rfc3339(v: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(v)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
 }
-fn deserialize_ats<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+
+fn deserialize_ats<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: de::Deserializer<'de>,
 {
     struct DueDTVisitor;
 
     impl<'de> de::Visitor<'de> for DueDTVisitor {
-        type Value = NaiveDateTime;
+        type Value = DateTime<Utc>;
     
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
             formatter.write_str("unix datetime str")
@@ -85,16 +88,13 @@ where
         where
             E: de::Error,
         {
-            if v.ends_with("Z") {
-            match NaiveDateTime::parse_from_str(v, FORMAT) {
-                Ok(res) => Ok(res),
-                Err(_) => Err(de::Error::custom("dt parse err"))
-            }
-            } else {
-            match NaiveDateTime::parse_from_str(v, FORMATNAIVE) {
-                Ok(res) => Ok(res),
-                Err(_) => Err(de::Error::custom("dt parse err"))
-            }
+            //timestamps without an offset are stored as UTC
+            match parse_rfc3339(v) {
+                Some(res) => Ok(res),
+                None => match NaiveDateTime::parse_from_str(v, FORMATNAIVE) {
+                    Ok(res) => Ok(Utc.from_utc_datetime(&res)),
+                    Err(_) => Err(de::Error::custom("dt parse err"))
+                }
             }
         }
     }
@@ -103,52 +103,207 @@ where
 
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct FilterStatus {
-    status: i32 
+pub struct VersionQuery {
+    version: Option<i32>
 }
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FilterText {
     term: String
 }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DueText {
+    text: String
+}
+
+//until is a preset like "tonight", "tomorrow" or "next week" or any due input, null wakes the task
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnoozeForm {
+    until: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuickForm {
+    text: String
+}
 
+//the created task and how each part of the quick add text was read
+#[derive(Debug, Serialize)]
+pub struct QuickResult {
+    task: Task,
+    tokens: Vec<QuickToken>
+}
+
+//what a due input resolved to, so clients can confirm it before saving
+#[derive(Debug, Serialize)]
+pub struct DuePreview {
+    input: String,
+    time_zone: String,
+    #[serde(flatten)]
+    parsed: ParsedDue
+}
+
+pub fn etag(task: &Task) -> header::ETag {
+    header::ETag(header::EntityTag::new_strong(task.version.to_string()))
+}
+
+//412 carries the current copy so the client can merge and retry
+pub fn task_error(err: TaskError) -> HttpResponse {
+    match err {
+        TaskError::InvalidDue => HttpResponse::BadRequest().json("Could not parse due date"),
+        TaskError::NotFound => HttpResponse::NotFound().json("Not Found"),
+        TaskError::Conflict(current) => HttpResponse::PreconditionFailed().insert_header(etag(&current)).json(current),
+        TaskError::Duplicate(existing) => HttpResponse::Conflict().json(existing),
+        TaskError::Database => HttpResponse::InternalServerError().json("Could not store task")
+    }
+}
+
+fn resolve_due(due: Option<&DueInput>, tz: &UserTz) -> Result<Option<ParsedDue>, (StatusCode, serde_json::Value)> {
+    match due {
+        Some(input) => match input.resolve(&tz.0) {
+            Some(parsed) => Ok(Some(parsed)),
+            None => Err((StatusCode::BAD_REQUEST, json!("Could not parse due date")))
+        },
+        None => Ok(None)
+    }
+}
+
+//status and body of a create, kept apart from the response so they can be stored
+//under an idempotency key. `find_or_create` is the store's, or Postgres inside the key's transaction
+fn create_task<F>(task_form: &TaskForm, tz: &UserTz, find_or_create: F) -> (StatusCode, serde_json::Value)
+where
+    F: FnOnce(&str, Option<&str>, Option<DateTime<Utc>>, TaskDetails, DuplicateMode) -> Result<(Task, bool), TaskError>
+{
+    let (due, scheduled, deadline) = match (
+        resolve_due(task_form.due.as_ref(), tz),
+        resolve_due(task_form.scheduled.as_ref(), tz),
+        resolve_due(task_form.deadline.as_ref(), tz)
+    ) {
+        (Ok(due), Ok(scheduled), Ok(deadline)) => (due, scheduled, deadline),
+        (Err(resp), _, _) | (_, Err(resp), _) | (_, _, Err(resp)) => return resp
+    };
+    //date only input like "2023-05-12" or "friday" makes an all day task
+    let all_day = task_form.all_day.unwrap_or_else(|| due.or(deadline).is_some_and(|p| p.all_day));
+    let details = TaskDetails {
+        tags: task_form.tags.clone(),
+        project: task_form.project.clone(),
+        priority: task_form.priority,
+        recurrence: task_form.recurrence.clone(),
+        scheduled: scheduled.map(|p| p.due),
+        deadline: deadline.map(|p| p.due),
+        all_day
+    };
+    match find_or_create(task_form.name.as_str(), task_form.description.as_deref(), due.map(|p| p.due), details, task_form.on_duplicate) {
+        Ok((task, true)) => (StatusCode::CREATED, json!(task)),
+        //on_duplicate "existing" found one, nothing was created
+        Ok((task, false)) => (StatusCode::OK, json!(task)),
+        Err(TaskError::Duplicate(existing)) => (StatusCode::CONFLICT, json!(existing)),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, json!("Could not create task"))
+    }
+}
+
+//with an Idempotency-Key header a retried request gets the first response back
+//instead of creating the task again. the keys are kept in Postgres
 #[post("/create")]
-pub async fn create(task_form: web::Json<TaskForm>, pool: web::Data<DbPool>) -> impl Responder {
-    let mut conn = pool.get().unwrap();
-    match Task::create(task_form.name.as_str(), task_form.description.as_deref(), task_form.due, &mut conn) {
-        Some(task) => HttpResponse::Created().insert_header(ContentType::json()).json(task),
-        _ => HttpResponse::InternalServerError().json("Could not create user")
+pub async fn create(req: HttpRequest, task_form: web::Json<TaskForm>, tz: UserTz, store: Store) -> impl Responder {
+    let key = req.headers().get(IDEMPOTENCY_KEY).map(|v| v.to_str().map(str::trim));
+    let (status, body) = match (key, store.0.pool()) {
+        (None, _) => create_task(&task_form, &tz, |name, descr, due, details, mode| store.0.find_or_create(name, descr, due, details, mode)),
+        (Some(Ok(key)), Some(pool)) if !key.is_empty() => {
+            let mut conn = pool.get().unwrap();
+            let fingerprint = hex::encode(Sha256::digest(serde_json::to_vec(&*task_form).unwrap_or_default()));
+            match IdempotencyKey::run(key, &fingerprint, &mut conn, |conn| {
+                let (status, body) = create_task(&task_form, &tz, |name, descr, due, details, mode| Task::find_or_create(name, descr, due, details, mode, conn));
+                (status.as_u16() as i32, body)
+            }) {
+                Ok((status, body)) => (StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK), body),
+                Err(KeyError::Mismatch) => (StatusCode::UNPROCESSABLE_ENTITY, json!("Idempotency-Key was used for another request")),
+                Err(KeyError::Database) => (StatusCode::INTERNAL_SERVER_ERROR, json!("Could not create task"))
+            }
+        },
+        (Some(Ok(key)), None) if !key.is_empty() => (StatusCode::NOT_IMPLEMENTED, json!("Idempotency-Key needs the database")),
+        (Some(_), _) => (StatusCode::BAD_REQUEST, json!("Invalid Idempotency-Key"))
+    };
+    HttpResponse::build(status).insert_header(ContentType::json()).json(body)
+}
+
+#[post("/quick")]
+pub async fn quick_add(quick_form: web::Json<QuickForm>, tz: UserTz, store: Store) -> impl Responder {
+    let quick = parse_quick(&quick_form.text, &tz.0, Utc::now());
+    if quick.name.is_empty() {
+        return HttpResponse::BadRequest().json("Task name is missing")
+    }
+    match store.0.find_or_create(&quick.name, None, quick.due.map(|d| d.due), quick.details, DuplicateMode::Allow) {
+        Ok((task, _)) => HttpResponse::Created().json(QuickResult { task, tokens: quick.tokens }),
+        _ => HttpResponse::InternalServerError().json("Could not create task")
     }
 }
 
 #[get("/")]
-pub async fn index(pool: web::Data<DbPool>) -> impl Responder {
-    let mut conn = pool.get().unwrap();
-    HttpResponse::Ok().json(Task::list(&mut conn))
+pub async fn index(store: Store) -> impl Responder {
+    HttpResponse::Ok().json(store.0.list())
 }
 #[get("/{id}")]
-pub async fn get_by_id(id: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
-    let mut conn = pool.get().unwrap();
-    match Task::by_id(&id, &mut conn) {
-        Some(task) => HttpResponse::Ok().json(task),
+pub async fn get_by_id(id: web::Path<String>, store: Store) -> impl Responder {
+    match store.0.by_id(&id) {
+        Some(task) => HttpResponse::Ok().insert_header(etag(&task)).json(task),
         _ => HttpResponse::NotFound().json("Not Found")
     }
 }
 
+//stale writes are refused with 412, the base version comes from If-Match, the
+//body's version or, for older clients, the updated_at they read
 #[put("/")]
-pub async fn task_update(task: web::Json<TaskUpdate>, pool: web::Data<DbPool>) -> impl Responder {
-    let mut conn = pool.get().unwrap();
-    match Task::update(task.into_inner(), &mut conn) {
-        Some(tsk) => HttpResponse::Ok().insert_header(ContentType::json()).json(tsk),
-        _ => HttpResponse::NotFound().json("Not Found")
+pub async fn task_update(task: web::Json<TaskUpdate>, if_match: IfMatch, tz: UserTz, store: Store) -> impl Responder {
+    let mut task = task.into_inner();
+    if let IfMatch::Version(v) = if_match {
+        task.version = Some(v);
+    }
+    match store.0.update(task, &tz.0) {
+        Ok(tsk) => HttpResponse::Ok().insert_header(ContentType::json()).insert_header(etag(&tsk)).json(tsk),
+        Err(err) => task_error(err)
+    }
+}
+
+#[post("/tasks/{id}/snooze")]
+pub async fn snooze(id: web::Path<String>, snooze_form: web::Json<SnoozeForm>, if_match: IfMatch, tz: UserTz, store: Store) -> impl Responder {
+    let until = match &snooze_form.until {
+        Some(text) => match parse_snooze(text, &tz.0, Utc::now()) {
+            Some(until) => Some(until),
+            None => return HttpResponse::BadRequest().json("Could not parse snooze time")
+        },
+        None => None
+    };
+    let expected = match if_match {
+        IfMatch::Version(v) => Some(v),
+        _ => None
+    };
+    match store.0.snooze(&id, until, expected) {
+        Ok(tsk) => HttpResponse::Ok().insert_header(etag(&tsk)).json(tsk),
+        Err(err) => task_error(err)
+    }
+}
+
+#[get("/next")]
+pub async fn next_task(store: Store) -> impl Responder {
+    match store.0.next() {
+        Some(tsk) => HttpResponse::Ok().insert_header(etag(&tsk)).json(tsk),
+        _ => HttpResponse::NotFound().json("Nothing to do")
     }
 }
 
+//needs the version being changed, as If-Match or ?version=
 #[get("/set/{id}/{status}")]
-pub async fn set_status(extracted: web::Path<(String, i32)>, pool: web::Data<DbPool>) -> impl Responder {
-    let mut conn = pool.get().unwrap();
-    match Task::set_status(&extracted.0, extracted.1, &mut conn) {
-        Some(tsk) => HttpResponse::Ok().json(tsk),
-        _ => HttpResponse::NotFound().json("Not Found")
+pub async fn set_status(extracted: web::Path<(String, i32)>, version_query: web::Query<VersionQuery>, if_match: IfMatch, store: Store) -> impl Responder {
+    let expected = match (if_match, version_query.version) {
+        (IfMatch::Version(v), _) => Some(v),
+        (IfMatch::Any, _) => None,
+        (IfMatch::Missing, Some(v)) => Some(v),
+        (IfMatch::Missing, None) => return HttpResponse::PreconditionRequired().json("If-Match or version required")
+    };
+    match store.0.set_status(&extracted.0, extracted.1, expected) {
+        Ok(tsk) => HttpResponse::Ok().insert_header(etag(&tsk)).json(tsk),
+        Err(err) => task_error(err)
     }
 }
 
@@ -163,12 +318,22 @@ pub async fn set_status(extracted: web::Path<(String, i32)>, pool: web::Data<DbP
 //}
 
 #[get("/filter")]
-pub async fn filter_text(text_query: web::Query<FilterText>,pool: web::Data<DbPool>) -> impl Responder {
-    let mut conn = pool.get().unwrap();
-    let result = Task::filter(&text_query.term, &mut conn);
+pub async fn filter_text(text_query: web::Query<FilterText>, tz: UserTz, store: Store) -> impl Responder {
+    let result = store.0.filter(&text_query.term, &tz.0);
     match result.len() {
         0 => HttpResponse::NotFound().json("No entries found."),
         _ => HttpResponse::Ok().json(result)
     }
 }
 
+#[get("/parse/due")]
+pub async fn parse_due(due_query: web::Query<DueText>, tz: UserTz) -> impl Responder {
+    match DueInput::Text(due_query.text.clone()).resolve(&tz.0) {
+        Some(parsed) => HttpResponse::Ok().json(DuePreview {
+            input: due_query.text.clone(),
+            time_zone: tz.0.name().to_string(),
+            parsed
+        }),
+        None => HttpResponse::BadRequest().json("Could not parse due date")
+    }
+}
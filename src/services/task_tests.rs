@@ -4,8 +4,10 @@ use actix_web::{
     test::{read_body_json, init_service, TestRequest}
 };
 use actix_rt;
+use chrono::TimeZone;
 use serde_json::json;
-use crate::db::{models::{Task, TaskStatus}, establish_connection};
+use crate::db::{models::{Task, TaskStatus}, establish_connection, store::TaskStore};
+use crate::vault::VaultStore;
 
 use super::task::{
     index, 
@@ -13,7 +15,11 @@ use super::task::{
     get_by_id, 
     task_update, 
     set_status, 
-    filter_text
+    filter_text,
+    parse_due,
+    quick_add,
+    snooze,
+    next_task
 };
 
 
@@ -33,7 +39,7 @@ async fn create_task_from_api() {
     let task: Task = read_body_json(resp).await;
     assert_eq!(task.name, test_name);
     assert_eq!(task.description, test_description);
-    assert_eq!(task.due, Some(chrono::NaiveDateTime::parse_from_str("2023-05-10T23:01:00.000Z", "%Y-%m-%dT%H:%M:%S%.fZ").unwrap()));
+    assert_eq!(task.due, Some(chrono::Utc.with_ymd_and_hms(2023, 5, 10, 23, 1, 0).unwrap()));
 }
 
 #[actix_rt::test]
@@ -66,7 +72,9 @@ async fn retrieve_by_id_api() {
         .send_request(&mut app)
         .await;
     assert!(resp_task.status().is_success(), "Failed to fetch task by id");
+    let tag = resp_task.headers().get("etag").unwrap().to_str().unwrap().to_string();
     let returned_task: Task = read_body_json(resp_task).await;
+    assert_eq!(tag, format!("\"{}\"", returned_task.version));
     assert_eq!(returned_task.id, task.id);
     assert_eq!(returned_task.name, task.name);
     assert_eq!(returned_task.description, task.description);
@@ -97,16 +105,37 @@ async fn update_task() {
         "updated_at": "2023-05-05T11:43:17.082Z"
     });
 
+    //the updated_at sent is not the stored one and there is no If-Match
     let resp_upd = TestRequest::put()
         .uri("/")
-        .set_json(tsk)
+        .set_json(&tsk)
+        .send_request(&mut app)
+        .await;
+    assert_eq!(resp_upd.status().as_u16(), 412);
+    let current: Task = read_body_json(resp_upd).await;
+    assert_eq!(current.version, task.version);
+
+    let resp_upd = TestRequest::put()
+        .uri("/")
+        .insert_header(("If-Match", format!("\"{}\"", task.version)))
+        .set_json(&tsk)
         .send_request(&mut app)
         .await;
     assert!(resp_upd.status().is_success(), "Error updating task");
+    assert_eq!(resp_upd.headers().get("etag").unwrap(), format!("\"{}\"", task.version + 1).as_str());
     let updated_task: Task = read_body_json(resp_upd).await;
     assert_eq!(updated_task.id, task.id);
     assert_eq!(updated_task.name, "endpoint_test_4_update".to_string());
     assert_eq!(updated_task.description, "endpoint_test_4 description update.".to_string());
+
+    //the copy read before is stale now
+    let resp_upd = TestRequest::put()
+        .uri("/")
+        .insert_header(("If-Match", format!("\"{}\"", task.version)))
+        .set_json(&tsk)
+        .send_request(&mut app)
+        .await;
+    assert_eq!(resp_upd.status().as_u16(), 412);
 }
 
 #[actix_rt::test]
@@ -126,6 +155,11 @@ async fn set_status_task() {
         .uri(uri.as_str())
         .send_request(&mut app)
         .await;
+    assert_eq!(resp_status.status().as_u16(), 428);
+    let resp_status = TestRequest::get()
+        .uri(format!("{uri}?version={}", task.version).as_str())
+        .send_request(&mut app)
+        .await;
     assert!(resp_status.status().is_success(), "Failed to update state");
     let t: Task = read_body_json(resp_status).await;
     assert_eq!(t.status, TaskStatus::Done.to_store());
@@ -274,3 +308,272 @@ async fn text_filters_do_status() {
 
 
 }
+
+#[actix_rt::test]
+async fn create_task_with_offset_due() {
+    let request_body = json!({"name": "endpoint_test_offset", "due": "2023-05-11T01:01:00+02:00"});
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(&request_body)
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to create task");
+    let task: Task = read_body_json(resp).await;
+    assert_eq!(task.due, Some(chrono::Utc.with_ymd_and_hms(2023, 5, 10, 23, 1, 0).unwrap()));
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn filter_rejects_unknown_time_zone() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(filter_text)).await;
+    let resp = TestRequest::get()
+        .uri("/filter?term=:due:today")
+        .insert_header(("X-Timezone", "Mars/Olympus_Mons"))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn create_task_with_natural_due() {
+    let request_body = json!({"name": "endpoint_test_natural", "due": "tomorrow 9am"});
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .insert_header(("X-Timezone", "Asia/Tokyo"))
+        .set_json(&request_body)
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to create task");
+    let task: Task = read_body_json(resp).await;
+    let tokyo = task.due.unwrap().with_timezone(&chrono_tz::Asia::Tokyo);
+    assert_eq!(tokyo.format("%H:%M").to_string(), "09:00");
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&task.id, &mut conn).unwrap();
+
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_natural_bad", "due": "whenever"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn echo_parsed_due() {
+    let app = init_service(App::new().service(parse_due)).await;
+    let resp = TestRequest::get()
+        .uri("/parse/due?text=2023-05-12")
+        .insert_header(("X-Timezone", "Europe/Vienna"))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to parse due");
+    let body: serde_json::Value = read_body_json(resp).await;
+    assert_eq!(body["input"], "2023-05-12");
+    assert_eq!(body["time_zone"], "Europe/Vienna");
+    assert_eq!(body["due"], "2023-05-11T22:00:00Z");
+    assert_eq!(body["all_day"], true);
+}
+
+#[actix_rt::test]
+async fn quick_add_task() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(quick_add)).await;
+    let resp = TestRequest::post()
+        .uri("/quick")
+        .set_json(json!({"text": "endpoint_test_quick rent tomorrow 9am #finance +home !high every month"}))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to quick add task");
+    let body: serde_json::Value = read_body_json(resp).await;
+    let task: Task = serde_json::from_value(body["task"].clone()).unwrap();
+    assert_eq!(task.name, "endpoint_test_quick rent");
+    assert_eq!(task.tags, vec!["finance".to_string()]);
+    assert_eq!(task.project, Some("home".to_string()));
+    assert_eq!(task.recurrence, Some("FREQ=MONTHLY".to_string()));
+    assert!(task.due.is_some());
+    assert_eq!(body["tokens"].as_array().unwrap().len(), 5);
+    assert_eq!(body["tokens"][4]["text"], "tomorrow 9am");
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn create_all_day_task() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_all_day", "due": "today", "deadline": "in 3 days"}))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to create task");
+    let task: Task = read_body_json(resp).await;
+    assert!(task.all_day);
+    assert!(task.deadline.is_some());
+    assert_eq!(task.status, TaskStatus::Created.to_store());
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn snooze_task() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create).service(snooze).service(next_task)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_snooze", "due": null}))
+        .send_request(&app)
+        .await;
+    let task: Task = read_body_json(resp).await;
+    let resp = TestRequest::post()
+        .uri(format!("/tasks/{}/snooze", task.id).as_str())
+        .set_json(json!({"until": "tomorrow"}))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to snooze task");
+    let snoozed: Task = read_body_json(resp).await;
+    assert!(snoozed.snoozed_until.unwrap() > chrono::Utc::now());
+    let resp = TestRequest::get().uri("/next").send_request(&app).await;
+    if resp.status().is_success() {
+        let next: Task = read_body_json(resp).await;
+        assert_ne!(next.id, task.id);
+    }
+    let resp = TestRequest::post()
+        .uri(format!("/tasks/{}/snooze", task.id).as_str())
+        .set_json(json!({"until": "someday"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn snooze_checks_if_match() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create).service(snooze)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_snooze_version", "due": null}))
+        .send_request(&app)
+        .await;
+    let task: Task = read_body_json(resp).await;
+    let resp = TestRequest::post()
+        .uri(format!("/tasks/{}/snooze", task.id).as_str())
+        .insert_header(("If-Match", format!("\"{}\"", task.version + 1)))
+        .set_json(json!({"until": "tomorrow"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::PRECONDITION_FAILED);
+    let resp = TestRequest::post()
+        .uri(format!("/tasks/{}/snooze", task.id).as_str())
+        .insert_header(("If-Match", format!("\"{}\"", task.version)))
+        .set_json(json!({"until": "tomorrow"}))
+        .send_request(&app)
+        .await;
+    assert!(resp.status().is_success(), "Failed to snooze task");
+    let snoozed: Task = read_body_json(resp).await;
+    assert_eq!(snoozed.version, task.version + 1);
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&task.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn create_with_duplicate_modes() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create)).await;
+    let name = "endpoint_test_duplicate";
+    let resp = TestRequest::post().uri("/create").set_json(json!({"name": name})).send_request(&app).await;
+    let first: Task = read_body_json(resp).await;
+    let resp = TestRequest::post().uri("/create").set_json(json!({"name": name})).send_request(&app).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let second: Task = read_body_json(resp).await;
+    assert_ne!(first.id, second.id);
+    let resp = TestRequest::post().uri("/create").set_json(json!({"name": name, "on_duplicate": "reject"})).send_request(&app).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let existing: Task = read_body_json(resp).await;
+    assert_eq!(existing.name, name);
+    let resp = TestRequest::post().uri("/create").set_json(json!({"name": name, "on_duplicate": "existing"})).send_request(&app).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let found: Task = read_body_json(resp).await;
+    assert_eq!(found.id, first.id);
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&first.id, &mut conn).unwrap();
+    Task::delete_task(&second.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn create_with_idempotency_key() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new().app_data(web::Data::new(conn_pool)).service(create)).await;
+    let key = uuid::Uuid::new_v4().to_string();
+    let body = json!({"name": "endpoint_test_idempotent", "due": "2023-05-10T23:01:00.000Z"});
+    let resp = TestRequest::post()
+        .uri("/create")
+        .insert_header(("Idempotency-Key", key.as_str()))
+        .set_json(&body)
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let first: Task = read_body_json(resp).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .insert_header(("Idempotency-Key", key.as_str()))
+        .set_json(&body)
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let retried: Task = read_body_json(resp).await;
+    assert_eq!(retried.id, first.id);
+    let resp = TestRequest::post()
+        .uri("/create")
+        .insert_header(("Idempotency-Key", key.as_str()))
+        .set_json(json!({"name": "endpoint_test_idempotent_other"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let mut conn = establish_connection().get().unwrap();
+    Task::delete_task(&first.id, &mut conn).unwrap();
+}
+
+#[actix_rt::test]
+async fn task_endpoints_on_a_vault() {
+    let dir = std::env::temp_dir().join(format!("doit-vault-{}", uuid::Uuid::new_v4().simple()));
+    let store: std::sync::Arc<dyn TaskStore> = std::sync::Arc::new(VaultStore::open(&dir).unwrap());
+    let app = init_service(App::new().app_data(web::Data::from(store)).service(create).service(get_by_id).service(task_update)).await;
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_vault", "due": "2023-05-10T23:01:00.000Z"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let task: Task = read_body_json(resp).await;
+    assert_eq!(task.status, TaskStatus::Overdue.to_store());
+    assert!(dir.join(format!("{}.md", task.id)).exists());
+
+    let resp = TestRequest::get().uri(&format!("/{}", task.id)).send_request(&app).await;
+    assert_eq!(resp.headers().get("etag").unwrap(), "\"1\"");
+    let update = json!({"id": task.id, "name": "endpoint_test_vault renamed", "description": "", "status": 2,
+        "created_at": task.created_at, "updated_at": task.updated_at});
+    let resp = TestRequest::put().uri("/").insert_header(("If-Match", "\"1\"")).set_json(&update).send_request(&app).await;
+    assert!(resp.status().is_success());
+    let resp = TestRequest::put().uri("/").insert_header(("If-Match", "\"1\"")).set_json(&update).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 412);
+    let text = std::fs::read_to_string(dir.join(format!("{}.md", task.id))).unwrap();
+    assert!(text.contains("status: done\n") && text.contains("# endpoint_test_vault renamed\n"));
+
+    let resp = TestRequest::post()
+        .uri("/create")
+        .insert_header(("Idempotency-Key", "vault"))
+        .set_json(json!({"name": "endpoint_test_vault keyed"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_IMPLEMENTED);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,35 @@
+use actix_web::{Responder, web, get, post, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{Task, taskwarrior::{self, TaskwarriorError}}};
+use crate::utils::taskwarrior::document;
+use super::extract::UserTz;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TaskwarriorQuery {
+    filter: Option<String>
+}
+
+//every task that is not deleted or what ?filter= finds, for `task import`
+#[get("/export/taskwarrior")]
+pub async fn export_taskwarrior(query: web::Query<TaskwarriorQuery>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    let tasks = match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(filter) => Task::filter(filter, &tz.0, &mut conn),
+        None => Task::all(&mut conn)
+    };
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(document(&tasks))
+}
+
+//what `task export` writes, read as text so curl needs no content type
+#[post("/import/taskwarrior")]
+pub async fn import_taskwarrior(body: String, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match taskwarrior::import(&body, &mut conn) {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(TaskwarriorError::Invalid(err)) => HttpResponse::BadRequest().json(err),
+        Err(TaskwarriorError::Database) => HttpResponse::InternalServerError().json("Could not import tasks")
+    }
+}
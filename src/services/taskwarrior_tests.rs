@@ -0,0 +1,65 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body, read_body_json, init_service, TestRequest}
+};
+use serde_json::{json, Value};
+use crate::db::{models::{Task, TaskStatus}, establish_connection};
+
+use super::taskwarrior::{export_taskwarrior, import_taskwarrior};
+
+#[actix_rt::test]
+async fn taskwarrior_round_trip() {
+    let conn_pool = establish_connection();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(conn_pool))
+            .service(export_taskwarrior)
+            .service(import_taskwarrior)
+    ).await;
+    let mut conn = establish_connection().get().unwrap();
+    let project = uuid::Uuid::new_v4().simple().to_string();
+    let (first, second) = (uuid::Uuid::new_v4().to_string(), uuid::Uuid::new_v4().to_string());
+    let exported = json!([
+        {"id": 1, "uuid": first, "description": "endpoint_test_taskwarrior", "status": "pending", "project": project,
+         "entry": "20230420T090000Z", "modified": "20230420T090000Z", "tags": ["home"], "priority": "M",
+         "annotations": [{"entry": "20230421T100000Z", "description": "ask about the leak"}]},
+        {"id": 0, "uuid": second, "description": "endpoint_test_taskwarrior done", "status": "completed", "project": project,
+         "entry": "20230420T090000Z", "modified": "20230422T090000Z", "end": "20230422T090000Z", "depends": [first]},
+        {"description": ""}
+    ]);
+
+    let resp = TestRequest::post().uri("/import/taskwarrior").set_payload(exported.to_string()).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["created"].as_array().unwrap().len(), 2);
+    assert_eq!(report["skipped"][0]["index"], 2);
+    let task = Task::by_id(&first, &mut conn).unwrap();
+    assert_eq!(task.description, "ask about the leak");
+    assert_eq!(Task::by_id(&second, &mut conn).unwrap().status, TaskStatus::Done.to_store());
+
+    let resp = TestRequest::get().uri(&format!("/export/taskwarrior?filter=:project:{project}")).send_request(&app).await;
+    let text = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+    let tasks: Vec<Value> = serde_json::from_str(&text).unwrap();
+    let done = tasks.iter().find(|t| t["uuid"] == second.as_str()).unwrap();
+    assert_eq!(done["status"], "completed");
+    assert_eq!(done["depends"], json!([first]));
+    let open = tasks.iter().find(|t| t["uuid"] == first.as_str()).unwrap();
+    assert_eq!(open["annotations"], json!([{"entry": "20230421T100000Z", "description": "ask about the leak"}]));
+    assert_eq!(open["priority"], "M");
+
+    //importing the export again changes nothing, a later modification updates
+    let resp = TestRequest::post().uri("/import/taskwarrior").set_payload(text).send_request(&app).await;
+    let report: Value = read_body_json(resp).await;
+    assert_eq!((report["created"].as_array().unwrap().len(), report["unchanged"].as_u64()), (0, Some(2)));
+    let modified = json!({"uuid": first, "description": "endpoint_test_taskwarrior", "status": "completed", "modified": "20990101T000000Z"});
+    let resp = TestRequest::post().uri("/import/taskwarrior").set_payload(format!("{modified}\n")).send_request(&app).await;
+    let report: Value = read_body_json(resp).await;
+    assert_eq!(report["updated"][0]["version"], task.version + 1);
+    assert_eq!(Task::by_id(&first, &mut conn).unwrap().status, TaskStatus::Done.to_store());
+
+    let resp = TestRequest::post().uri("/import/taskwarrior").set_payload("not json").send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    Task::delete_task(&first, &mut conn).unwrap();
+    Task::delete_task(&second, &mut conn).unwrap();
+}
@@ -0,0 +1,32 @@
+use actix_web::{Responder, web, get, post, HttpResponse, http::header};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{Task, DuplicateMode, todotxt}};
+use crate::utils::todotxt::document;
+use super::extract::UserTz;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TodoTxtQuery {
+    #[serde(default)]
+    on_duplicate: DuplicateMode
+}
+
+//every task that is not deleted as a todo.txt file, dates in the client's zone
+#[get("/export/todotxt")]
+pub async fn export_todotxt(tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header(header::ContentDisposition::attachment("todo.txt"))
+        .body(document(&Task::all(&mut conn), &tz.0))
+}
+
+//a task per line, ?on_duplicate=reject skips lines whose task already exists
+#[post("/import/todotxt")]
+pub async fn import_todotxt(body: String, query: web::Query<TodoTxtQuery>, tz: UserTz, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match todotxt::import(&body, &tz.0, query.on_duplicate, &mut conn) {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(_) => HttpResponse::InternalServerError().json("Could not import tasks")
+    }
+}
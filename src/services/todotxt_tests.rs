@@ -0,0 +1,53 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body, read_body_json, init_service, TestRequest}
+};
+use crate::db::{models::Task, establish_connection};
+
+use super::todotxt::{export_todotxt, import_todotxt};
+
+#[actix_rt::test]
+async fn todotxt_round_trip() {
+    let conn_pool = establish_connection();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(conn_pool))
+            .service(export_todotxt)
+            .service(import_todotxt)
+    ).await;
+    let name = format!("endpoint_test_todotxt {}", uuid::Uuid::new_v4().simple());
+    let file = format!(
+        "(A) 2023-04-20 {name} +Family @phone due:2030-05-10 id:7 rec:+1w\n\
+        \n\
+        x 2023-05-02 2023-04-20 {name} done pri:B\n\
+        (B) @home\n"
+    );
+    let resp = TestRequest::post().uri("/import/todotxt").insert_header(("X-Timezone", "UTC")).set_payload(file.clone()).send_request(&app).await;
+    assert!(resp.status().is_success());
+    let report: serde_json::Value = read_body_json(resp).await;
+    let created = report["created"].as_array().unwrap();
+    assert_eq!(created.len(), 2);
+    assert_eq!(created[0]["project"], "Family");
+    assert_eq!(created[0]["tags"], serde_json::json!(["phone"]));
+    assert_eq!(created[0]["extensions"], serde_json::json!({"id": "7", "rec": "+1w"}));
+    assert_eq!(created[1]["status"], 2);
+    assert_eq!(report["skipped"][0]["line"], 4);
+    assert_eq!(report["skipped"][0]["reason"], "Task text is missing");
+
+    let resp = TestRequest::post().uri("/import/todotxt?on_duplicate=reject").insert_header(("X-Timezone", "UTC")).set_payload(file).send_request(&app).await;
+    let report: serde_json::Value = read_body_json(resp).await;
+    assert!(report["created"].as_array().unwrap().is_empty());
+    assert_eq!(report["skipped"][0]["reason"], "Duplicate name");
+
+    let resp = TestRequest::get().uri("/export/todotxt").insert_header(("X-Timezone", "UTC")).send_request(&app).await;
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/plain"));
+    let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains(&format!("(A) 2023-04-20 {name} +Family @phone due:2030-05-10 id:7 rec:+1w\n")));
+    assert!(body.contains(&format!("x 2023-05-02 2023-04-20 {name} done pri:B\n")));
+
+    let mut conn = establish_connection().get().unwrap();
+    for task in created {
+        Task::delete_task(task["id"].as_str().unwrap(), &mut conn).unwrap();
+    }
+}
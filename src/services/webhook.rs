@@ -0,0 +1,72 @@
+use actix_web::{Responder, web, get, post, delete, HttpResponse};
+use serde::{Serialize, Deserialize};
+
+use crate::db::{DbPool, models::{event::EventKind, webhook::{Webhook, Delivery}}};
+
+//`events` like ["task.created", "task.deleted"], leave it out to receive all of them.
+//without a secret one is generated, either way it is returned once here
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookForm {
+    url: String,
+    #[serde(default)]
+    events: Vec<String>,
+    secret: Option<String>
+}
+
+//the only response that shows the secret
+#[derive(Debug, Serialize)]
+struct CreatedWebhook {
+    #[serde(flatten)]
+    hook: Webhook,
+    secret: String
+}
+
+#[post("/webhooks")]
+pub async fn create_webhook(form: web::Json<WebhookForm>, pool: web::Data<DbPool>) -> impl Responder {
+    let form = form.into_inner();
+    if !form.url.starts_with("http://") && !form.url.starts_with("https://") {
+        return HttpResponse::BadRequest().json("Webhook url must be http or https")
+    }
+    if let Some(unknown) = form.events.iter().find(|e| EventKind::from_str(e).is_none()) {
+        return HttpResponse::BadRequest().json(format!("Unknown event {unknown}"))
+    }
+    let mut conn = pool.get().unwrap();
+    match Webhook::create(&form.url, form.events, form.secret, &mut conn) {
+        Some(hook) => HttpResponse::Created().json(CreatedWebhook { secret: hook.secret.clone(), hook }),
+        _ => HttpResponse::InternalServerError().json("Could not create webhook")
+    }
+}
+
+#[get("/webhooks")]
+pub async fn list_webhooks(pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    HttpResponse::Ok().json(Webhook::list(&mut conn))
+}
+
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook(id: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match Webhook::delete_webhook(&id, &mut conn) {
+        Ok(0) | Err(_) => HttpResponse::NotFound().json("Not Found"),
+        Ok(_) => HttpResponse::NoContent().finish()
+    }
+}
+
+//the delivery log of a webhook, newest first
+#[get("/webhooks/{id}/deliveries")]
+pub async fn webhook_deliveries(id: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match Webhook::by_id(&id, &mut conn) {
+        Some(hook) => HttpResponse::Ok().json(Delivery::for_webhook(&hook.id, &mut conn)),
+        _ => HttpResponse::NotFound().json("Not Found")
+    }
+}
+
+#[post("/webhooks/deliveries/{id}/redeliver")]
+pub async fn redeliver(id: web::Path<i64>, pool: web::Data<DbPool>) -> impl Responder {
+    let mut conn = pool.get().unwrap();
+    match Delivery::redeliver(*id, &mut conn) {
+        Some(delivery) => HttpResponse::Accepted().json(delivery),
+        _ => HttpResponse::NotFound().json("Not Found")
+    }
+}
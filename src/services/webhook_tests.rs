@@ -0,0 +1,63 @@
+use actix_web::{
+    App,
+    web,
+    test::{read_body_json, init_service, TestRequest}
+};
+use serde_json::json;
+use crate::db::{models::{Task, webhook::{Delivery, Webhook}}, establish_connection};
+
+use super::task::create;
+use super::webhook::{create_webhook, list_webhooks, delete_webhook, webhook_deliveries, redeliver};
+
+#[actix_rt::test]
+async fn register_webhook_and_redeliver() {
+    let conn_pool = establish_connection();
+    let app = init_service(App::new()
+        .app_data(web::Data::new(conn_pool))
+        .service(create)
+        .service(list_webhooks)
+        .service(create_webhook)
+        .service(delete_webhook)
+        .service(webhook_deliveries)
+        .service(redeliver)).await;
+    let resp = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({"url": "http://127.0.0.1:9/hook", "events": ["task.exploded"]}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({"url": "http://127.0.0.1:9/hook", "events": ["task.created"], "secret": "s3cret"}))
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let hook: Webhook = read_body_json(resp).await;
+    assert_eq!(hook.secret, "s3cret");
+
+    let resp = TestRequest::get().uri("/webhooks").send_request(&app).await;
+    let listed: serde_json::Value = read_body_json(resp).await;
+    assert!(listed.as_array().unwrap().iter().all(|h| h.get("secret").is_none()));
+
+    let resp = TestRequest::post()
+        .uri("/create")
+        .set_json(json!({"name": "endpoint_test_webhook"}))
+        .send_request(&app)
+        .await;
+    let task: Task = read_body_json(resp).await;
+    let resp = TestRequest::get().uri(format!("/webhooks/{}/deliveries", hook.id).as_str()).send_request(&app).await;
+    let deliveries: Vec<Delivery> = read_body_json(resp).await;
+    assert!(!deliveries.is_empty());
+    let resp = TestRequest::post()
+        .uri(format!("/webhooks/deliveries/{}/redeliver", deliveries[0].id).as_str())
+        .send_request(&app)
+        .await;
+    assert_eq!(resp.status().as_u16(), 202);
+
+    let resp = TestRequest::delete().uri(format!("/webhooks/{}", hook.id).as_str()).send_request(&app).await;
+    assert_eq!(resp.status().as_u16(), 204);
+    let resp = TestRequest::get().uri("/webhooks").send_request(&app).await;
+    let hooks: Vec<Webhook> = read_body_json(resp).await;
+    assert!(!hooks.iter().any(|h| h.id == hook.id));
+    let _ = Task::delete_task(&task.id, &mut establish_connection().get().unwrap());
+}
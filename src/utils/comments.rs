@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use sha2::{Digest, Sha256};
+
+pub const MARKERS: [&str; 3] = ["TODO", "FIXME", "XXX"];
+//bigger files are generated or vendored more often than not
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+//not descended into when the tree is not a git working tree
+const SKIPPED_DIRS: [&str; 6] = ["target", "node_modules", "vendor", "dist", "build", "__pycache__"];
+
+//a TODO, FIXME or XXX comment. `path` is relative to the scanned root and `line` counts from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeComment {
+    pub marker: String,
+    pub text: String,
+    pub path: String,
+    pub line: i32,
+    pub fingerprint: String
+}
+
+//how comments start in a file, by extension or name. None for files that are not scanned
+fn openers(path: &Path) -> Option<&'static [&'static str]> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+    let openers: &'static [&'static str] = match ext.as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "js" | "jsx" | "mjs" | "ts" | "tsx" | "go" | "swift"
+        | "kt" | "kts" | "scala" | "cs" | "php" | "css" | "scss" | "less" | "dart" | "proto" | "groovy" => &["//", "/*", "*"],
+        "py" | "rb" | "sh" | "bash" | "zsh" | "fish" | "yml" | "yaml" | "toml" | "pl" | "r" | "cmake" | "nix"
+        | "conf" | "cfg" | "ps1" | "tf" | "ex" | "exs" | "jl" => &["#"],
+        "sql" | "lua" | "hs" | "elm" | "ada" => &["--"],
+        "html" | "htm" | "vue" | "svelte" => &["<!--", "//", "/*", "*"],
+        "xml" | "svg" | "md" => &["<!--"],
+        "clj" | "cljs" | "el" | "lisp" | "scm" | "ini" | "asm" | "s" => &[";"],
+        "tex" | "erl" | "hrl" | "m" => &["%"],
+        _ => match name.as_str() {
+            "makefile" | "dockerfile" | "containerfile" | "gemfile" | "rakefile" | "justfile" => &["#"],
+            _ => return None
+        }
+    };
+    Some(openers)
+}
+
+//the marker and text of a comment on `line`, like ("TODO", "handle errors") for
+//"x.unwrap(); // TODO(ann): handle errors"
+pub fn parse_line(line: &str, openers: &[&str]) -> Option<(String, String)> {
+    let comment = match line.trim_start().starts_with('*') && openers.contains(&"*") {
+        true => line.trim_start(),
+        false => {
+            let start = openers.iter().filter(|o| **o != "*").filter_map(|o| line.find(o).map(|i| i + o.len())).min()?;
+            &line[start..]
+        }
+    };
+    for (index, _) in comment.match_indices(|c: char| c.is_ascii_uppercase()) {
+        let rest = &comment[index..];
+        let Some(marker) = MARKERS.iter().find(|m| rest.starts_with(**m)) else { continue };
+        let before = comment[..index].chars().next_back();
+        let after = rest[marker.len()..].chars().next();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '_') || after.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            continue
+        }
+        let mut text = rest[marker.len()..].trim_start();
+        //an owner like TODO(ann) or TODO[ann]
+        if let Some(end) = text.strip_prefix(['(', '[']).and_then(|t| t.find([')', ']'])) {
+            text = &text[end + 2..];
+        }
+        let text = text
+            .trim_start_matches([':', '-', '!', ' '])
+            .trim_end()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        return Some((marker.to_string(), text.to_string()))
+    }
+    None
+}
+
+//every comment in a file. the fingerprint leaves the line number out so comments keep
+//their task when code moves around them, repeated comments are told apart by their order
+pub fn scan_text(root: &str, path: &str, text: &str, openers: &[&str]) -> Vec<CodeComment> {
+    let mut comments = Vec::<CodeComment>::new();
+    for (index, line) in text.lines().enumerate() {
+        let Some((marker, text)) = parse_line(line, openers) else { continue };
+        //counted on the same lower case text the key is made of
+        let folded = text.to_lowercase();
+        let repeat = comments.iter().filter(|c| c.marker == marker && c.text.to_lowercase() == folded).count();
+        let key = format!("{root}\0{path}\0{marker}\0{folded}\0{repeat}");
+        comments.push(CodeComment {
+            fingerprint: hex::encode(Sha256::digest(key.as_bytes())),
+            marker,
+            text,
+            path: path.to_string(),
+            line: index as i32 + 1
+        });
+    }
+    comments
+}
+
+//the files of a git working tree, tracked and untracked but not ignored, or else every
+//file below `root` outside hidden and build directories
+pub fn files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    if root.join(".git").exists() {
+        let listed = Command::new("git")
+            .arg("-C").arg(root)
+            .args(["ls-files", "-z", "--cached", "--others", "--exclude-standard"])
+            .output();
+        if let Some(output) = listed.ok().filter(|o| o.status.success()) {
+            return Ok(output.stdout
+                .split(|b| *b == 0)
+                .filter(|p| !p.is_empty())
+                .map(|p| root.join(String::from_utf8_lossy(p).as_ref()))
+                .collect())
+        }
+    }
+    let mut found = Vec::new();
+    walk(root, &mut found)?;
+    Ok(found)
+}
+
+fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let kind = entry.file_type()?;
+        if kind.is_dir() && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+            walk(&entry.path(), found)?;
+        } else if kind.is_file() {
+            found.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+//every comment below `root`, with paths relative to it. unreadable, binary and large files are skipped
+pub fn scan(root: &Path) -> io::Result<Vec<CodeComment>> {
+    let root_key = root.to_string_lossy().to_string();
+    let mut comments = Vec::new();
+    let mut files = files(root)?;
+    files.sort();
+    for file in files {
+        let Some(openers) = openers(&file) else { continue };
+        if fs::metadata(&file).ok().is_none_or(|m| !m.is_file() || m.len() > MAX_FILE_BYTES) {
+            continue
+        }
+        let Ok(text) = fs::read_to_string(&file) else { continue };
+        let relative = file.strip_prefix(root).unwrap_or(&file).to_string_lossy().replace('\\', "/");
+        comments.extend(scan_text(&root_key, &relative, &text, openers));
+    }
+    Ok(comments)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let c = ["//", "/*", "*"];
+        let parsed = |line: &str, openers: &[&str]| parse_line(line, openers).map(|(m, t)| format!("{m}|{t}"));
+        assert_eq!(parsed("x.unwrap(); // TODO(ann): handle errors", &c), Some("TODO|handle errors".to_string()));
+        assert_eq!(parsed("/* FIXME - leaks memory */", &c), Some("FIXME|leaks memory".to_string()));
+        assert_eq!(parsed("   * XXX: racy", &c), Some("XXX|racy".to_string()));
+        assert_eq!(parsed("# TODO", &["#"]), Some("TODO|".to_string()));
+        assert_eq!(parsed("<!-- TODO: alt text -->", &["<!--"]), Some("TODO|alt text".to_string()));
+        assert_eq!(parsed("let todo = \"TODO: not a comment\";", &c), None);
+        assert_eq!(parsed("// TODOS and XXXL are no markers", &c), None);
+        assert_eq!(parsed("-- todo: lower case", &["--"]), None);
+    }
+
+    #[test]
+    fn test_scan_text() {
+        let text = "fn a() {}\n// TODO: same\nfn b() {}\n// TODO: same\n# TODO: not rust\n";
+        let comments = scan_text("/repo", "src/lib.rs", text, &["//", "/*", "*"]);
+        assert_eq!(comments.len(), 2);
+        assert_eq!((comments[0].line, comments[1].line), (2, 4));
+        assert_ne!(comments[0].fingerprint, comments[1].fingerprint);
+        let moved = scan_text("/repo", "src/lib.rs", &format!("\n\n{text}"), &["//", "/*", "*"]);
+        assert_eq!(moved[0].fingerprint, comments[0].fingerprint);
+        assert_eq!(moved[0].line, 4);
+        let cased = scan_text("/repo", "src/lib.rs", "// TODO: Fix x\n// TODO: fix x\n", &["//"]);
+        assert_ne!(cased[0].fingerprint, cased[1].fingerprint);
+        assert!(openers(Path::new("Makefile")).is_some());
+        assert!(openers(Path::new("logo.png")).is_none());
+    }
+}
@@ -0,0 +1,374 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+pub const TZ_HEADER: &str = "X-Timezone";
+const TZ_ENV: &str = "DEFAULT_TZ";
+const NAIVE_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+];
+//"tonight" and snoozing until "tomorrow" or "next week"
+const EVENING_HOUR: u32 = 20;
+const MORNING_HOUR: u32 = 9;
+//epoch values at or above this are milliseconds, in seconds it would be the year 5138
+const EPOCH_MILLIS_FROM: i64 = 100_000_000_000;
+
+//due date as sent by a client, a number is read as unix epoch
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum DueInput {
+    Epoch(i64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ParsedDue {
+    pub due: DateTime<Utc>,
+    pub all_day: bool,
+}
+
+impl DueInput {
+    pub fn resolve(&self, tz: &Tz) -> Option<ParsedDue> {
+        match self {
+            DueInput::Epoch(value) => from_epoch(*value),
+            DueInput::Text(text) => parse_due(text, tz, Utc::now()),
+        }
+    }
+}
+
+impl From<DateTime<Utc>> for DueInput {
+    fn from(dt: DateTime<Utc>) -> Self {
+        DueInput::Text(dt.to_rfc3339())
+    }
+}
+
+impl ParsedDue {
+    fn exact(due: DateTime<Utc>) -> Self {
+        Self { due, all_day: false }
+    }
+    fn all_day(date: NaiveDate, tz: &Tz) -> Self {
+        Self { due: start_of_day(date, tz), all_day: true }
+    }
+}
+
+pub fn parse_tz(name: &str) -> Option<Tz> {
+    name.trim().parse::<Tz>().ok()
+}
+
+//zone used when the client does not send one, configured via DEFAULT_TZ
+pub fn default_tz() -> Tz {
+    std::env::var(TZ_ENV)
+        .ok()
+        .and_then(|name| parse_tz(&name))
+        .unwrap_or(Tz::UTC)
+}
+
+//wall clock time in `tz` to an instant. Times skipped by a DST jump are
+//moved forward by an hour, ambiguous ones take the earlier instant.
+pub fn localize(naive: NaiveDateTime, tz: &Tz) -> DateTime<Utc> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(naive + Duration::hours(1))).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+}
+
+pub fn start_of_day(date: NaiveDate, tz: &Tz) -> DateTime<Utc> {
+    localize(date.and_hms_opt(0, 0, 0).unwrap(), tz)
+}
+
+pub fn today(tz: &Tz) -> NaiveDate {
+    Utc::now().with_timezone(tz).date_naive()
+}
+
+//half open range [start, end) covering `days` calendar days from `date` in `tz`,
+//None when the end is past the last representable date
+pub fn day_range(date: NaiveDate, days: i64, tz: &Tz) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let end = date.checked_add_signed(Duration::try_days(days)?)?;
+    Some((start_of_day(date, tz), start_of_day(end, tz)))
+}
+
+//accepts RFC 3339 with any offset, local date times and bare dates in `tz`,
+//unix epoch seconds or millis and phrases like "tomorrow 5pm", "next friday" or "in 3 days"
+pub fn parse_due(text: &str, tz: &Tz, now: DateTime<Utc>) -> Option<ParsedDue> {
+    let text = text.trim();
+    if let Ok(value) = text.parse::<i64>() {
+        return from_epoch(value)
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(ParsedDue::exact(dt.with_timezone(&Utc)))
+    }
+    for format in NAIVE_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(ParsedDue::exact(localize(naive, tz)))
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(ParsedDue::all_day(date, tz))
+    }
+    parse_natural(&text.to_ascii_lowercase(), tz, now)
+}
+
+//snooze presets "tonight", "tomorrow" (morning) and "next week" (monday morning),
+//anything else is read like a due date
+pub fn parse_snooze(text: &str, tz: &Tz, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let today = now.with_timezone(tz).date_naive();
+    let at = |date: NaiveDate, hour: u32| localize(date.and_hms_opt(hour, 0, 0).unwrap(), tz);
+    match text.trim().to_ascii_lowercase().as_str() {
+        "tonight"   => Some(at(today, EVENING_HOUR)),
+        "tomorrow"  => Some(at(today.succ_opt()?, MORNING_HOUR)),
+        "next week" => Some(at(next_weekday(today, Weekday::Mon, true)?, MORNING_HOUR)),
+        _           => parse_due(text, tz, now).map(|parsed| parsed.due)
+    }
+}
+
+//minutes ahead of the due date for "15 minutes before", "1 hour", "2 days before" or "at due"
+pub fn parse_before(text: &str) -> Option<i32> {
+    let text = text.trim().to_ascii_lowercase();
+    if text == "at due" || text == "0" {
+        return Some(0)
+    }
+    let words = text
+        .split_whitespace()
+        .filter(|w| *w != "before")
+        .collect::<Vec<&str>>();
+    let (amount, unit) = match words.as_slice() {
+        [amount, unit] => (amount.parse::<i32>().ok()?, *unit),
+        [amount] => (amount.parse::<i32>().ok()?, "minutes"),
+        _ => return None
+    };
+    if amount < 0 {
+        return None
+    }
+    match unit.trim_end_matches('s') {
+        "minute" | "min" => Some(amount),
+        "hour" | "hr"    => amount.checked_mul(60),
+        "day"            => amount.checked_mul(60 * 24),
+        "week"           => amount.checked_mul(60 * 24 * 7),
+        _                => None
+    }
+}
+
+fn from_epoch(value: i64) -> Option<ParsedDue> {
+    let millis = if value.abs() >= EPOCH_MILLIS_FROM { value } else { value.checked_mul(1000)? };
+    Utc.timestamp_millis_opt(millis).single().map(ParsedDue::exact)
+}
+
+fn parse_natural(text: &str, tz: &Tz, now: DateTime<Utc>) -> Option<ParsedDue> {
+    let today = now.with_timezone(tz).date_naive();
+    let words = text
+        .split_whitespace()
+        .filter(|w| *w != "at" && *w != "on")
+        .collect::<Vec<&str>>();
+    if words.first() == Some(&"in") {
+        return parse_offset(&words[1..], today, tz, now)
+    }
+    let mut date = None;
+    let mut time = None;
+    let mut i = 0;
+    while i < words.len() {
+        match words[i] {
+            "today" => date = Some(today),
+            "tonight" => {
+                date = Some(today);
+                time = time.or(NaiveTime::from_hms_opt(EVENING_HOUR, 0, 0));
+            }
+            "tomorrow" => date = Some(today.succ_opt()?),
+            "next" => {
+                i += 1;
+                date = match *words.get(i)? {
+                    "week" => Some(next_weekday(today, Weekday::Mon, true)?),
+                    word => Some(next_weekday(today, word.parse::<Weekday>().ok()?, true)?)
+                };
+            }
+            word => {
+                if let Ok(weekday) = word.parse::<Weekday>() {
+                    date = Some(next_weekday(today, weekday, false)?);
+                } else if let Some(t) = parse_time(word) {
+                    time = Some(t);
+                } else if let Some(t) = words.get(i + 1).and_then(|next| parse_time(&format!("{word}{next}"))) {
+                    //"5 pm"
+                    time = Some(t);
+                    i += 1;
+                } else {
+                    return None
+                }
+            }
+        }
+        i += 1;
+    }
+    match (date, time) {
+        (Some(d), Some(t)) => Some(ParsedDue::exact(localize(d.and_time(t), tz))),
+        (Some(d), None) => Some(ParsedDue::all_day(d, tz)),
+        (None, Some(t)) => Some(ParsedDue::exact(localize(today.and_time(t), tz))),
+        (None, None) => None
+    }
+}
+
+//"in 3 days", "in 2 hours", days and weeks land on a whole day. None when the
+//amount is too large for a date
+fn parse_offset(words: &[&str], today: NaiveDate, tz: &Tz, now: DateTime<Utc>) -> Option<ParsedDue> {
+    if words.len() != 2 {
+        return None
+    }
+    let amount = words[0].parse::<i64>().ok()?;
+    let exact = |offset: Duration| now.checked_add_signed(offset).map(ParsedDue::exact);
+    let all_day = |offset: Duration| today.checked_add_signed(offset).map(|date| ParsedDue::all_day(date, tz));
+    match words[1].trim_end_matches('s') {
+        "minute" | "min" => exact(Duration::try_minutes(amount)?),
+        "hour" | "hr"    => exact(Duration::try_hours(amount)?),
+        "day"            => all_day(Duration::try_days(amount)?),
+        "week"           => all_day(Duration::try_weeks(amount)?),
+        _                => None
+    }
+}
+
+//"5pm", "5:30pm", "17:00", "noon", "midnight"
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+    let (clock, offset) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(0))
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(12))
+    } else {
+        (word, None)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None if offset.is_some() => (clock.parse::<u32>().ok()?, 0),
+        None => return None
+    };
+    let hour = match offset {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(offset) => hour % 12 + offset,
+        None => hour
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+//the coming `weekday`, today counts unless `strictly_after` is set
+fn next_weekday(today: NaiveDate, weekday: Weekday, strictly_after: bool) -> Option<NaiveDate> {
+    let ahead = (7 + weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64) % 7;
+    let days = if ahead == 0 && strictly_after { 7 } else { ahead };
+    today.checked_add_signed(Duration::try_days(days)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_start_of_day_in_zone() {
+        let date = NaiveDate::from_ymd_opt(2023, 5, 10).unwrap();
+        let vienna = parse_tz("Europe/Vienna").unwrap();
+        let expected = Utc.with_ymd_and_hms(2023, 5, 9, 22, 0, 0).unwrap();
+        assert_eq!(start_of_day(date, &vienna), expected);
+        let expected_utc = Utc.with_ymd_and_hms(2023, 5, 10, 0, 0, 0).unwrap();
+        assert_eq!(start_of_day(date, &Tz::UTC), expected_utc);
+    }
+
+    #[test]
+    fn test_day_range_over_dst_change() {
+        //clocks in Vienna go forward on 2023-03-26, that day has 23 hours
+        let date = NaiveDate::from_ymd_opt(2023, 3, 26).unwrap();
+        let vienna = parse_tz("Europe/Vienna").unwrap();
+        let (start, end) = day_range(date, 1, &vienna).unwrap();
+        assert_eq!(end - start, Duration::hours(23));
+    }
+
+    #[test]
+    fn test_parse_tz() {
+        assert_eq!(parse_tz(" America/New_York "), Some(Tz::America__New_York));
+        assert_eq!(parse_tz("Mars/Olympus_Mons"), None);
+    }
+
+    //Wednesday 2023-05-10 10:00 in Vienna
+    fn vienna_now() -> (Tz, DateTime<Utc>) {
+        (parse_tz("Europe/Vienna").unwrap(), Utc.with_ymd_and_hms(2023, 5, 10, 8, 0, 0).unwrap())
+    }
+
+    fn vienna(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        localize(NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap(), &parse_tz("Europe/Vienna").unwrap())
+    }
+
+    #[test]
+    fn test_parse_due_absolute() {
+        let (tz, now) = vienna_now();
+        let expected = Utc.with_ymd_and_hms(2023, 5, 10, 23, 1, 0).unwrap();
+        assert_eq!(parse_due("2023-05-10T23:01:00.000Z", &tz, now), Some(ParsedDue::exact(expected)));
+        assert_eq!(parse_due("2023-05-11T01:01:00+02:00", &tz, now), Some(ParsedDue::exact(expected)));
+        assert_eq!(parse_due("2023-05-11 01:01", &tz, now), Some(ParsedDue::exact(expected)));
+        assert_eq!(parse_due("1683759660", &tz, now), Some(ParsedDue::exact(expected)));
+        assert_eq!(parse_due("1683759660000", &tz, now), Some(ParsedDue::exact(expected)));
+        assert_eq!(DueInput::Epoch(1683759660).resolve(&tz), Some(ParsedDue::exact(expected)));
+    }
+
+    #[test]
+    fn test_parse_due_date_only_is_all_day() {
+        let (tz, now) = vienna_now();
+        let parsed = parse_due("2023-05-12", &tz, now).unwrap();
+        assert!(parsed.all_day);
+        assert_eq!(parsed.due, vienna(2023, 5, 12, 0, 0));
+    }
+
+    #[test]
+    fn test_parse_due_natural() {
+        let (tz, now) = vienna_now();
+        assert_eq!(parse_due("tomorrow 5pm", &tz, now), Some(ParsedDue::exact(vienna(2023, 5, 11, 17, 0))));
+        assert_eq!(parse_due("Tomorrow at 5 PM", &tz, now), Some(ParsedDue::exact(vienna(2023, 5, 11, 17, 0))));
+        assert_eq!(parse_due("friday 9:30am", &tz, now), Some(ParsedDue::exact(vienna(2023, 5, 12, 9, 30))));
+        assert_eq!(parse_due("wednesday", &tz, now).unwrap().due, vienna(2023, 5, 10, 0, 0));
+        assert_eq!(parse_due("next wednesday", &tz, now).unwrap().due, vienna(2023, 5, 17, 0, 0));
+        assert_eq!(parse_due("next friday", &tz, now), Some(ParsedDue::all_day(NaiveDate::from_ymd_opt(2023, 5, 12).unwrap(), &tz)));
+        assert_eq!(parse_due("next week", &tz, now).unwrap().due, vienna(2023, 5, 15, 0, 0));
+        assert_eq!(parse_due("in 3 days", &tz, now).unwrap().due, vienna(2023, 5, 13, 0, 0));
+        assert_eq!(parse_due("in 2 hours", &tz, now), Some(ParsedDue::exact(now + Duration::hours(2))));
+        assert_eq!(parse_due("17:45", &tz, now), Some(ParsedDue::exact(vienna(2023, 5, 10, 17, 45))));
+        assert_eq!(parse_due("tonight", &tz, now), Some(ParsedDue::exact(vienna(2023, 5, 10, 20, 0))));
+    }
+
+    #[test]
+    fn test_parse_snooze_presets() {
+        let (tz, now) = vienna_now();
+        assert_eq!(parse_snooze("tonight", &tz, now), Some(vienna(2023, 5, 10, 20, 0)));
+        assert_eq!(parse_snooze("Tomorrow", &tz, now), Some(vienna(2023, 5, 11, 9, 0)));
+        assert_eq!(parse_snooze("next week", &tz, now), Some(vienna(2023, 5, 15, 9, 0)));
+        assert_eq!(parse_snooze("in 2 hours", &tz, now), Some(now + Duration::hours(2)));
+        assert_eq!(parse_snooze("later", &tz, now), None);
+    }
+
+    #[test]
+    fn test_parse_before() {
+        assert_eq!(parse_before("15 minutes before"), Some(15));
+        assert_eq!(parse_before("1 hour"), Some(60));
+        assert_eq!(parse_before("2 days before"), Some(2880));
+        assert_eq!(parse_before("at due"), Some(0));
+        assert_eq!(parse_before("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_due_rejects_garbage() {
+        let (tz, now) = vienna_now();
+        assert_eq!(parse_due("someday", &tz, now), None);
+        assert_eq!(parse_due("13pm", &tz, now), None);
+        assert_eq!(parse_due("in 3 fortnights", &tz, now), None);
+        assert_eq!(parse_due("", &tz, now), None);
+    }
+
+    #[test]
+    fn test_parse_due_offset_overflow() {
+        let (tz, now) = vienna_now();
+        assert_eq!(parse_due("in 1000000000 days", &tz, now), None);
+        assert_eq!(parse_due("in 9999999999999999 minutes", &tz, now), None);
+        assert_eq!(parse_due("in 9223372036854775807 weeks", &tz, now), None);
+        assert_eq!(parse_snooze("in 3000000000 hours", &tz, now), None);
+        assert_eq!(day_range(NaiveDate::MAX, 1, &tz), None);
+    }
+}
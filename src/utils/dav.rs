@@ -0,0 +1,231 @@
+pub const DAV_NS: &str = "DAV:";
+pub const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+pub const CS_NS: &str = "http://calendarserver.org/ns/";
+
+//a property by namespace and local name, like ("DAV:", "getetag")
+pub type PropName = (String, String);
+
+//the properties a PROPFIND or REPORT asks for, `all` for an empty body or <allprop/>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropRequest {
+    pub all: bool,
+    pub props: Vec<PropName>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    //every resource, unless the comp-filter asks for something other than VTODO
+    Query { props: PropRequest, component: Option<String> },
+    Multiget { props: PropRequest, hrefs: Vec<String> },
+}
+
+//one <response> of a multistatus: found properties with their XML content, and the
+//requested ones this resource does not have. `status` replaces both for missing resources
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DavResponse {
+    pub href: String,
+    pub found: Vec<(PropName, String)>,
+    pub missing: Vec<PropName>,
+    pub status: Option<u16>
+}
+
+impl PropRequest {
+    pub fn all() -> Self {
+        Self { all: true, props: Vec::new() }
+    }
+
+    pub fn wants(&self, ns: &str, name: &str) -> bool {
+        self.all || self.props.iter().any(|(n, l)| n == ns && l == name)
+    }
+}
+
+pub fn prop(ns: &str, name: &str) -> PropName {
+    (ns.to_string(), name.to_string())
+}
+
+fn props_of(node: roxmltree::Node) -> PropRequest {
+    match node.children().find(|n| n.has_tag_name((DAV_NS, "prop"))) {
+        Some(prop) => PropRequest {
+            all: false,
+            props: prop
+                .children()
+                .filter(|n| n.is_element())
+                .map(|n| (n.tag_name().namespace().unwrap_or_default().to_string(), n.tag_name().name().to_string()))
+                .collect()
+        },
+        None => PropRequest::all()
+    }
+}
+
+//None when the body is not well formed XML
+pub fn parse_propfind(body: &str) -> Option<PropRequest> {
+    if body.trim().is_empty() {
+        return Some(PropRequest::all())
+    }
+    let doc = roxmltree::Document::parse(body).ok()?;
+    Some(props_of(doc.root_element()))
+}
+
+pub fn parse_report(body: &str) -> Option<Report> {
+    let doc = roxmltree::Document::parse(body).ok()?;
+    let root = doc.root_element();
+    let props = props_of(root);
+    match (root.tag_name().namespace(), root.tag_name().name()) {
+        (Some(CALDAV_NS), "calendar-query") => {
+            //the innermost comp-filter names the component, the outer one is VCALENDAR
+            let component = root
+                .descendants()
+                .filter(|n| n.has_tag_name((CALDAV_NS, "comp-filter")))
+                .filter_map(|n| n.attribute("name"))
+                .map(|name| name.to_ascii_uppercase())
+                .rfind(|name| name != "VCALENDAR");
+            Some(Report::Query { props, component })
+        },
+        (Some(CALDAV_NS), "calendar-multiget") => {
+            let hrefs = root
+                .children()
+                .filter(|n| n.has_tag_name((DAV_NS, "href")))
+                .filter_map(|n| n.text())
+                .map(|h| h.trim().to_string())
+                .collect();
+            Some(Report::Multiget { props, hrefs })
+        },
+        _ => None
+    }
+}
+
+//task ids may come from imported UIDs like abc@example.com, anything but
+//unreserved characters and @ is percent encoded in hrefs and URL paths
+pub fn encode(id: &str) -> String {
+    id.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'@' => (b as char).to_string(),
+            _ => format!("%{b:02X}")
+        })
+        .collect()
+}
+
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::<u8>::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn tag(name: &PropName) -> (String, String) {
+    let prefix = match name.0.as_str() {
+        DAV_NS => "d",
+        CALDAV_NS => "c",
+        CS_NS => "cs",
+        _ => return (format!("x:{} xmlns:x=\"{}\"", name.1, escape_xml(&name.0)), format!("x:{}", name.1))
+    };
+    (format!("{prefix}:{}", name.1), format!("{prefix}:{}", name.1))
+}
+
+fn element(name: &PropName, content: &str) -> String {
+    let (open, close) = tag(name);
+    match content.is_empty() {
+        true => format!("<{open}/>"),
+        false => format!("<{open}>{content}</{close}>")
+    }
+}
+
+fn status_line(code: u16) -> String {
+    let reason = match code {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Unknown"
+    };
+    format!("<d:status>HTTP/1.1 {code} {reason}</d:status>")
+}
+
+//a 207 Multi-Status body
+pub fn multistatus(responses: &[DavResponse]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"{DAV_NS}\" xmlns:c=\"{CALDAV_NS}\" xmlns:cs=\"{CS_NS}\">"
+    );
+    for response in responses {
+        xml.push_str(&format!("<d:response><d:href>{}</d:href>", escape_xml(&response.href)));
+        if let Some(code) = response.status {
+            xml.push_str(&status_line(code));
+        }
+        if !response.found.is_empty() {
+            let props = response.found.iter().map(|(name, content)| element(name, content)).collect::<String>();
+            xml.push_str(&format!("<d:propstat><d:prop>{props}</d:prop>{}</d:propstat>", status_line(200)));
+        }
+        if !response.missing.is_empty() {
+            let props = response.missing.iter().map(|name| element(name, "")).collect::<String>();
+            xml.push_str(&format!("<d:propstat><d:prop>{props}</d:prop>{}</d:propstat>", status_line(404)));
+        }
+        xml.push_str("</d:response>");
+    }
+    xml.push_str("</d:multistatus>");
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_propfind() {
+        let body = r#"<?xml version="1.0"?><propfind xmlns="DAV:" xmlns:CS="http://calendarserver.org/ns/"><prop><getetag/><CS:getctag/></prop></propfind>"#;
+        let props = parse_propfind(body).unwrap();
+        assert!(!props.all);
+        assert_eq!(props.props, vec![prop(DAV_NS, "getetag"), prop(CS_NS, "getctag")]);
+        assert!(parse_propfind("").unwrap().all);
+        assert!(parse_propfind("<d:propfind xmlns:d=\"DAV:\"><d:allprop/></d:propfind>").unwrap().all);
+        assert!(parse_propfind("<propfind").is_none());
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let query = r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><d:getetag/></d:prop>
+            <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter></c:calendar-query>"#;
+        let Some(Report::Query { props, component }) = parse_report(query) else { panic!("not a query") };
+        assert_eq!(props.props, vec![prop(DAV_NS, "getetag")]);
+        assert_eq!(component, Some("VTODO".to_string()));
+        let multiget = r#"<C:calendar-multiget xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav"><D:prop><C:calendar-data/></D:prop>
+            <D:href>/dav/tasks/a.ics</D:href><D:href> /dav/tasks/b.ics </D:href></C:calendar-multiget>"#;
+        let Some(Report::Multiget { hrefs, .. }) = parse_report(multiget) else { panic!("not a multiget") };
+        assert_eq!(hrefs, vec!["/dav/tasks/a.ics".to_string(), "/dav/tasks/b.ics".to_string()]);
+    }
+
+    #[test]
+    fn test_multistatus() {
+        let response = DavResponse {
+            href: "/dav/tasks/a.ics".to_string(),
+            found: vec![(prop(DAV_NS, "getetag"), "\"1\"".to_string()), (prop(DAV_NS, "resourcetype"), String::new())],
+            missing: vec![prop("urn:other", "color")],
+            status: None
+        };
+        let xml = multistatus(&[response]);
+        assert!(xml.contains("<d:getetag>\"1\"</d:getetag><d:resourcetype/>"));
+        assert!(xml.contains("<x:color xmlns:x=\"urn:other\"/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status>"));
+        assert!(roxmltree::Document::parse(&xml).is_ok());
+    }
+}
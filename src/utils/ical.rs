@@ -0,0 +1,462 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+use super::date::{localize, parse_tz, start_of_day, ParsedDue};
+
+const PRODID: &str = "-//doit//tasks//EN";
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const DATE_FORMAT: &str = "%Y%m%d";
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+//content lines longer than this many octets are folded (RFC 5545 3.1)
+const LINE_OCTETS: usize = 75;
+//read or deliberately ignored on import, anything else is reported as unsupported
+const KNOWN_PROPERTIES: [&str; 19] = [
+    "UID", "SUMMARY", "DESCRIPTION", "DUE", "DTSTART", "STATUS", "COMPLETED", "CATEGORIES",
+    "RRULE", "PRIORITY", "X-DOIT-PROJECT", "X-DOIT-DEADLINE", "DTSTAMP", "CREATED",
+    "LAST-MODIFIED", "SEQUENCE", "PERCENT-COMPLETE", "CLASS", "TRANSP",
+];
+
+//one content line, `NAME;PARAM=value:VALUE`, with its value still escaped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String
+}
+
+//a component directly inside VCALENDAR like VTODO or VEVENT, `children` names
+//components nested in it such as VALARM
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Component {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub children: Vec<String>
+}
+
+//tasks as an RFC 5545 calendar of VTODO components. all day dates are written as
+//dates in `tz`, everything else in UTC
+pub fn calendar(tasks: &[Task], tz: &Tz, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{PRODID}"),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:doit".to_string(),
+    ];
+    for task in tasks {
+        lines.extend(vtodo(task, tz, now));
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|l| fold(l)).collect::<String>()
+}
+
+//UID is the task id so clients can match the entries up again
+pub fn vtodo(task: &Task, tz: &Tz, now: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", escape(&task.id)),
+        format!("DTSTAMP:{}", now.format(UTC_FORMAT)),
+        format!("CREATED:{}", task.created_at.format(UTC_FORMAT)),
+        format!("LAST-MODIFIED:{}", task.updated_at.format(UTC_FORMAT)),
+        format!("SEQUENCE:{}", task.version - 1),
+        format!("SUMMARY:{}", escape(&task.name)),
+    ];
+    if !task.description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(&task.description)));
+    }
+    if let Some(scheduled) = task.scheduled {
+        lines.push(date_property("DTSTART", scheduled, task.all_day, tz));
+    }
+    if let Some(due) = task.due {
+        lines.push(date_property("DUE", due, task.all_day, tz));
+    }
+    //iCalendar has no deadline, other clients just skip it
+    if let Some(deadline) = task.deadline {
+        lines.push(date_property("X-DOIT-DEADLINE", deadline, task.all_day, tz));
+    }
+    lines.push(format!("STATUS:{}", status(task.status)));
+    if task.status == TaskStatus::Done.to_store() {
+        lines.push(format!("COMPLETED:{}", task.updated_at.format(UTC_FORMAT)));
+        lines.push("PERCENT-COMPLETE:100".to_string());
+    }
+    if task.priority != TaskPriority::None.to_store() {
+        lines.push(format!("PRIORITY:{}", priority(task.priority)));
+    }
+    if !task.tags.is_empty() {
+        lines.push(format!("CATEGORIES:{}", task.tags.iter().map(|t| escape(t)).collect::<Vec<String>>().join(",")));
+    }
+    if let Some(project) = &task.project {
+        lines.push(format!("X-DOIT-PROJECT:{}", escape(project)));
+    }
+    if let Some(rule) = task.recurrence.as_deref().filter(|r| is_recur(r)) {
+        lines.push(format!("RRULE:{rule}"));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+fn date_property(name: &str, at: DateTime<Utc>, all_day: bool, tz: &Tz) -> String {
+    match all_day {
+        true => format!("{name};VALUE=DATE:{}", at.with_timezone(tz).format(DATE_FORMAT)),
+        false => format!("{name}:{}", at.format(UTC_FORMAT))
+    }
+}
+
+fn status(status: i32) -> &'static str {
+    match status {
+        s if s == TaskStatus::Done.to_store() => "COMPLETED",
+        s if s == TaskStatus::Deleted.to_store() => "CANCELLED",
+        _ => "NEEDS-ACTION"
+    }
+}
+
+//1 is the highest priority in iCalendar, 9 the lowest
+fn priority(priority: i32) -> i32 {
+    match priority {
+        p if p == TaskPriority::High.to_store() => 1,
+        p if p == TaskPriority::Medium.to_store() => 5,
+        _ => 9
+    }
+}
+
+//a TEXT value. control characters other than line breaks are dropped, so nothing
+//can end the content line early
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.replace("\r\n", "\n").chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => {},
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+//a RECUR value like FREQ=WEEKLY;BYDAY=MO,-1FR is not escaped, anything else is left out
+fn is_recur(rule: &str) -> bool {
+    !rule.is_empty() && rule.chars().all(|c| c.is_ascii_alphanumeric() || "=;,+-".contains(c))
+}
+
+//splits a content line into CRLF terminated lines of at most 75 octets, continuation
+//lines start with a space. never splits inside a UTF-8 character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > LINE_OCTETS {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += len;
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+impl Property {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+impl Component {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.property(name).map(|p| unescape(&p.value))
+    }
+}
+
+//the components of a calendar, lines are unfolded first. anything outside a
+//component and malformed lines are dropped
+pub fn parse(text: &str) -> Vec<Component> {
+    let mut components = Vec::<Component>::new();
+    let mut current: Option<Component> = None;
+    let mut depth = 0;
+    for line in unfold(text) {
+        let Some(property) = content_line(&line) else { continue };
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if property.value.eq_ignore_ascii_case("VCALENDAR") => {},
+            ("BEGIN", None) => current = Some(Component { name: property.value.to_ascii_uppercase(), ..Default::default() }),
+            ("BEGIN", Some(component)) => {
+                if depth == 0 {
+                    component.children.push(property.value.to_ascii_uppercase());
+                }
+                depth += 1;
+            },
+            ("END", Some(_)) if depth > 0 => depth -= 1,
+            ("END", Some(_)) => components.extend(current.take()),
+            (_, Some(component)) if depth == 0 => component.properties.push(property),
+            _ => {}
+        }
+    }
+    components
+}
+
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for line in text.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)) {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {},
+            _ => lines.push(line.to_string())
+        }
+    }
+    lines
+}
+
+//splits at the first colon outside a quoted parameter value
+fn content_line(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let colon = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    })?.0;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None
+    }
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(n, v)| (n.trim().to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        .collect();
+    Some(Property { name, params, value: value.to_string() })
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+//a list value like CATEGORIES, split at commas that are not escaped
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => items.push(String::new()),
+            _ => items.last_mut().unwrap().push(c)
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    items.iter().map(|i| unescape(i)).filter(|i| !i.trim().is_empty()).collect()
+}
+
+//DATE values are all day in `tz`, DATE-TIME values are UTC with a trailing Z, in their
+//TZID or else floating and read in `tz`
+pub fn parse_date(property: &Property, tz: &Tz) -> Option<ParsedDue> {
+    let value = property.value.trim();
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, DATE_FORMAT).ok()?;
+        return Some(ParsedDue { due: start_of_day(date, tz), all_day: true })
+    }
+    let (local, utc) = match value.strip_suffix('Z') {
+        Some(local) => (local, true),
+        None => (value, false)
+    };
+    let naive = NaiveDateTime::parse_from_str(local, LOCAL_FORMAT).ok()?;
+    let due = match (utc, property.param("TZID").and_then(parse_tz)) {
+        (true, _) => Utc.from_utc_datetime(&naive),
+        (false, Some(zone)) => localize(naive, &zone),
+        (false, None) => localize(naive, tz)
+    };
+    Some(ParsedDue { due, all_day: false })
+}
+
+//a VTODO as a task with its UID as id, plus the names of properties and nested
+//components that were not imported
+pub fn todo_task(todo: &Component, tz: &Tz) -> Result<(Task, Vec<String>), String> {
+    let name = todo.text("SUMMARY").filter(|s| !s.trim().is_empty()).ok_or("SUMMARY is missing")?;
+    let due = match todo.property("DUE") {
+        Some(p) => Some(parse_date(p, tz).ok_or("Could not parse DUE")?),
+        None => None
+    };
+    let deadline = match todo.property("X-DOIT-DEADLINE") {
+        Some(p) => Some(parse_date(p, tz).ok_or("Could not parse X-DOIT-DEADLINE")?),
+        None => None
+    };
+    let mut task = Task::new(name.trim(), todo.text("DESCRIPTION").as_deref(), due.map(|d| d.due));
+    if let Some(uid) = todo.text("UID").filter(|u| !u.trim().is_empty()) {
+        task.id = uid.trim().to_string();
+    }
+    task.deadline = deadline.map(|d| d.due);
+    task.all_day = due.or(deadline).is_some_and(|d| d.all_day);
+    task.scheduled = todo.property("DTSTART").and_then(|p| parse_date(p, tz)).map(|d| d.due);
+    for tag in todo.properties.iter().filter(|p| p.name == "CATEGORIES").flat_map(|p| split_list(&p.value)) {
+        let tag = tag.trim().to_lowercase();
+        if !task.tags.contains(&tag) {
+            task.tags.push(tag);
+        }
+    }
+    task.project = todo.text("X-DOIT-PROJECT").filter(|p| !p.is_empty());
+    task.recurrence = todo.property("RRULE").map(|p| p.value.trim().to_string()).filter(|r| is_recur(r));
+    task.priority = match todo.property("PRIORITY").and_then(|p| p.value.trim().parse::<i32>().ok()) {
+        Some(1..=4) => TaskPriority::High.to_store(),
+        Some(5) => TaskPriority::Medium.to_store(),
+        Some(6..=9) => TaskPriority::Low.to_store(),
+        _ => TaskPriority::None.to_store()
+    };
+    let completed = todo.property("COMPLETED").and_then(|p| parse_date(p, tz)).map(|d| d.due);
+    task.status = match todo.property("STATUS").map(|p| p.value.trim().to_ascii_uppercase()).as_deref() {
+        Some("COMPLETED") => TaskStatus::Done.to_store(),
+        Some("CANCELLED") => TaskStatus::Deleted.to_store(),
+        _ if completed.is_some() => TaskStatus::Done.to_store(),
+        _ => TaskStatus::Created.to_store()
+    };
+    if let Some(completed) = completed {
+        task.updated_at = completed;
+    }
+    let mut unsupported = Vec::<String>::new();
+    for name in todo.properties.iter().map(|p| &p.name).chain(todo.children.iter()) {
+        if !KNOWN_PROPERTIES.contains(&name.as_str()) && !unsupported.contains(name) {
+            unsupported.push(name.clone());
+        }
+    }
+    Ok((task, unsupported))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn task() -> Task {
+        let mut task = Task::new("Pay rent, finally; really", Some("line one\nline two"), Some(Utc.with_ymd_and_hms(2023, 5, 10, 9, 0, 0).unwrap()));
+        task.id = "task-1".to_string();
+        task.tags = vec!["finance".to_string(), "home".to_string()];
+        task.priority = TaskPriority::High.to_store();
+        task.recurrence = Some("FREQ=MONTHLY".to_string());
+        task
+    }
+
+    #[test]
+    fn test_vtodo() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 0, 0, 0).unwrap();
+        let lines = vtodo(&task(), &Tz::UTC, now);
+        assert!(lines.contains(&"UID:task-1".to_string()));
+        assert!(lines.contains(&"SUMMARY:Pay rent\\, finally\\; really".to_string()));
+        assert!(lines.contains(&"DESCRIPTION:line one\\nline two".to_string()));
+        assert!(lines.contains(&"DUE:20230510T090000Z".to_string()));
+        assert!(lines.contains(&"STATUS:NEEDS-ACTION".to_string()));
+        assert!(lines.contains(&"PRIORITY:1".to_string()));
+        assert!(lines.contains(&"CATEGORIES:finance,home".to_string()));
+        assert!(lines.contains(&"RRULE:FREQ=MONTHLY".to_string()));
+    }
+
+    #[test]
+    fn test_vtodo_done_and_all_day() {
+        let mut task = task();
+        task.status = TaskStatus::Done.to_store();
+        task.all_day = true;
+        let vienna = "Europe/Vienna".parse::<Tz>().unwrap();
+        task.due = Some(Utc.with_ymd_and_hms(2023, 5, 9, 22, 0, 0).unwrap());
+        let lines = vtodo(&task, &vienna, Utc::now());
+        assert!(lines.contains(&"DUE;VALUE=DATE:20230510".to_string()));
+        assert!(lines.contains(&"STATUS:COMPLETED".to_string()));
+        assert!(lines.iter().any(|l| l.starts_with("COMPLETED:")));
+    }
+
+    #[test]
+    fn test_parse_todo() {
+        let text = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTODO\r\nUID:abc@example.com\r\n\
+            SUMMARY:Pay rent\\, finally\r\nDESCRIPTION:one\\ntwo\r\nDUE;TZID=Europe/Vienna:20230510T110000\r\n\
+            STATUS:COMPLETED\r\nCOMPLETED:20230509T080000Z\r\nCATEGORIES:Finance,home\r\nCATEGORIES:home\r\n\
+            RRULE:FREQ=MONTHLY\r\nPRIORITY:5\r\nGEO:48.2;16.3\r\nBEGIN:VALARM\r\nTRIGGER:-PT15M\r\nEND:VALARM\r\n\
+            END:VTODO\r\nBEGIN:VEVENT\r\nSUMMARY:meeting\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let components = parse(text);
+        assert_eq!(components.iter().map(|c| c.name.as_str()).collect::<Vec<&str>>(), vec!["VTODO", "VEVENT"]);
+        let (task, unsupported) = todo_task(&components[0], &Tz::UTC).unwrap();
+        assert_eq!(task.id, "abc@example.com");
+        assert_eq!(task.name, "Pay rent, finally");
+        assert_eq!(task.description, "one\ntwo");
+        assert_eq!(task.due, Some(Utc.with_ymd_and_hms(2023, 5, 10, 9, 0, 0).unwrap()));
+        assert_eq!(task.status, TaskStatus::Done.to_store());
+        assert_eq!(task.updated_at, Utc.with_ymd_and_hms(2023, 5, 9, 8, 0, 0).unwrap());
+        assert_eq!(task.tags, vec!["finance".to_string(), "home".to_string()]);
+        assert_eq!(task.recurrence, Some("FREQ=MONTHLY".to_string()));
+        assert_eq!(task.priority, TaskPriority::Medium.to_store());
+        assert_eq!(unsupported, vec!["GEO".to_string(), "VALARM".to_string()]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let exported = calendar(&[task()], &Tz::UTC, Utc::now());
+        let components = parse(&exported);
+        let (task, unsupported) = todo_task(&components[0], &Tz::UTC).unwrap();
+        assert_eq!((task.id.as_str(), task.name.as_str()), ("task-1", "Pay rent, finally; really"));
+        assert_eq!(task.description, "line one\nline two");
+        assert_eq!(task.tags, vec!["finance".to_string(), "home".to_string()]);
+        assert_eq!(task.priority, TaskPriority::High.to_store());
+        assert!(unsupported.is_empty());
+        let folded = ["BEGIN:VTODO".to_string(), format!("SUMMARY:{}", "x".repeat(100)), "END:VTODO".to_string()]
+            .iter()
+            .map(|l| fold(l))
+            .collect::<String>();
+        assert_eq!(todo_task(&parse(&folded)[0], &Tz::UTC).unwrap().0.name.len(), 100);
+        assert!(todo_task(&parse("BEGIN:VTODO\nUID:1\nEND:VTODO\n")[0], &Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_fold() {
+        let long = format!("DESCRIPTION:{}", "ü".repeat(60));
+        let folded = fold(&long);
+        assert!(folded.split("\r\n").all(|l| l.len() <= LINE_OCTETS));
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), long);
+        let cal = calendar(&[task()], &Tz::UTC, Utc::now());
+        assert!(cal.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(cal.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_vtodo_strips_line_breaks() {
+        let mut task = task();
+        task.id = "evil\r\nBEGIN:VEVENT".to_string();
+        task.recurrence = Some("FREQ=DAILY\r\nSUMMARY:injected".to_string());
+        task.name = "bell\u{7}".to_string();
+        let lines = vtodo(&task, &Tz::UTC, Utc::now());
+        assert!(lines.contains(&"UID:evil\\nBEGIN:VEVENT".to_string()));
+        assert!(lines.contains(&"SUMMARY:bell".to_string()));
+        assert!(!lines.iter().any(|l| l.starts_with("RRULE")));
+        assert!(lines.iter().all(|l| !l.contains('\r') && !l.contains('\n')));
+        assert_eq!(parse(&calendar(&[task], &Tz::UTC, Utc::now())).len(), 1);
+    }
+
+    #[test]
+    fn test_deadline_round_trip() {
+        let mut task = task();
+        task.due = None;
+        task.deadline = Some(Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap());
+        let lines = vtodo(&task, &Tz::UTC, Utc::now());
+        assert!(!lines.iter().any(|l| l.starts_with("DUE")));
+        assert!(lines.contains(&"X-DOIT-DEADLINE:20230601T120000Z".to_string()));
+        let (parsed, unsupported) = todo_task(&parse(&calendar(&[task.clone()], &Tz::UTC, Utc::now()))[0], &Tz::UTC).unwrap();
+        assert_eq!((parsed.due, parsed.deadline), (None, task.deadline));
+        assert!(unsupported.is_empty());
+    }
+}
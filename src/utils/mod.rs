@@ -1,2 +1,11 @@
 pub mod sort;
 pub mod parse;
+pub mod date;
+pub mod quick;
+pub mod ical;
+pub mod dav;
+pub mod todotxt;
+pub mod spreadsheet;
+pub mod notes;
+pub mod comments;
+pub mod taskwarrior;
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+
+const NO_PROJECT: &str = "No project";
+
+//the headings tasks are listed under
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grouping {
+    #[default]
+    Project,
+    Status,
+    None,
+}
+
+//groups in heading order, tasks keep their order within a group. projects are sorted
+//with tasks outside one last, statuses go overdue, open, done. deleted tasks are left out
+pub fn group(tasks: &[Task], by: Grouping) -> Vec<(String, Vec<&Task>)> {
+    let tasks = tasks.iter().filter(|t| t.status != TaskStatus::Deleted.to_store());
+    let mut groups = Vec::<(String, Vec<&Task>)>::new();
+    for task in tasks {
+        let heading = match by {
+            Grouping::None => String::new(),
+            Grouping::Project => task.project.clone().unwrap_or(NO_PROJECT.to_string()),
+            Grouping::Status => status_heading(task.status).to_string()
+        };
+        match groups.iter_mut().find(|(h, _)| *h == heading) {
+            Some((_, group)) => group.push(task),
+            None => groups.push((heading, vec![task]))
+        }
+    }
+    match by {
+        Grouping::Project => groups.sort_by_key(|(h, _)| (h == NO_PROJECT, h.to_lowercase())),
+        Grouping::Status => groups.sort_by_key(|(h, _)| ["Overdue", "Open", "Done"].iter().position(|s| s == h)),
+        Grouping::None => ()
+    }
+    groups
+}
+
+fn status_heading(status: i32) -> &'static str {
+    match status {
+        s if s == TaskStatus::Overdue.to_store() => "Overdue",
+        s if s == TaskStatus::Done.to_store() => "Done",
+        _ => "Open"
+    }
+}
+
+//GitHub task lists under a ## heading per group, dates in `tz` with descriptions indented below
+pub fn markdown(tasks: &[Task], by: Grouping, tz: &Tz) -> String {
+    let mut text = String::new();
+    for (heading, tasks) in group(tasks, by) {
+        if !heading.is_empty() {
+            text.push_str(&format!("## {heading}\n\n"));
+        }
+        for task in tasks {
+            let done = if task.status == TaskStatus::Done.to_store() { "x" } else { " " };
+            let mut dates = Vec::<String>::new();
+            if let Some(due) = task.due {
+                dates.push(format!("due {}", date(due, task.all_day, tz)));
+            }
+            if let Some(deadline) = task.deadline {
+                dates.push(format!("deadline {}", date(deadline, task.all_day, tz)));
+            }
+            let dates = match dates.is_empty() {
+                true => String::new(),
+                false => format!(" ({})", dates.join(", "))
+            };
+            text.push_str(&format!("- [{done}] {}{dates}\n", task.name));
+            for line in task.description.lines().filter(|l| !l.trim().is_empty()) {
+                text.push_str(&format!("  {}\n", line.trim_end()));
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+//Org mode TODO and DONE headlines with priority cookies, tags, DEADLINE and SCHEDULED
+//timestamps and a CLOSED one for done tasks. groups become top level headlines
+pub fn org(tasks: &[Task], by: Grouping, tz: &Tz) -> String {
+    let mut text = String::new();
+    for (heading, tasks) in group(tasks, by) {
+        let stars = match heading.is_empty() {
+            true => "*",
+            false => {
+                text.push_str(&format!("* {heading}\n"));
+                "**"
+            }
+        };
+        for task in tasks {
+            let done = task.status == TaskStatus::Done.to_store();
+            let mut headline = format!("{stars} {}", if done { "DONE" } else { "TODO" });
+            if let Some(cookie) = priority_cookie(task.priority) {
+                headline.push_str(&format!(" [#{cookie}]"));
+            }
+            headline.push_str(&format!(" {}", task.name));
+            if !task.tags.is_empty() {
+                let tags = task.tags.iter().map(|t| org_tag(t)).collect::<Vec<_>>().join(":");
+                headline.push_str(&format!(" :{tags}:"));
+            }
+            text.push_str(&headline);
+            text.push('\n');
+
+            let mut planning = Vec::<String>::new();
+            if done {
+                planning.push(format!("CLOSED: [{}]", timestamp(task.updated_at, false, tz)));
+            }
+            if let Some(deadline) = task.deadline.or(task.due) {
+                planning.push(format!("DEADLINE: <{}>", timestamp(deadline, task.all_day, tz)));
+            }
+            if let Some(scheduled) = task.scheduled {
+                planning.push(format!("SCHEDULED: <{}>", timestamp(scheduled, task.all_day, tz)));
+            }
+            let indent = " ".repeat(stars.len() + 1);
+            if !planning.is_empty() {
+                text.push_str(&format!("{indent}{}\n", planning.join(" ")));
+            }
+            for line in task.description.lines().filter(|l| !l.trim().is_empty()) {
+                //a line starting with * would read as a headline
+                text.push_str(&format!("{indent}{}\n", line.trim_end().trim_start_matches('*')));
+            }
+        }
+    }
+    text
+}
+
+fn date(at: DateTime<Utc>, all_day: bool, tz: &Tz) -> String {
+    let local = at.with_timezone(tz);
+    match all_day {
+        true => local.format("%Y-%m-%d").to_string(),
+        false => local.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+fn timestamp(at: DateTime<Utc>, all_day: bool, tz: &Tz) -> String {
+    let local = at.with_timezone(tz);
+    match all_day {
+        true => local.format("%Y-%m-%d %a").to_string(),
+        false => local.format("%Y-%m-%d %a %H:%M").to_string()
+    }
+}
+
+fn priority_cookie(priority: i32) -> Option<char> {
+    match priority {
+        p if p == TaskPriority::High.to_store() => Some('A'),
+        p if p == TaskPriority::Medium.to_store() => Some('B'),
+        p if p == TaskPriority::Low.to_store() => Some('C'),
+        _ => None
+    }
+}
+
+//org tags are letters, numbers, _ and @
+fn org_tag(tag: &str) -> String {
+    tag.chars().map(|c| if c.is_alphanumeric() || c == '@' { c } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tasks() -> Vec<Task> {
+        let mut rent = Task::new("Pay rent", Some("transfer\n\nto landlord"), Some(Utc.with_ymd_and_hms(2023, 5, 10, 0, 0, 0).unwrap()));
+        rent.all_day = true;
+        rent.project = Some("home".to_string());
+        rent.priority = TaskPriority::High.to_store();
+        rent.tags = vec!["finance".to_string(), "to-do".to_string()];
+        let mut plants = Task::new("Water plants", None, None);
+        plants.status = TaskStatus::Done.to_store();
+        plants.updated_at = Utc.with_ymd_and_hms(2023, 5, 2, 8, 30, 0).unwrap();
+        let mut report = Task::new("Write report", None, Some(Utc.with_ymd_and_hms(2023, 5, 12, 16, 0, 0).unwrap()));
+        report.status = TaskStatus::Overdue.to_store();
+        report.project = Some("Work".to_string());
+        report.scheduled = Some(Utc.with_ymd_and_hms(2023, 5, 11, 7, 0, 0).unwrap());
+        let mut gone = Task::new("Gone", None, None);
+        gone.status = TaskStatus::Deleted.to_store();
+        vec![plants, rent, report, gone]
+    }
+
+    #[test]
+    fn test_group() {
+        let tasks = tasks();
+        let headings = |by| group(&tasks, by).into_iter().map(|(h, _)| h).collect::<Vec<String>>();
+        assert_eq!(headings(Grouping::Project), vec!["home", "Work", "No project"]);
+        assert_eq!(headings(Grouping::Status), vec!["Overdue", "Open", "Done"]);
+        assert_eq!(group(&tasks, Grouping::None)[0].1.len(), 3);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let text = markdown(&tasks(), Grouping::Project, &Tz::Europe__Berlin);
+        assert!(text.starts_with("## home\n\n- [ ] Pay rent (due 2023-05-10)\n  transfer\n  to landlord\n\n## Work\n"));
+        assert!(text.contains("- [ ] Write report (due 2023-05-12 18:00)\n"));
+        assert!(text.ends_with("## No project\n\n- [x] Water plants\n\n"));
+        assert!(!text.contains("Gone"));
+    }
+
+    #[test]
+    fn test_org() {
+        let text = org(&tasks(), Grouping::None, &Tz::UTC);
+        assert!(text.starts_with("* DONE Water plants\n  CLOSED: [2023-05-02 Tue 08:30]\n"));
+        assert!(text.contains("* TODO [#A] Pay rent :finance:to_do:\n  DEADLINE: <2023-05-10 Wed>\n  transfer\n  to landlord\n"));
+        assert!(text.contains("* TODO Write report\n  DEADLINE: <2023-05-12 Fri 16:00> SCHEDULED: <2023-05-11 Thu 07:00>\n"));
+        let text = org(&tasks(), Grouping::Project, &Tz::UTC);
+        assert!(text.starts_with("* home\n** TODO [#A] Pay rent :finance:to_do:\n   DEADLINE: <2023-05-10 Wed>\n"));
+    }
+}
@@ -1,7 +1,8 @@
 pub fn parse_search_value(text: &str) -> (String, Vec<String>) {
-    let n = text[1..].find(':').unwrap() + 1;
+    //a term typed halfway like ":proj" is a key without values
+    let n = text[1..].find(':').map_or(text.len(), |i| i + 1);
     let key = text[1..n].to_string();
-    let value = text[n+1..].to_string();
+    let value = text.get(n+1..).unwrap_or_default().to_string();
     let mut current = String::new();
     let mut values = Vec::new();
     for ch in value.chars() {
@@ -12,7 +13,7 @@ pub fn parse_search_value(text: &str) -> (String, Vec<String>) {
             current.push(ch.to_ascii_lowercase())
         }
     }
-    if current.len() > 0 {
+    if !current.is_empty() {
         values.push(current)
     }
     (key, values)
@@ -42,5 +43,10 @@ mod test {
         assert_eq!(parsed.0, status);
         assert_eq!(parsed.1, vec!["overdue".to_string(), "done".to_string()]);
     }
+    #[test]
+    fn test_parse_search_value_unfinished() {
+        assert_eq!(parse_search_value(":proj"), ("proj".to_string(), Vec::<String>::new()));
+        assert_eq!(parse_search_value(":"), (String::new(), Vec::<String>::new()));
+        assert_eq!(parse_search_value(":tag:"), ("tag".to_string(), Vec::<String>::new()));
+    }
 }
-
@@ -0,0 +1,197 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use crate::db::models::{TaskDetails, TaskPriority};
+use super::date::{parse_due, ParsedDue};
+
+//longest run of words tried as a due date, "next friday at 5 pm" has five
+const MAX_DUE_WORDS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuickToken {
+    pub text: String,
+    pub field: String,
+    pub value: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct QuickAdd {
+    pub name: String,
+    pub due: Option<ParsedDue>,
+    pub details: TaskDetails,
+    pub tokens: Vec<QuickToken>,
+}
+
+impl QuickAdd {
+    fn interpret(&mut self, words: &[&str], field: &str, value: String) {
+        self.tokens.push(QuickToken {
+            text: words.join(" "),
+            field: field.to_string(),
+            value,
+        });
+    }
+}
+
+//"Pay rent tomorrow 9am #finance +home !high every month", words that are not
+//understood stay in the name
+pub fn parse_quick(text: &str, tz: &Tz, now: DateTime<Utc>) -> QuickAdd {
+    let words = text.split_whitespace().collect::<Vec<&str>>();
+    let mut quick = QuickAdd::default();
+    let mut rest = Vec::<&str>::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i];
+        if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+            quick.details.tags.push(tag.to_lowercase());
+            quick.interpret(&words[i..=i], "tags", tag.to_lowercase());
+        } else if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            quick.details.project = Some(project.to_string());
+            quick.interpret(&words[i..=i], "project", project.to_string());
+        } else if let Some(priority) = parse_priority(word) {
+            quick.details.priority = priority.to_store();
+            quick.interpret(&words[i..=i], "priority", priority.to_string());
+        } else if let Some((rule, used)) = parse_recurrence(&words[i..]) {
+            quick.interpret(&words[i..i + used], "recurrence", rule.clone());
+            quick.details.recurrence = Some(rule);
+            i += used;
+            continue
+        } else {
+            rest.push(word);
+        }
+        i += 1;
+    }
+    if let Some((start, end, due)) = find_due(&rest, tz, now) {
+        quick.interpret(&rest[start..end], "due", due.due.to_rfc3339());
+        quick.details.all_day = due.all_day;
+        quick.due = Some(due);
+        rest.drain(start..end);
+    }
+    quick.name = rest.join(" ");
+    quick
+}
+
+//priority markers: "!", "!!" and "!!!" or "!low", "!medium" and "!high"
+fn parse_priority(word: &str) -> Option<TaskPriority> {
+    let marker = word.strip_prefix('!')?;
+    match marker {
+        ""   => Some(TaskPriority::Low),
+        "!"  => Some(TaskPriority::Medium),
+        "!!" => Some(TaskPriority::High),
+        name => TaskPriority::from_str(name)
+    }
+}
+
+//"daily", "every month", "every 2 weeks" or "every monday" as an RRULE value
+pub fn parse_recurrence(words: &[&str]) -> Option<(String, usize)> {
+    let first = words.first()?.to_ascii_lowercase();
+    if let Some(freq) = frequency(&first, true) {
+        return Some((format!("FREQ={freq}"), 1))
+    }
+    if first != "every" {
+        return None
+    }
+    let next = words.get(1)?.to_ascii_lowercase();
+    if let Some(day) = weekday_code(&next) {
+        return Some((format!("FREQ=WEEKLY;BYDAY={day}"), 2))
+    }
+    if let Some(freq) = frequency(&next, false) {
+        return Some((format!("FREQ={freq}"), 2))
+    }
+    let interval = next.parse::<u32>().ok().filter(|n| *n > 0)?;
+    let freq = frequency(&words.get(2)?.to_ascii_lowercase(), false)?;
+    if interval == 1 {
+        Some((format!("FREQ={freq}"), 3))
+    } else {
+        Some((format!("FREQ={freq};INTERVAL={interval}"), 3))
+    }
+}
+
+fn frequency(word: &str, adverb: bool) -> Option<&'static str> {
+    let word = if adverb { word } else { word.trim_end_matches('s') };
+    match (word, adverb) {
+        ("daily", true) | ("day", false)     => Some("DAILY"),
+        ("weekly", true) | ("week", false)   => Some("WEEKLY"),
+        ("monthly", true) | ("month", false) => Some("MONTHLY"),
+        ("yearly", true) | ("year", false)   => Some("YEARLY"),
+        _ => None
+    }
+}
+
+fn weekday_code(word: &str) -> Option<&'static str> {
+    let day = word.parse::<chrono::Weekday>().ok()?;
+    Some(match day {
+        chrono::Weekday::Mon => "MO",
+        chrono::Weekday::Tue => "TU",
+        chrono::Weekday::Wed => "WE",
+        chrono::Weekday::Thu => "TH",
+        chrono::Weekday::Fri => "FR",
+        chrono::Weekday::Sat => "SA",
+        chrono::Weekday::Sun => "SU",
+    })
+}
+
+//longest run of words that reads as a due date, plain numbers are left in the
+//name so "Buy 2 apples" does not end up in 1970
+fn find_due(words: &[&str], tz: &Tz, now: DateTime<Utc>) -> Option<(usize, usize, ParsedDue)> {
+    for len in (1..=MAX_DUE_WORDS.min(words.len())).rev() {
+        for start in 0..=words.len() - len {
+            let span = &words[start..start + len];
+            if span.iter().all(|w| w.parse::<i64>().is_ok() || *w == "at" || *w == "on") {
+                continue
+            }
+            if let Some(due) = parse_due(&span.join(" "), tz, now) {
+                return Some((start, start + len, due))
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::utils::date::{localize, parse_tz};
+
+    fn vienna_now() -> (Tz, DateTime<Utc>) {
+        (parse_tz("Europe/Vienna").unwrap(), Utc.with_ymd_and_hms(2023, 5, 10, 8, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn test_parse_quick_full() {
+        let (tz, now) = vienna_now();
+        let quick = parse_quick("Pay rent tomorrow 9am #finance +home !high every month", &tz, now);
+        assert_eq!(quick.name, "Pay rent");
+        let expected = localize(chrono::NaiveDate::from_ymd_opt(2023, 5, 11).unwrap().and_hms_opt(9, 0, 0).unwrap(), &tz);
+        assert_eq!(quick.due.unwrap().due, expected);
+        assert_eq!(quick.details.tags, vec!["finance".to_string()]);
+        assert_eq!(quick.details.project, Some("home".to_string()));
+        assert_eq!(quick.details.priority, TaskPriority::High.to_store());
+        assert_eq!(quick.details.recurrence, Some("FREQ=MONTHLY".to_string()));
+        let fields = quick.tokens.iter().map(|t| t.field.as_str()).collect::<Vec<&str>>();
+        assert_eq!(fields, vec!["tags", "project", "priority", "recurrence", "due"]);
+        assert_eq!(quick.tokens[4].text, "tomorrow 9am");
+    }
+
+    #[test]
+    fn test_parse_quick_keeps_remainder() {
+        let (tz, now) = vienna_now();
+        let quick = parse_quick("Buy 2 apples", &tz, now);
+        assert_eq!(quick.name, "Buy 2 apples");
+        assert!(quick.due.is_none());
+        assert!(quick.tokens.is_empty());
+        let quick = parse_quick("Call mom on friday at 5 pm !! #family", &tz, now);
+        assert_eq!(quick.name, "Call mom");
+        assert_eq!(quick.details.priority, TaskPriority::Medium.to_store());
+        assert!(!quick.due.unwrap().all_day);
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        assert_eq!(parse_recurrence(&["daily"]), Some(("FREQ=DAILY".to_string(), 1)));
+        assert_eq!(parse_recurrence(&["every", "2", "weeks", "x"]), Some(("FREQ=WEEKLY;INTERVAL=2".to_string(), 3)));
+        assert_eq!(parse_recurrence(&["every", "Monday"]), Some(("FREQ=WEEKLY;BYDAY=MO".to_string(), 2)));
+        assert_eq!(parse_recurrence(&["every", "now"]), None);
+    }
+}
@@ -3,18 +3,12 @@ use crate::db::models::Task;
 
 #[derive(Debug)]
 struct TaskScored {
-    id: String,
-    name: String,
-    description: String,
-    status: i32,
-    due: Option<chrono::NaiveDateTime>,
-    created_at: chrono::NaiveDateTime,
-    updated_at: chrono::NaiveDateTime,
+    task: Task,
     distance: usize,
 }
 
 fn min_of_three(a: usize, b: usize, c: usize) -> usize {
-    return cmp::min(cmp::min(a, b), c)
+    cmp::min(cmp::min(a, b), c)
 } 
 
 
@@ -34,12 +28,12 @@ pub fn wagner_fisher(source: &str, target: &str) -> usize {
     //zero initialize 2d matrix    
     let mut d = vec![vec![0; n]; m];
     //source to target prefixes is always just a dropping of char
-    for i in 1..m {
-        d[i][0] = i;
+    for (i, row) in d.iter_mut().enumerate().skip(1) {
+        row[0] = i;
     }
     //the reverse is true for target    
-    for j in 1..n {
-        d[0][j] = j;
+    for (j, cell) in d[0].iter_mut().enumerate().skip(1) {
+        *cell = j;
     }
     
     let mut sub_cost;
@@ -57,7 +51,7 @@ pub fn wagner_fisher(source: &str, target: &str) -> usize {
             );
         }
     }
-    return d[m -1][n-1]
+    d[m -1][n-1]
 }
 
 
@@ -75,17 +69,9 @@ fn get_min_distance_from_words(words: &str, term: &str) -> usize {
     min_dist as usize
 }
 
-impl Into<Task> for TaskScored {
-    fn into(self) -> Task {
-        Task { 
-            id: self.id, 
-            name: self.name, 
-            description: self.description, 
-            status: self.status, 
-            due: self.due, 
-            created_at: self.created_at, 
-            updated_at: self.updated_at 
-        }
+impl From<TaskScored> for Task {
+    fn from(scored: TaskScored) -> Task {
+        scored.task
     }
 }
 
@@ -96,13 +82,7 @@ impl TaskScored {
         let dist_desc = get_min_distance_from_words(&task.description, term);
         let distance = cmp::min(dist_name, dist_desc);
         Self {
-            id: task.id,
-            name: task.name,
-            description: task.description,
-            status: task.status,
-            due: task.due,
-            created_at: task.created_at,
-            updated_at: task.updated_at,
+            task,
             distance,
         }
     }
@@ -125,6 +105,7 @@ pub fn sort_by_score(tasks: Vec<Task>, term: &str) -> Vec<Task> {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod test {
     use super::*;
 
@@ -0,0 +1,243 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc, format::{Item, StrftimeItems}};
+use chrono_tz::Tz;
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+use super::date::{localize, parse_due, start_of_day, ParsedDue};
+
+//a task field as a CSV column, named like the JSON field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    Name,
+    Description,
+    Status,
+    Due,
+    CreatedAt,
+    UpdatedAt,
+    Tags,
+    Project,
+    Priority,
+    Recurrence,
+    Scheduled,
+    Deadline,
+    AllDay,
+    Version,
+}
+
+pub const ALL_COLUMNS: [Column; 15] = [
+    Column::Id, Column::Name, Column::Description, Column::Status, Column::Due, Column::CreatedAt,
+    Column::UpdatedAt, Column::Tags, Column::Project, Column::Priority, Column::Recurrence,
+    Column::Scheduled, Column::Deadline, Column::AllDay, Column::Version,
+];
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Column::Id          => "id",
+            Column::Name        => "name",
+            Column::Description => "description",
+            Column::Status      => "status",
+            Column::Due         => "due",
+            Column::CreatedAt   => "created_at",
+            Column::UpdatedAt   => "updated_at",
+            Column::Tags        => "tags",
+            Column::Project     => "project",
+            Column::Priority    => "priority",
+            Column::Recurrence  => "recurrence",
+            Column::Scheduled   => "scheduled",
+            Column::Deadline    => "deadline",
+            Column::AllDay      => "all_day",
+            Column::Version     => "version",
+        }
+    }
+
+    //case and spaces do not matter, "Created At" is created_at
+    pub fn from_str(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace([' ', '-'], "_");
+        ALL_COLUMNS.into_iter().find(|c| c.name() == name)
+    }
+}
+
+//a comma separated list like "name,due,status", the error names the unknown column
+pub fn parse_columns(list: &str) -> Result<Vec<Column>, String> {
+    list.split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(|c| Column::from_str(c).ok_or_else(|| format!("Unknown column {}", c.trim())))
+        .collect()
+}
+
+//the column each header is read into. `mapping` is a list like "Title:name,Due Date:due",
+//headers not in it are matched by name and None when they match no field
+pub fn map_headers(headers: &[String], mapping: &str) -> Result<Vec<Option<Column>>, String> {
+    let mut pairs = Vec::<(String, Column)>::new();
+    for pair in mapping.split(',').filter(|p| !p.trim().is_empty()) {
+        let (header, field) = pair.rsplit_once(':').ok_or_else(|| format!("Mapping {} is not header:field", pair.trim()))?;
+        let column = Column::from_str(field).ok_or_else(|| format!("Unknown column {}", field.trim()))?;
+        pairs.push((header.trim().to_lowercase(), column));
+    }
+    Ok(headers
+        .iter()
+        .map(|header| {
+            let key = header.trim().to_lowercase();
+            pairs.iter().find(|(h, _)| *h == key).map(|(_, c)| *c).or_else(|| Column::from_str(header))
+        })
+        .collect())
+}
+
+//tries each strftime format in turn, date only formats give all day dates in `tz`.
+//anything the due field takes is accepted as well
+pub fn parse_date(text: &str, formats: &[String], tz: &Tz, now: DateTime<Utc>) -> Option<ParsedDue> {
+    let text = text.trim();
+    for format in formats {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(ParsedDue { due: localize(naive, tz), all_day: false })
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+            return Some(ParsedDue { due: start_of_day(date, tz), all_day: true })
+        }
+    }
+    parse_due(text, tz, now)
+}
+
+//chrono panics when writing a date with a format it cannot read, so check it first
+pub fn valid_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+//the column's value for `task`. dates are written with `format` in `tz`, or as RFC 3339
+pub fn cell(task: &Task, column: Column, format: Option<&str>, tz: &Tz) -> String {
+    let date = |at: Option<DateTime<Utc>>| match (at, format) {
+        (Some(at), Some(format)) => at.with_timezone(tz).format(format).to_string(),
+        (Some(at), None) => at.with_timezone(tz).to_rfc3339(),
+        (None, _) => String::new()
+    };
+    match column {
+        Column::Id          => task.id.clone(),
+        Column::Name        => task.name.clone(),
+        Column::Description => task.description.clone(),
+        Column::Status      => status_name(task.status),
+        Column::Due         => date(task.due),
+        Column::CreatedAt   => date(Some(task.created_at)),
+        Column::UpdatedAt   => date(Some(task.updated_at)),
+        Column::Tags        => task.tags.join(", "),
+        Column::Project     => task.project.clone().unwrap_or_default(),
+        Column::Priority    => priority_name(task.priority),
+        Column::Recurrence  => task.recurrence.clone().unwrap_or_default(),
+        Column::Scheduled   => date(task.scheduled),
+        Column::Deadline    => date(task.deadline),
+        Column::AllDay      => task.all_day.to_string(),
+        Column::Version     => task.version.to_string(),
+    }
+}
+
+//a header row with the column names and a row per task
+pub fn write(tasks: &[Task], columns: &[Column], format: Option<&str>, tz: &Tz) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let _ = writer.write_record(columns.iter().map(|c| c.name()));
+    for task in tasks {
+        let _ = writer.write_record(columns.iter().map(|c| cell(task, *c, format, tz)));
+    }
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
+
+//writes one cell into `task`, an empty cell clears optional fields. ids, timestamps
+//and versions are read by the import itself and left alone here
+pub fn set_cell(task: &mut Task, column: Column, value: &str, formats: &[String], tz: &Tz, now: DateTime<Utc>) -> Result<(), String> {
+    let value = value.trim();
+    let optional = |value: &str| Some(value.to_string()).filter(|v| !v.is_empty());
+    let date = |value: &str| match value.is_empty() {
+        true => Ok(None),
+        false => parse_date(value, formats, tz, now).map(Some).ok_or_else(|| format!("Could not parse date {value}"))
+    };
+    match column {
+        Column::Name if value.is_empty() => return Err("name is missing".to_string()),
+        Column::Name => task.name = value.to_string(),
+        Column::Description => task.description = value.to_string(),
+        Column::Status if value.is_empty() => (),
+        Column::Status => {
+            task.status = TaskStatus::from_str(value).ok_or_else(|| format!("Unknown status {value}"))?.to_store();
+        },
+        Column::Due => {
+            let due = date(value)?;
+            task.due = due.map(|d| d.due);
+            task.all_day = due.is_some_and(|d| d.all_day);
+        },
+        Column::Tags => {
+            task.tags = value.split(',').map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+        },
+        Column::Project => task.project = optional(value),
+        Column::Priority => {
+            task.priority = match value.is_empty() {
+                true => TaskPriority::None,
+                false => TaskPriority::from_str(value).ok_or_else(|| format!("Unknown priority {value}"))?
+            }.to_store();
+        },
+        Column::Recurrence => task.recurrence = optional(value),
+        Column::Scheduled => task.scheduled = date(value)?.map(|d| d.due),
+        Column::Deadline => task.deadline = date(value)?.map(|d| d.due),
+        Column::AllDay => {
+            task.all_day = match value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => true,
+                "false" | "no" | "0" | "" => false,
+                _ => return Err(format!("all_day {value} is not true or false"))
+            };
+        },
+        Column::Id | Column::CreatedAt | Column::UpdatedAt | Column::Version => ()
+    }
+    Ok(())
+}
+
+fn status_name(status: i32) -> String {
+    TaskStatus::from_store(status).unwrap_or(TaskStatus::Created).to_string()
+}
+
+fn priority_name(priority: i32) -> String {
+    TaskPriority::from_store(priority).unwrap_or(TaskPriority::None).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_map_headers() {
+        let headers = ["Title", "Due Date", "Status", "Owner"].map(String::from);
+        let columns = map_headers(&headers, "Title:name, due date:due").unwrap();
+        assert_eq!(columns, vec![Some(Column::Name), Some(Column::Due), Some(Column::Status), None]);
+        assert!(map_headers(&headers, "Title:heading").is_err());
+        assert_eq!(parse_columns("name, due,all_day").unwrap(), vec![Column::Name, Column::Due, Column::AllDay]);
+        assert!(parse_columns("name,owner").is_err());
+    }
+
+    #[test]
+    fn test_cells() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let formats = vec!["%d.%m.%Y %H:%M".to_string(), "%d.%m.%Y".to_string()];
+        let tz = Tz::Europe__Berlin;
+        let mut task = Task::new("Pay rent", None, None);
+        set_cell(&mut task, Column::Due, "10.05.2023", &formats, &tz, now).unwrap();
+        assert_eq!(task.due, Some(Utc.with_ymd_and_hms(2023, 5, 9, 22, 0, 0).unwrap()));
+        assert!(task.all_day);
+        set_cell(&mut task, Column::Deadline, "12.05.2023 18:30", &formats, &tz, now).unwrap();
+        assert_eq!(task.deadline, Some(Utc.with_ymd_and_hms(2023, 5, 12, 16, 30, 0).unwrap()));
+        set_cell(&mut task, Column::Status, "Done", &formats, &tz, now).unwrap();
+        assert_eq!(task.status, TaskStatus::Done.to_store());
+        set_cell(&mut task, Column::Tags, "Home, finance", &formats, &tz, now).unwrap();
+        assert_eq!(task.tags, vec!["home".to_string(), "finance".to_string()]);
+        assert!(set_cell(&mut task, Column::Status, "doing", &formats, &tz, now).is_err());
+        assert!(set_cell(&mut task, Column::Due, "someday", &formats, &tz, now).is_err());
+        assert!(set_cell(&mut task, Column::Name, " ", &formats, &tz, now).is_err());
+
+        assert_eq!(cell(&task, Column::Status, None, &tz), "done");
+        assert_eq!(cell(&task, Column::Tags, None, &tz), "home, finance");
+        assert_eq!(cell(&task, Column::Due, Some("%d.%m.%Y"), &tz), "10.05.2023");
+        assert_eq!(cell(&task, Column::Due, None, &tz), "2023-05-10T00:00:00+02:00");
+        set_cell(&mut task, Column::Due, "", &formats, &tz, now).unwrap();
+        assert_eq!(cell(&task, Column::Due, None, &tz), "");
+
+        task.name = "Pay rent, \"soon\"".to_string();
+        let text = write(&[task], &[Column::Name, Column::Tags], None, &tz);
+        assert_eq!(text, "name,tags\n\"Pay rent, \"\"soon\"\"\",\"home, finance\"\n");
+    }
+}
@@ -0,0 +1,251 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+//attributes read into task fields. `id` (the working set number) and `urgency` are
+//Taskwarrior's own and dropped, everything else is kept in `extensions`
+const ATTRIBUTES: [&str; 16] = [
+    "uuid", "description", "status", "due", "entry", "modified", "end", "scheduled", "wait",
+    "tags", "project", "priority", "depends", "annotations", "id", "urgency"
+];
+
+//what `task export` writes: a JSON array, or an object per line from older versions
+pub fn parse_document(text: &str) -> Result<Vec<Map<String, Value>>, String> {
+    let text = text.trim();
+    let values = match text.starts_with('[') {
+        true => serde_json::from_str::<Vec<Value>>(text).map_err(|e| e.to_string())?,
+        false => text
+            .lines()
+            .map(|l| l.trim().trim_end_matches(','))
+            .filter(|l| !l.is_empty())
+            .map(serde_json::from_str::<Value>)
+            .collect::<Result<Vec<Value>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    values
+        .into_iter()
+        .map(|v| match v {
+            Value::Object(object) => Ok(object),
+            other => Err(format!("Expected a task object, got {other}"))
+        })
+        .collect()
+}
+
+fn date(object: &Map<String, Value>, key: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = object.get(key) else { return Ok(None) };
+    let text = value.as_str().unwrap_or_default();
+    NaiveDateTime::parse_from_str(text, DATE_FORMAT)
+        .map(|d| Utc.from_utc_datetime(&d))
+        .or_else(|_| DateTime::parse_from_rfc3339(text).map(|d| d.with_timezone(&Utc)))
+        .map(Some)
+        .map_err(|_| format!("Could not parse {key} {value}"))
+}
+
+fn format_date(at: DateTime<Utc>) -> String {
+    at.format(DATE_FORMAT).to_string()
+}
+
+//a list as an array of strings, older versions write "a,b"
+fn strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(items)) => items.iter().filter_map(|i| i.as_str()).map(String::from).collect(),
+        Some(Value::String(text)) => text.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+        _ => Vec::new()
+    }
+}
+
+//a Taskwarrior task. the description is the name and annotations become the lines of the
+//description, depends and the annotations with their dates are kept in `extensions`.
+//a waiting task is snoozed until its wait date
+pub fn from_json(object: &Map<String, Value>, now: DateTime<Utc>) -> Result<Task, String> {
+    let name = object.get("description").and_then(|d| d.as_str()).map(str::trim).unwrap_or_default();
+    if name.is_empty() {
+        return Err("Task description is missing".to_string())
+    }
+    let mut task = Task::new(name, None, date(object, "due")?);
+    if let Some(uuid) = object.get("uuid") {
+        task.id = uuid
+            .as_str()
+            .and_then(|u| Uuid::parse_str(u).ok())
+            .ok_or_else(|| format!("Invalid uuid {uuid}"))?
+            .hyphenated()
+            .to_string();
+    }
+    task.status = match object.get("status").and_then(|s| s.as_str()).unwrap_or("pending") {
+        "pending" | "waiting" | "recurring" => TaskStatus::Created,
+        "completed" => TaskStatus::Done,
+        "deleted" => TaskStatus::Deleted,
+        other => return Err(format!("Unknown status {other}"))
+    }.to_store();
+    task.priority = match object.get("priority").and_then(|p| p.as_str()) {
+        None | Some("") => TaskPriority::None,
+        Some("H") => TaskPriority::High,
+        Some("M") => TaskPriority::Medium,
+        Some("L") => TaskPriority::Low,
+        Some(other) => return Err(format!("Unknown priority {other}"))
+    }.to_store();
+    task.project = object.get("project").and_then(|p| p.as_str()).filter(|p| !p.is_empty()).map(String::from);
+    for tag in strings(object.get("tags")).iter().map(|t| t.to_lowercase()) {
+        if !task.tags.contains(&tag) {
+            task.tags.push(tag);
+        }
+    }
+    task.scheduled = date(object, "scheduled")?;
+    task.snoozed_until = date(object, "wait")?.filter(|w| *w > now);
+    task.created_at = date(object, "entry")?.unwrap_or(now);
+    task.updated_at = match date(object, "modified")? {
+        Some(modified) => modified,
+        None => date(object, "end")?.unwrap_or(task.created_at)
+    };
+
+    let mut extensions = object
+        .iter()
+        .filter(|(k, _)| !ATTRIBUTES.contains(&k.as_str()))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<Map<String, Value>>();
+    let depends = strings(object.get("depends"));
+    if !depends.is_empty() {
+        extensions.insert("depends".to_string(), json!(depends));
+    }
+    if let Some(Value::Array(annotations)) = object.get("annotations") {
+        task.description = annotations
+            .iter()
+            .filter_map(|a| a.get("description").and_then(|d| d.as_str()))
+            .collect::<Vec<&str>>()
+            .join("\n");
+        extensions.insert("annotations".to_string(), Value::Array(annotations.clone()));
+    }
+    task.extensions = Value::Object(extensions);
+    Ok(task)
+}
+
+//the task as `task import` reads it. each description line is an annotation, dated like
+//the imported annotation with the same text or else by the last change
+pub fn to_json(task: &Task) -> Value {
+    let mut object = Map::new();
+    let extensions = task.extensions.as_object().cloned().unwrap_or_default();
+    for (key, value) in extensions.iter().filter(|(k, _)| !ATTRIBUTES.contains(&k.as_str())) {
+        object.insert(key.clone(), value.clone());
+    }
+    object.insert("uuid".to_string(), json!(task.id));
+    object.insert("description".to_string(), json!(task.name));
+    let status = match TaskStatus::from_store(task.status) {
+        Some(TaskStatus::Done) => "completed",
+        Some(TaskStatus::Deleted) => "deleted",
+        _ => "pending"
+    };
+    object.insert("status".to_string(), json!(status));
+    object.insert("entry".to_string(), json!(format_date(task.created_at)));
+    object.insert("modified".to_string(), json!(format_date(task.updated_at)));
+    if status != "pending" {
+        object.insert("end".to_string(), json!(format_date(task.updated_at)));
+    }
+    for (key, at) in [("due", task.due), ("scheduled", task.scheduled), ("wait", task.snoozed_until)] {
+        if let Some(at) = at {
+            object.insert(key.to_string(), json!(format_date(at)));
+        }
+    }
+    if !task.tags.is_empty() {
+        object.insert("tags".to_string(), json!(task.tags));
+    }
+    if let Some(project) = &task.project {
+        object.insert("project".to_string(), json!(project));
+    }
+    let priority = match TaskPriority::from_store(task.priority) {
+        Some(TaskPriority::High) => Some("H"),
+        Some(TaskPriority::Medium) => Some("M"),
+        Some(TaskPriority::Low) => Some("L"),
+        _ => None
+    };
+    if let Some(priority) = priority {
+        object.insert("priority".to_string(), json!(priority));
+    }
+    let depends = strings(extensions.get("depends"));
+    if !depends.is_empty() {
+        object.insert("depends".to_string(), json!(depends));
+    }
+    let dated = extensions.get("annotations").and_then(|a| a.as_array()).cloned().unwrap_or_default();
+    let annotations = task.description
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let entry = dated
+                .iter()
+                .find(|a| a.get("description").and_then(|d| d.as_str()) == Some(line))
+                .and_then(|a| a.get("entry").cloned())
+                .unwrap_or_else(|| json!(format_date(task.updated_at)));
+            json!({"entry": entry, "description": line})
+        })
+        .collect::<Vec<Value>>();
+    if !annotations.is_empty() {
+        object.insert("annotations".to_string(), Value::Array(annotations));
+    }
+    Value::Object(object)
+}
+
+//an array with a task per line, the way `task export` writes it
+pub fn document(tasks: &[Task]) -> String {
+    let lines = tasks.iter().map(|t| to_json(t).to_string()).collect::<Vec<String>>();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    const EXPORTED: &str = r#"[
+{"id":0,"description":"Pay rent","end":"20230502T080000Z","entry":"20230420T090000Z","modified":"20230502T080000Z","project":"home","priority":"H","status":"completed","tags":["Finance","finance"],"uuid":"5D1E6B38-3C1C-4BF6-9A4E-6B1F9E4A1C01","annotations":[{"entry":"20230421T100000Z","description":"transfer"}],"urgency":0,"estimate":"2h"},
+{"id":1,"description":"Call plumber","due":"20230510T070000Z","entry":"20230420T090000Z","modified":"20230420T090000Z","status":"pending","depends":"5d1e6b38-3c1c-4bf6-9a4e-6b1f9e4a1c01","uuid":"0b4d5d3c-9f0a-4c55-8a43-1d7f1d5b2e02","urgency":8.2}
+]"#;
+
+    #[test]
+    fn test_from_json() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let objects = parse_document(EXPORTED).unwrap();
+        let rent = from_json(&objects[0], now).unwrap();
+        assert_eq!(rent.id, "5d1e6b38-3c1c-4bf6-9a4e-6b1f9e4a1c01");
+        assert_eq!(rent.status, TaskStatus::Done.to_store());
+        assert_eq!(rent.priority, TaskPriority::High.to_store());
+        assert_eq!(rent.tags, vec!["finance"]);
+        assert_eq!(rent.description, "transfer");
+        assert_eq!(rent.created_at, Utc.with_ymd_and_hms(2023, 4, 20, 9, 0, 0).unwrap());
+        assert_eq!(rent.updated_at, Utc.with_ymd_and_hms(2023, 5, 2, 8, 0, 0).unwrap());
+        assert_eq!(rent.extensions["estimate"], "2h");
+        assert!(rent.extensions.get("urgency").is_none());
+        let plumber = from_json(&objects[1], now).unwrap();
+        assert_eq!(plumber.due, Some(Utc.with_ymd_and_hms(2023, 5, 10, 7, 0, 0).unwrap()));
+        assert_eq!(plumber.extensions["depends"], json!([rent.id]));
+
+        let lines = "{\"description\":\"a\",\"wait\":\"20230601T000000Z\"},\n{\"description\":\"b\"}\n";
+        let objects = parse_document(lines).unwrap();
+        assert_eq!(from_json(&objects[0], now).unwrap().snoozed_until, Some(Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap()));
+        assert!(from_json(&parse_document(r#"[{"description":"x","status":"doing"}]"#).unwrap()[0], now).is_err());
+        assert!(from_json(&parse_document(r#"[{"description":"x","uuid":"7"}]"#).unwrap()[0], now).is_err());
+        assert!(parse_document("[1]").is_err());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let objects = parse_document(EXPORTED).unwrap();
+        let mut rent = from_json(&objects[0], now).unwrap();
+        let value = to_json(&rent);
+        assert_eq!(value["uuid"], rent.id);
+        assert_eq!(value["status"], "completed");
+        assert_eq!(value["end"], "20230502T080000Z");
+        assert_eq!(value["priority"], "H");
+        assert_eq!(value["estimate"], "2h");
+        assert_eq!(value["annotations"], json!([{"entry": "20230421T100000Z", "description": "transfer"}]));
+        assert_eq!(from_json(value.as_object().unwrap(), now).unwrap(), rent);
+
+        rent.description = "transfer\nreceipt filed".to_string();
+        let value = to_json(&rent);
+        assert_eq!(value["annotations"][1], json!({"entry": "20230502T080000Z", "description": "receipt filed"}));
+        assert!(document(&[rent]).starts_with("[\n{"));
+    }
+}
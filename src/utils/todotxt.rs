@@ -0,0 +1,209 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde_json::{Map, Value};
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+use super::date::start_of_day;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+//one todo.txt line (http://todotxt.org) as a task, the error when the line has no text.
+//(A) is high, (B) medium and (C) to (Z) low priority. the first +project becomes the
+//project and further ones stay in the name, @contexts become tags, due: and t: (threshold)
+//dates set due and scheduled and pri: keeps the priority of completed tasks. every
+//other key:value is kept in `extensions`
+pub fn parse_line(line: &str, tz: &Tz, now: DateTime<Utc>) -> Result<Task, String> {
+    let mut words = line.split_whitespace().peekable();
+    let mut task = Task::new("", None, None);
+    task.created_at = now;
+    task.updated_at = now;
+    let date = |word: Option<&&str>| word.and_then(|w| NaiveDate::parse_from_str(w, DATE_FORMAT).ok());
+
+    let mut priority = None;
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.status = TaskStatus::Done.to_store();
+        //a single date after x is the completion date, two are completion and creation
+        if let Some(completed) = date(words.peek()) {
+            words.next();
+            task.updated_at = start_of_day(completed, tz);
+        }
+    } else if let Some(letter) = words.peek().and_then(|w| priority_letter(w)) {
+        words.next();
+        priority = Some(letter);
+    }
+    if let Some(created) = date(words.peek()) {
+        words.next();
+        task.created_at = start_of_day(created, tz);
+    }
+
+    let mut name = Vec::<&str>::new();
+    let mut extensions = Map::new();
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            match task.project {
+                None => task.project = Some(project.to_string()),
+                Some(_) => name.push(word)
+            }
+            continue
+        }
+        if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            let tag = context.to_lowercase();
+            if !task.tags.contains(&tag) {
+                task.tags.push(tag);
+            }
+            continue
+        }
+        let Some((key, value)) = extension(word) else {
+            name.push(word);
+            continue
+        };
+        match (key, date(Some(&value))) {
+            ("due", Some(due)) => {
+                task.due = Some(start_of_day(due, tz));
+                task.all_day = true;
+            },
+            ("t", Some(scheduled)) => task.scheduled = Some(start_of_day(scheduled, tz)),
+            ("pri", _) if priority_letter(&format!("({value})")).is_some() => {
+                priority = priority_letter(&format!("({value})"));
+            },
+            _ => {
+                extensions.insert(key.to_string(), Value::String(value.to_string()));
+            }
+        }
+    }
+    task.name = name.join(" ");
+    if task.name.is_empty() {
+        return Err("Task text is missing".to_string())
+    }
+    task.priority = match priority {
+        Some('A') => TaskPriority::High.to_store(),
+        Some('B') => TaskPriority::Medium.to_store(),
+        Some(_) => TaskPriority::Low.to_store(),
+        None => TaskPriority::None.to_store()
+    };
+    task.extensions = Value::Object(extensions);
+    Ok(task)
+}
+
+//the task as a todo.txt line. completed tasks keep their priority as pri:, due and
+//scheduled are written as dates in `tz` and descriptions are left out
+pub fn line(task: &Task, tz: &Tz) -> String {
+    let date = |at: DateTime<Utc>| at.with_timezone(tz).date_naive().format(DATE_FORMAT).to_string();
+    let letter = match task.priority {
+        p if p == TaskPriority::High.to_store() => Some('A'),
+        p if p == TaskPriority::Medium.to_store() => Some('B'),
+        p if p == TaskPriority::Low.to_store() => Some('C'),
+        _ => None
+    };
+    let done = task.status == TaskStatus::Done.to_store();
+    let mut words = Vec::<String>::new();
+    match (done, letter) {
+        (true, _) => words.push(format!("x {}", date(task.updated_at))),
+        (false, Some(letter)) => words.push(format!("({letter})")),
+        (false, None) => ()
+    }
+    words.push(date(task.created_at));
+    words.push(task.name.split_whitespace().collect::<Vec<_>>().join(" "));
+    if let Some(project) = &task.project {
+        words.push(format!("+{}", project.split_whitespace().collect::<Vec<_>>().join("_")));
+    }
+    words.extend(task.tags.iter().map(|tag| format!("@{}", tag.split_whitespace().collect::<Vec<_>>().join("_"))));
+    if let Some(due) = task.due {
+        words.push(format!("due:{}", date(due)));
+    }
+    if let Some(scheduled) = task.scheduled {
+        words.push(format!("t:{}", date(scheduled)));
+    }
+    if let (true, Some(letter)) = (done, letter) {
+        words.push(format!("pri:{letter}"));
+    }
+    if let Some(extensions) = task.extensions.as_object() {
+        for (key, value) in extensions {
+            if let Some(value) = value.as_str() {
+                words.push(format!("{key}:{value}"));
+            }
+        }
+    }
+    words.join(" ")
+}
+
+//a todo.txt file with a line per task, deleted tasks are left out
+pub fn document(tasks: &[Task], tz: &Tz) -> String {
+    tasks
+        .iter()
+        .filter(|t| t.status != TaskStatus::Deleted.to_store())
+        .map(|t| line(t, tz) + "\n")
+        .collect()
+}
+
+fn priority_letter(word: &str) -> Option<char> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c @ 'A'..='Z'), None) => Some(c),
+        _ => None
+    }
+}
+
+//key:value with a key starting with a letter and a value. urls like https://example.com
+//and times like 10:30 are left alone
+fn extension(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    let letter = key.starts_with(|c: char| c.is_alphabetic());
+    match !letter || value.is_empty() || value.starts_with("//") || value.contains(':') {
+        true => None,
+        false => Some((key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_line() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let task = parse_line("(A) 2023-04-20 Call Mom +Family +Phone @home due:2023-05-10 t:2023-05-08 id:7", &Tz::UTC, now).unwrap();
+        assert_eq!(task.name, "Call Mom +Phone");
+        assert_eq!(task.priority, TaskPriority::High.to_store());
+        assert_eq!(task.created_at, Utc.with_ymd_and_hms(2023, 4, 20, 0, 0, 0).unwrap());
+        assert_eq!(task.project, Some("Family".to_string()));
+        assert_eq!(task.tags, vec!["home".to_string()]);
+        assert_eq!(task.due, Some(Utc.with_ymd_and_hms(2023, 5, 10, 0, 0, 0).unwrap()));
+        assert!(task.all_day);
+        assert_eq!(task.scheduled, Some(Utc.with_ymd_and_hms(2023, 5, 8, 0, 0, 0).unwrap()));
+        assert_eq!(task.extensions, json!({"id": "7"}));
+
+        let task = parse_line("x 2023-05-02 2023-04-20 Pay rent pri:B see https://example.com at 10:30", &Tz::UTC, now).unwrap();
+        assert_eq!(task.status, TaskStatus::Done.to_store());
+        assert_eq!(task.updated_at, Utc.with_ymd_and_hms(2023, 5, 2, 0, 0, 0).unwrap());
+        assert_eq!(task.created_at, Utc.with_ymd_and_hms(2023, 4, 20, 0, 0, 0).unwrap());
+        assert_eq!(task.priority, TaskPriority::Medium.to_store());
+        assert_eq!(task.name, "Pay rent see https://example.com at 10:30");
+
+        let task = parse_line("(d) xylophone lessons", &Tz::UTC, now).unwrap();
+        assert_eq!(task.name, "(d) xylophone lessons");
+        assert_eq!(task.created_at, now);
+        assert!(parse_line("(B) 2023-04-20 @home", &Tz::UTC, now).is_err());
+    }
+
+    #[test]
+    fn test_line() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let text = "(A) 2023-04-20 Call Mom +Family @home due:2023-05-10 id:7";
+        assert_eq!(line(&parse_line(text, &Tz::UTC, now).unwrap(), &Tz::UTC), text);
+        let text = "x 2023-05-02 2023-04-20 Pay rent +Home pri:B";
+        assert_eq!(line(&parse_line(text, &Tz::UTC, now).unwrap(), &Tz::UTC), text);
+
+        let mut task = Task::new("Water   plants", Some("not exported"), None);
+        task.created_at = Utc.with_ymd_and_hms(2023, 4, 30, 23, 30, 0).unwrap();
+        task.project = Some("Back yard".to_string());
+        task.priority = TaskPriority::Low.to_store();
+        assert_eq!(line(&task, &Tz::Europe__Berlin), "(C) 2023-05-01 Water plants +Back_yard");
+        task.status = TaskStatus::Deleted.to_store();
+        assert_eq!(document(&[task], &Tz::UTC), "");
+    }
+}
@@ -0,0 +1,374 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, atomic::{self, AtomicBool}};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use ::notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use uuid::Uuid;
+
+use crate::db::store::TaskStore;
+use crate::db::models::{DuplicateMode, Task, TaskDetails, TaskError, TaskPriority, TaskStatus};
+use crate::services::task::TaskUpdate;
+use crate::utils::{sort::sort_by_score, parse::parse_search_value, date::{today, day_range}};
+
+pub mod note;
+
+const DIR_ENV: &str = "VAULT_DIR";
+
+//a task store that keeps every task as a Markdown file in one directory, e.g. to have
+//tasks under git, and needs no database. the files are the tasks: the watcher marks
+//what was read as stale when anything in the directory changes and the next call
+//reads the files again
+pub struct VaultStore {
+    dir: PathBuf,
+    tasks: Mutex<HashMap<String, Note>>,
+    stale: Arc<AtomicBool>,
+    _watcher: Mutex<RecommendedWatcher>
+}
+
+//a task and the file it was read from, files may be renamed or moved into subdirectories
+struct Note {
+    task: Task,
+    path: PathBuf
+}
+
+//VAULT_DIR keeps the tasks in that directory instead of Postgres
+pub fn from_env() -> Option<PathBuf> {
+    env::var(DIR_ENV).ok().filter(|d| !d.trim().is_empty()).map(PathBuf::from)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()).is_none_or(|n| n.starts_with('.'))
+}
+
+fn is_note(path: &Path) -> bool {
+    !is_hidden(path) && path.extension().is_some_and(|e| e == "md")
+}
+
+fn notes_in(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() && !is_hidden(&path) {
+            notes_in(&path, found)?;
+        } else if is_note(&path) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+//through a hidden temporary file, so editors never see half a file
+fn write_atomic(path: &Path, text: &str) -> io::Result<()> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("task.md");
+    let tmp = path.with_file_name(format!(".{name}.tmp"));
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path)
+}
+
+//what a file can change, versions and timestamps are the store's business
+fn same_content(a: &Task, b: &Task) -> bool {
+    Task { version: b.version, updated_at: b.updated_at, created_at: b.created_at, ..a.clone() } == *b
+}
+
+fn is_open(task: &Task) -> bool {
+    task.status == TaskStatus::Created.to_store() || task.status == TaskStatus::Overdue.to_store()
+}
+
+//nulls last like Postgres sorts them ascending
+fn nulls_last<T: Ord>(a: &Option<T>, b: &Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal
+    }
+}
+
+//the order of Task::list and the filters: due, status, last changed first
+fn listed(mut tasks: Vec<Task>) -> Vec<Task> {
+    tasks.sort_by(|a, b| nulls_last(&a.due, &b.due)
+        .then(a.status.cmp(&b.status))
+        .then(b.updated_at.cmp(&a.updated_at)));
+    tasks
+}
+
+impl VaultStore {
+    //reads the directory, creating it if needed, and starts watching it
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let stale = Arc::new(AtomicBool::new(true));
+        let flag = stale.clone();
+        let mut watcher = ::notify::recommended_watcher(move |event: ::notify::Result<::notify::Event>| {
+            //the store's own temporary files are hidden
+            if event.is_ok_and(|e| e.paths.iter().any(|p| !is_hidden(p))) {
+                flag.store(true, atomic::Ordering::SeqCst);
+            }
+        }).map_err(io::Error::other)?;
+        watcher.watch(dir, RecursiveMode::Recursive).map_err(io::Error::other)?;
+        let store = Self { dir: dir.to_path_buf(), tasks: Mutex::new(HashMap::new()), stale, _watcher: Mutex::new(watcher) };
+        store.reload()?;
+        Ok(store)
+    }
+
+    //reads every file now, without waiting for the watcher
+    pub fn reload(&self) -> io::Result<()> {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        self.stale.store(false, atomic::Ordering::SeqCst);
+        self.read_all(&mut tasks)
+    }
+
+    //files without an id get one written in. a file edited outside gets the next
+    //version, so a client holding the old one gets 412 like after any other write.
+    //removed files take their task with them
+    fn read_all(&self, tasks: &mut HashMap<String, Note>) -> io::Result<()> {
+        let mut found = Vec::new();
+        notes_in(&self.dir, &mut found)?;
+        found.sort();
+        let now = Utc::now();
+        let mut read = HashMap::<String, Note>::new();
+        for path in found {
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(err) => {
+                    log::warn!("vault skipped {}: {err}", path.display());
+                    continue
+                }
+            };
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let mut task = match note::parse(&text, stem, now) {
+                Ok(task) => task,
+                Err(err) => {
+                    log::warn!("vault skipped {}: {err}", path.display());
+                    continue
+                }
+            };
+            let mut rewrite = task.id.is_empty();
+            if rewrite {
+                task.id = Uuid::new_v4().hyphenated().to_string();
+            }
+            if let Some(other) = read.get(&task.id) {
+                log::warn!("vault skipped {}: task {} is already in {}", path.display(), task.id, other.path.display());
+                continue
+            }
+            if let Some(known) = tasks.get(&task.id).filter(|n| !same_content(&task, &n.task) && task.version <= n.task.version) {
+                task.version = known.task.version + 1;
+                task.updated_at = now;
+                rewrite = true;
+            }
+            if rewrite {
+                if let Err(err) = write_atomic(&path, &note::render(&task)) {
+                    log::warn!("vault could not write {}: {err}", path.display());
+                }
+            }
+            read.insert(task.id.clone(), Note { task, path });
+        }
+        *tasks = read;
+        Ok(())
+    }
+
+    //the tasks as the files have them now. like on Postgres overdue and woken snoozes
+    //follow from the dates and keep the version
+    fn tasks(&self) -> MutexGuard<'_, HashMap<String, Note>> {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if self.stale.swap(false, atomic::Ordering::SeqCst) {
+            if let Err(err) = self.read_all(&mut tasks) {
+                log::warn!("vault could not read {}: {err}", self.dir.display());
+            }
+        }
+        let now = Utc::now();
+        for note in tasks.values_mut() {
+            let mut task = note.task.clone();
+            if task.status == TaskStatus::Created.to_store() && task.is_overdue(now) {
+                task.status = TaskStatus::Overdue.to_store();
+            }
+            if task.snoozed_until.is_some_and(|until| until <= now) {
+                task.snoozed_until = None;
+            }
+            if task != note.task {
+                if let Err(err) = write_atomic(&note.path, &note::render(&task)) {
+                    log::warn!("vault could not write {}: {err}", note.path.display());
+                }
+                note.task = task;
+            }
+        }
+        tasks
+    }
+
+    fn write(&self, tasks: &mut HashMap<String, Note>, task: Task) -> Result<Task, TaskError> {
+        let path = tasks.get(&task.id).map_or_else(|| self.dir.join(format!("{}.md", task.id)), |n| n.path.clone());
+        if let Err(err) = write_atomic(&path, &note::render(&task)) {
+            log::error!("vault could not write {}: {err}", path.display());
+            return Err(TaskError::Database)
+        }
+        tasks.insert(task.id.clone(), Note { task: task.clone(), path });
+        Ok(task)
+    }
+
+    //the current copy for a change based on version `expected`, None skips the check
+    fn current(tasks: &HashMap<String, Note>, id: &str, expected: Option<i32>) -> Result<Task, TaskError> {
+        let current = tasks.get(id).map(|n| n.task.clone()).ok_or(TaskError::NotFound)?;
+        if expected.is_some_and(|v| v != current.version) {
+            return Err(TaskError::Conflict(Box::new(current)))
+        }
+        Ok(current)
+    }
+
+    //day windows are computed in the client's zone, like Task::filter
+    fn date_filter(tasks: Vec<Task>, column: &str, values: &[String], tz: &Tz) -> Vec<Task> {
+        let current = today(tz);
+        let windows = values
+            .iter()
+            .filter_map(|value| match value.as_str() {
+                "today"    => day_range(current, 1, tz),
+                "tomorrow" => current.succ_opt().and_then(|d| day_range(d, 1, tz)),
+                "week"     => day_range(current, 7, tz),
+                _          => None
+            })
+            .collect::<Vec<(DateTime<Utc>, DateTime<Utc>)>>();
+        let within = |date: Option<DateTime<Utc>>, (start, end): (DateTime<Utc>, DateTime<Utc>)| date.is_some_and(|d| d >= start && d < end);
+        tasks
+            .into_iter()
+            .filter(|t| windows.iter().any(|&window| match column {
+                "scheduled" => within(t.scheduled, window),
+                "deadline"  => within(t.deadline, window),
+                _           => within(t.due, window) && t.scheduled.is_none_or(|s| s < window.1)
+            }))
+            .collect()
+    }
+}
+
+impl TaskStore for VaultStore {
+    fn list(&self) -> Vec<Task> {
+        let tasks = self.tasks();
+        listed(tasks
+            .values()
+            .map(|n| n.task.clone())
+            .filter(|t| t.status != TaskStatus::Deleted.to_store() && t.snoozed_until.is_none())
+            .collect())
+    }
+
+    fn by_id(&self, id: &str) -> Option<Task> {
+        self.tasks().get(id).map(|n| n.task.clone())
+    }
+
+    //deleted tasks do not hold on to their name
+    fn by_name(&self, name: &str) -> Option<Task> {
+        self.tasks()
+            .values()
+            .map(|n| &n.task)
+            .filter(|t| t.name == name && t.status != TaskStatus::Deleted.to_store())
+            .min_by_key(|t| t.created_at)
+            .cloned()
+    }
+
+    fn next(&self) -> Option<Task> {
+        let now = Utc::now();
+        self.tasks()
+            .values()
+            .map(|n| &n.task)
+            .filter(|t| is_open(t) && t.snoozed_until.is_none() && t.scheduled.is_none_or(|s| s <= now))
+            .min_by(|a, b| a.status.cmp(&b.status)
+                .then(nulls_last(&a.deadline, &b.deadline))
+                .then(nulls_last(&a.due, &b.due))
+                .then(b.priority.cmp(&a.priority))
+                .then(a.created_at.cmp(&b.created_at)))
+            .cloned()
+    }
+
+    fn filter(&self, text: &str, tz: &Tz) -> Vec<Task> {
+        let all = self.tasks().values().map(|n| n.task.clone()).collect::<Vec<Task>>();
+        if !text.starts_with(':') {
+            let term = text.to_lowercase();
+            let found = all
+                .into_iter()
+                .filter(|t| t.name.to_lowercase().contains(&term) || t.description.to_lowercase().contains(&term))
+                .collect();
+            return sort_by_score(found, text)
+        }
+        let (column, values) = parse_search_value(text);
+        let kept = all.iter().filter(|t| t.status != TaskStatus::Deleted.to_store()).cloned().collect::<Vec<Task>>();
+        match column.as_str() {
+            "due" | "scheduled" | "deadline" => listed(Self::date_filter(kept, &column, &values, tz)),
+            "tag" => listed(kept.into_iter().filter(|t| t.tags.iter().any(|tag| values.contains(tag))).collect()),
+            "project" => listed(kept.into_iter().filter(|t| t.project.as_ref().is_some_and(|p| values.contains(&p.to_lowercase()))).collect()),
+            "priority" => {
+                let priorities = values.iter().filter_map(|v| TaskPriority::from_str(v)).map(|p| p.to_store()).collect::<Vec<i32>>();
+                listed(kept.into_iter().filter(|t| priorities.contains(&t.priority)).collect())
+            },
+            "snoozed" => {
+                let mut snoozed = kept.into_iter().filter(|t| t.snoozed_until.is_some()).collect::<Vec<Task>>();
+                snoozed.sort_by_key(|t| t.snoozed_until);
+                snoozed
+            },
+            //like on Postgres a status filter can ask for deleted tasks
+            _ => {
+                let statuses = values.iter().filter_map(|v| TaskStatus::from_str(v)).map(|s| s.to_store()).collect::<Vec<i32>>();
+                listed(all.into_iter().filter(|t| statuses.contains(&t.status)).collect())
+            }
+        }
+    }
+
+    fn find_or_create(&self, name: &str, description: Option<&str>, due: Option<DateTime<Utc>>, details: TaskDetails, on_duplicate: DuplicateMode) -> Result<(Task, bool), TaskError> {
+        let mut tasks = self.tasks();
+        if on_duplicate != DuplicateMode::Allow {
+            let existing = tasks
+                .values()
+                .map(|n| &n.task)
+                .filter(|t| t.name == name && t.status != TaskStatus::Deleted.to_store())
+                .min_by_key(|t| t.created_at);
+            if let Some(existing) = existing {
+                return match on_duplicate {
+                    DuplicateMode::Reject => Err(TaskError::Duplicate(Box::new(existing.clone()))),
+                    _ => Ok((existing.clone(), false))
+                }
+            }
+        }
+        let mut task = Task::new(name, description, due).with_details(details);
+        task.settle_status(Utc::now());
+        self.write(&mut tasks, task).map(|task| (task, true))
+    }
+
+    fn update(&self, update: TaskUpdate, tz: &Tz) -> Result<Task, TaskError> {
+        let mut tasks = self.tasks();
+        let current = Self::current(&tasks, &update.id, None)?;
+        let mut changed = Task::apply(&current, update, tz)?;
+        changed.settle_status(Utc::now());
+        changed.version = current.version + 1;
+        self.write(&mut tasks, changed)
+    }
+
+    fn set_status(&self, id: &str, status: i32, expected: Option<i32>) -> Result<Task, TaskError> {
+        let mut tasks = self.tasks();
+        let current = Self::current(&tasks, id, expected)?;
+        let changed = Task { status, updated_at: Utc::now(), version: current.version + 1, ..current };
+        self.write(&mut tasks, changed)
+    }
+
+    fn snooze(&self, id: &str, until: Option<DateTime<Utc>>, expected: Option<i32>) -> Result<Task, TaskError> {
+        let mut tasks = self.tasks();
+        let current = Self::current(&tasks, id, expected)?;
+        let changed = Task { snoozed_until: until, updated_at: Utc::now(), version: current.version + 1, ..current };
+        self.write(&mut tasks, changed)
+    }
+
+    fn delete(&self, id: &str) -> Result<usize, TaskError> {
+        let mut tasks = self.tasks();
+        let Some(note) = tasks.remove(id) else { return Ok(0) };
+        match fs::remove_file(&note.path) {
+            Ok(()) => Ok(1),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(1),
+            Err(err) => {
+                log::error!("vault could not remove {}: {err}", note.path.display());
+                tasks.insert(id.to_string(), note);
+                Err(TaskError::Database)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod vault_tests;
@@ -0,0 +1,180 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::{Task, TaskPriority, TaskStatus};
+
+const FENCE: &str = "---";
+
+//the YAML between the --- fences. the name is the first # heading of the body and
+//the description everything below it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FrontMatter {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default = "open")]
+    status: String,
+    #[serde(default, skip_serializing_if = "is_none")]
+    priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    all_day: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scheduled: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deadline: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recurrence: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snoozed_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    version: Option<i32>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    extensions: serde_json::Map<String, serde_json::Value>
+}
+
+fn open() -> String {
+    TaskStatus::Created.to_string()
+}
+
+fn is_none(priority: &Option<String>) -> bool {
+    priority.as_deref().is_none_or(|p| p == TaskPriority::None.to_string())
+}
+
+//the task as a Markdown file with YAML front matter
+pub fn render(task: &Task) -> String {
+    let front = FrontMatter {
+        id: Some(task.id.clone()),
+        status: status_name(task.status),
+        priority: Some(priority_name(task.priority)),
+        project: task.project.clone(),
+        tags: task.tags.clone(),
+        due: task.due,
+        all_day: task.all_day,
+        scheduled: task.scheduled,
+        deadline: task.deadline,
+        recurrence: task.recurrence.clone(),
+        snoozed_until: task.snoozed_until,
+        created_at: Some(task.created_at),
+        updated_at: Some(task.updated_at),
+        version: Some(task.version),
+        extensions: task.extensions.as_object().cloned().unwrap_or_default()
+    };
+    let yaml = serde_yaml::to_string(&front).unwrap_or_default();
+    let mut text = format!("{FENCE}\n{yaml}{FENCE}\n# {}\n", task.name);
+    if !task.description.trim().is_empty() {
+        text.push_str(&format!("\n{}\n", task.description.trim_end()));
+    }
+    text
+}
+
+//a file written by `render` or by hand. missing timestamps are `now`, a missing id is
+//left empty for the caller to fill in and the file name stands in for a missing heading
+pub fn parse(text: &str, fallback_name: &str, now: DateTime<Utc>) -> Result<Task, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text).replace("\r\n", "\n");
+    let (yaml, body) = match text.strip_prefix(&format!("{FENCE}\n")) {
+        Some(rest) => match rest.find(&format!("\n{FENCE}\n")).map(|end| (end, end + FENCE.len() + 2)) {
+            Some((end, body)) => (&rest[..end], &rest[body..]),
+            None => match rest.strip_suffix(&format!("\n{FENCE}")) {
+                Some(yaml) => (yaml, ""),
+                None => return Err("Front matter is not closed with ---".to_string())
+            }
+        },
+        None => ("", text.as_str())
+    };
+    let front = match yaml.trim().is_empty() {
+        true => serde_yaml::from_str::<FrontMatter>("{}"),
+        false => serde_yaml::from_str::<FrontMatter>(yaml)
+    }.map_err(|e| format!("Front matter: {e}"))?;
+
+    let body = body.trim_start_matches('\n');
+    let (name, description) = match body.strip_prefix("# ") {
+        Some(rest) => match rest.split_once('\n') {
+            Some((heading, rest)) => (heading.trim().to_string(), rest.trim().to_string()),
+            None => (rest.trim().to_string(), String::new())
+        },
+        None => (fallback_name.trim().to_string(), body.trim().to_string())
+    };
+    if name.is_empty() {
+        return Err("Task name is missing".to_string())
+    }
+    let mut task = Task::new(&name, Some(&description), front.due);
+    task.id = front.id.unwrap_or_default();
+    task.status = TaskStatus::from_str(&front.status).ok_or_else(|| format!("Unknown status {}", front.status))?.to_store();
+    task.priority = match front.priority.as_deref() {
+        Some(priority) => TaskPriority::from_str(priority).ok_or_else(|| format!("Unknown priority {priority}"))?,
+        None => TaskPriority::None
+    }.to_store();
+    task.project = front.project.filter(|p| !p.is_empty());
+    for tag in front.tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()) {
+        if !task.tags.contains(&tag) {
+            task.tags.push(tag);
+        }
+    }
+    task.all_day = front.all_day;
+    task.scheduled = front.scheduled;
+    task.deadline = front.deadline;
+    task.recurrence = front.recurrence.filter(|r| !r.is_empty());
+    task.snoozed_until = front.snoozed_until;
+    task.created_at = front.created_at.unwrap_or(now);
+    task.updated_at = front.updated_at.unwrap_or(now);
+    task.version = front.version.unwrap_or(1);
+    task.extensions = serde_json::Value::Object(front.extensions);
+    Ok(task)
+}
+
+fn status_name(status: i32) -> String {
+    TaskStatus::from_store(status).unwrap_or(TaskStatus::Created).to_string()
+}
+
+fn priority_name(priority: i32) -> String {
+    TaskPriority::from_store(priority).unwrap_or(TaskPriority::None).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_and_parse() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let mut task = Task::new("Pay rent", Some("transfer\nto landlord"), Some(Utc.with_ymd_and_hms(2023, 5, 10, 0, 0, 0).unwrap()));
+        task.id = "task-1".to_string();
+        task.all_day = true;
+        task.project = Some("home".to_string());
+        task.tags = vec!["finance".to_string()];
+        task.priority = TaskPriority::High.to_store();
+        task.created_at = now;
+        task.updated_at = now;
+        task.version = 3;
+        let text = render(&task);
+        assert!(text.starts_with("---\nid: task-1\nstatus: created\npriority: high\nproject: home\ntags:\n- finance\ndue: 2023-05-10T00:00:00Z\nall_day: true\n"));
+        assert!(text.ends_with("version: 3\n---\n# Pay rent\n\ntransfer\nto landlord\n"));
+        assert_eq!(parse(&text, "ignored", Utc::now()).unwrap(), task);
+    }
+
+    #[test]
+    fn test_parse_by_hand() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let task = parse("---\nstatus: done\ndue: 2023-05-10T09:00:00+02:00\n---\nCall the plumber\n", "plumber", now).unwrap();
+        assert_eq!(task.id, "");
+        assert_eq!(task.name, "plumber");
+        assert_eq!(task.description, "Call the plumber");
+        assert_eq!(task.status, TaskStatus::Done.to_store());
+        assert_eq!(task.due, Some(Utc.with_ymd_and_hms(2023, 5, 10, 7, 0, 0).unwrap()));
+        assert_eq!(task.created_at, now);
+        let task = parse("# Just a heading", "file", now).unwrap();
+        assert_eq!(task.name, "Just a heading");
+        assert!(parse("---\nstatus: doing\n---\n# x\n", "x", now).is_err());
+        assert!(parse("---\nstatus: done\n# x\n", "x", now).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::db::{store::TaskStore, models::{Task, TaskDetails, TaskError, TaskStatus, DuplicateMode}};
+use super::VaultStore;
+
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("doit-vault-{}", Uuid::new_v4().simple()))
+}
+
+#[test]
+fn files_are_the_tasks() {
+    let dir = temp_dir();
+    let store = VaultStore::open(&dir).unwrap();
+    let (task, created) = store.find_or_create("test_vault", Some("notes"), None, TaskDetails::default(), DuplicateMode::Allow).unwrap();
+    assert!(created);
+    let path = dir.join(format!("{}.md", task.id));
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with(&format!("---\nid: {}\nstatus: created\n", task.id)));
+    assert!(text.ends_with("version: 1\n---\n# test_vault\n\nnotes\n"));
+
+    //an edit made outside is the next version, a client still holding 1 is refused
+    fs::write(&path, text.replace("status: created", "status: done").replace("# test_vault", "# test_vault edited")).unwrap();
+    store.reload().unwrap();
+    let edited = store.by_id(&task.id).unwrap();
+    assert_eq!(edited.name, "test_vault edited");
+    assert_eq!(edited.status, TaskStatus::Done.to_store());
+    assert_eq!(edited.version, 2);
+    assert!(fs::read_to_string(&path).unwrap().contains("version: 2\n"));
+    store.reload().unwrap();
+    assert_eq!(store.by_id(&task.id).unwrap().version, 2);
+    assert!(matches!(store.set_status(&task.id, TaskStatus::Created.to_store(), Some(1)), Err(TaskError::Conflict(_))));
+    let reopened = store.set_status(&task.id, TaskStatus::Created.to_store(), Some(2)).unwrap();
+    assert_eq!(reopened.version, 3);
+    assert!(fs::read_to_string(&path).unwrap().contains("status: created\n"));
+
+    let by_hand = dir.join("test_vault groceries.md");
+    fs::write(&by_hand, "Buy milk\n").unwrap();
+    store.reload().unwrap();
+    let text = fs::read_to_string(&by_hand).unwrap();
+    let id = text.lines().find_map(|l| l.strip_prefix("id: ")).unwrap().to_string();
+    let groceries = store.by_id(&id).unwrap();
+    assert_eq!(groceries.name, "test_vault groceries");
+    assert_eq!(groceries.description, "Buy milk");
+
+    fs::create_dir(dir.join("errands")).unwrap();
+    let moved = dir.join("errands").join("groceries.md");
+    fs::rename(&by_hand, &moved).unwrap();
+    store.reload().unwrap();
+    assert_eq!(store.by_id(&id).unwrap().version, 1);
+    let updated = store.snooze(&id, None, Some(1)).unwrap();
+    assert_eq!(updated.version, 2);
+    assert!(fs::read_to_string(&moved).unwrap().contains("version: 2\n"));
+
+    fs::remove_file(&moved).unwrap();
+    store.reload().unwrap();
+    assert!(store.by_id(&id).is_none());
+    assert_eq!(store.delete(&task.id), Ok(1));
+    assert!(!path.exists());
+    assert!(store.by_id(&task.id).is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn answers_like_the_task_methods() {
+    let dir = temp_dir();
+    let store = VaultStore::open(&dir).unwrap();
+    let past = Utc::now() - chrono::Duration::days(2);
+    let later = Utc::now() + chrono::Duration::days(2);
+    let details = TaskDetails { tags: vec!["Home".to_string()], project: Some("House".to_string()), ..TaskDetails::default() };
+    let (overdue, _) = store.find_or_create("test_vault overdue", None, Some(past), details, DuplicateMode::Allow).unwrap();
+    let (open, _) = store.find_or_create("test_vault open", Some("call the bank"), Some(later), TaskDetails::default(), DuplicateMode::Allow).unwrap();
+    let (undated, _) = store.find_or_create("test_vault undated", None, None, TaskDetails::default(), DuplicateMode::Allow).unwrap();
+    assert_eq!(overdue.status, TaskStatus::Overdue.to_store());
+
+    assert_eq!(store.list().iter().map(|t| t.id.clone()).collect::<Vec<String>>(), [overdue.id.clone(), open.id.clone(), undated.id.clone()]);
+    assert_eq!(store.next().unwrap().id, overdue.id);
+    assert_eq!(store.filter(":tag:home", &chrono_tz::UTC).len(), 1);
+    assert_eq!(store.filter(":project:house", &chrono_tz::UTC)[0].id, overdue.id);
+    assert_eq!(store.filter(":status:overdue", &chrono_tz::UTC)[0].id, overdue.id);
+    assert_eq!(store.filter("BANK", &chrono_tz::UTC)[0].id, open.id);
+
+    let rejected = store.find_or_create("test_vault open", None, None, TaskDetails::default(), DuplicateMode::Reject);
+    assert_eq!(rejected, Err(TaskError::Duplicate(Box::new(open.clone()))));
+    let (existing, created) = store.find_or_create("test_vault open", None, None, TaskDetails::default(), DuplicateMode::Existing).unwrap();
+    assert!(!created);
+    assert_eq!(existing, open);
+    assert_eq!(store.by_name("test_vault undated").unwrap().id, undated.id);
+
+    let snoozed = store.snooze(&overdue.id, Some(later), None).unwrap();
+    assert!(store.list().iter().all(|t| t.id != snoozed.id));
+    assert_eq!(store.filter(":snoozed:", &chrono_tz::UTC)[0].id, snoozed.id);
+    assert_eq!(store.next().unwrap().id, open.id);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn watcher_picks_up_edits() {
+    let dir = temp_dir();
+    let store = VaultStore::open(&dir).unwrap();
+    let mut task = Task::new("test_vault watched", None, None);
+    task.id = Uuid::new_v4().hyphenated().to_string();
+    fs::write(dir.join("watched.md"), super::note::render(&task)).unwrap();
+    let mut found = None;
+    for _ in 0..100 {
+        found = store.by_id(&task.id);
+        if found.is_some() {
+            break
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(found.unwrap().name, "test_vault watched");
+    fs::remove_dir_all(&dir).unwrap();
+}